@@ -0,0 +1,47 @@
+//! `dfa!` and `nfa!`: write a machine inline and have it checked at compile time.
+//!
+//! ```ignore
+//! let even = dfa! {
+//!     states: 2,
+//!     start: 0,
+//!     accept: [0],
+//!     alphabet: ['0', '1'],
+//!     transitions: {
+//!         0 => { '0' | '1' => 1 },
+//!         1 => { '0' | '1' => 0 },
+//!     },
+//! };
+//!
+//! let ends_with_11 = nfa! {
+//!     states: 3,
+//!     start: 0,
+//!     accept: [2],
+//!     alphabet: ['0', '1'],
+//!     transitions: {
+//!         0 => { '0' => 0, '1' => [0, 1] },
+//!         1 => { '1' => 2 },
+//!     },
+//! };
+//! ```
+//!
+//! The checks are the ones `DFA::new` and `NFA::new` run, reported at the offending token. An
+//! NFA uses `'~'` for epsilon transitions and may list several targets in brackets; in a DFA,
+//! `'~'` is an ordinary symbol.
+
+mod machine;
+
+use proc_macro::TokenStream;
+
+#[proc_macro]
+pub fn dfa(input: TokenStream) -> TokenStream {
+    machine::expand_dfa(input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+#[proc_macro]
+pub fn nfa(input: TokenStream) -> TokenStream {
+    machine::expand_nfa(input.into())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
@@ -0,0 +1,394 @@
+use std::collections::HashSet;
+
+use proc_macro2::{Span, TokenStream};
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{Error, Ident, LitChar, LitInt, Result, Token, braced, bracketed};
+
+// must match fsim::nfa::EPSILON
+const EPSILON: char = '~';
+
+struct Spanned<T> {
+    value: T,
+    span: Span,
+}
+
+fn parse_state(input: ParseStream) -> Result<Spanned<usize>> {
+    let lit: LitInt = input.parse()?;
+    Ok(Spanned {
+        value: lit.base10_parse()?,
+        span: lit.span(),
+    })
+}
+
+fn parse_symbol(input: ParseStream) -> Result<Spanned<char>> {
+    let lit: LitChar = input.parse()?;
+    Ok(Spanned {
+        value: lit.value(),
+        span: lit.span(),
+    })
+}
+
+fn parse_list<T>(
+    input: ParseStream,
+    item: fn(ParseStream) -> Result<Spanned<T>>,
+) -> Result<Vec<Spanned<T>>> {
+    let content;
+    bracketed!(content in input);
+    let items = content.parse_terminated(item, Token![,])?;
+    Ok(items.into_iter().collect())
+}
+
+struct Edge {
+    from: Spanned<usize>,
+    symbol: Spanned<char>,
+    targets: Vec<Spanned<usize>>,
+    // whether the targets were written as a bracketed list
+    bracketed: bool,
+}
+
+struct Machine {
+    states: Spanned<usize>,
+    start: Spanned<usize>,
+    accept: Vec<Spanned<usize>>,
+    alphabet: Vec<Spanned<char>>,
+    edges: Vec<Edge>,
+}
+
+fn parse_row(input: ParseStream, edges: &mut Vec<Edge>) -> Result<()> {
+    let from = parse_state(input)?;
+    input.parse::<Token![=>]>()?;
+    let content;
+    braced!(content in input);
+    while !content.is_empty() {
+        let mut symbols = vec![parse_symbol(&content)?];
+        while content.parse::<Option<Token![|]>>()?.is_some() {
+            symbols.push(parse_symbol(&content)?);
+        }
+        content.parse::<Token![=>]>()?;
+        let bracketed = content.peek(syn::token::Bracket);
+        let targets = if bracketed {
+            parse_list(&content, parse_state)?
+        } else {
+            vec![parse_state(&content)?]
+        };
+        for symbol in symbols {
+            edges.push(Edge {
+                from: Spanned {
+                    value: from.value,
+                    span: from.span,
+                },
+                symbol,
+                targets: targets
+                    .iter()
+                    .map(|t| Spanned {
+                        value: t.value,
+                        span: t.span,
+                    })
+                    .collect(),
+                bracketed,
+            });
+        }
+        if content.parse::<Option<Token![,]>>()?.is_none() {
+            break;
+        }
+    }
+    if !content.is_empty() {
+        return Err(content.error("expected `,` between transitions"));
+    }
+    Ok(())
+}
+
+impl Parse for Machine {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let mut states = None;
+        let mut start = None;
+        let mut accept = None;
+        let mut alphabet = None;
+        let mut edges = None;
+
+        while !input.is_empty() {
+            let field: Ident = input.parse()?;
+            input.parse::<Token![:]>()?;
+            let duplicate = match field.to_string().as_str() {
+                "states" => states.replace(parse_state(input)?).is_some(),
+                "start" => start.replace(parse_state(input)?).is_some(),
+                "accept" => accept.replace(parse_list(input, parse_state)?).is_some(),
+                "alphabet" => alphabet.replace(parse_list(input, parse_symbol)?).is_some(),
+                "transitions" => {
+                    let content;
+                    braced!(content in input);
+                    let mut rows = Vec::new();
+                    while !content.is_empty() {
+                        parse_row(&content, &mut rows)?;
+                        content.parse::<Option<Token![,]>>()?;
+                    }
+                    edges.replace(rows).is_some()
+                }
+                _ => {
+                    return Err(Error::new(
+                        field.span(),
+                        "expected one of `states`, `start`, `accept`, `alphabet`, `transitions`",
+                    ));
+                }
+            };
+            if duplicate {
+                return Err(Error::new(field.span(), format!("`{field}` given twice")));
+            }
+            if input.parse::<Option<Token![,]>>()?.is_none() {
+                break;
+            }
+        }
+        if !input.is_empty() {
+            return Err(input.error("expected `,` between fields"));
+        }
+
+        let missing = |name: &str| Error::new(Span::call_site(), format!("missing `{name}`"));
+        Ok(Self {
+            states: states.ok_or_else(|| missing("states"))?,
+            start: start.ok_or_else(|| missing("start"))?,
+            accept: accept.unwrap_or_default(),
+            alphabet: alphabet.ok_or_else(|| missing("alphabet"))?,
+            edges: edges.ok_or_else(|| missing("transitions"))?,
+        })
+    }
+}
+
+impl Machine {
+    /// The checks shared by `validate_dfa` and `validate_nfa`.
+    fn validate_common(&self, allow_epsilon: bool) -> Result<()> {
+        let states = self.states.value;
+        if self.start.value >= states {
+            return Err(Error::new(
+                self.start.span,
+                format!(
+                    "start state {} is out of range for {states} states",
+                    self.start.value
+                ),
+            ));
+        }
+        if let Some(s) = self.accept.iter().find(|s| s.value >= states) {
+            return Err(Error::new(
+                s.span,
+                format!(
+                    "accept state {} is out of range for {states} states",
+                    s.value
+                ),
+            ));
+        }
+
+        let mut alphabet = HashSet::new();
+        for c in &self.alphabet {
+            if allow_epsilon && c.value == EPSILON {
+                return Err(Error::new(
+                    c.span,
+                    format!("{EPSILON:?} is reserved for epsilon transitions"),
+                ));
+            }
+            if !alphabet.insert(c.value) {
+                return Err(Error::new(c.span, format!("{:?} listed twice", c.value)));
+            }
+        }
+
+        for e in &self.edges {
+            if e.from.value >= states {
+                return Err(Error::new(
+                    e.from.span,
+                    format!("state {} is out of range for {states} states", e.from.value),
+                ));
+            }
+            let is_epsilon = allow_epsilon && e.symbol.value == EPSILON;
+            if !is_epsilon && !alphabet.contains(&e.symbol.value) {
+                return Err(Error::new(
+                    e.symbol.span,
+                    format!("{:?} is not in the alphabet", e.symbol.value),
+                ));
+            }
+            if let Some(t) = e.targets.iter().find(|t| t.value >= states) {
+                return Err(Error::new(
+                    t.span,
+                    format!("state {} is out of range for {states} states", t.value),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn validate_dfa(&self) -> Result<()> {
+        self.validate_common(false)?;
+        let mut defined = HashSet::new();
+        for e in &self.edges {
+            if e.bracketed || e.targets.len() != 1 {
+                let span = e.targets.first().map_or(e.symbol.span, |t| t.span);
+                return Err(Error::new(span, "a DFA transition has exactly one target"));
+            }
+            if !defined.insert((e.from.value, e.symbol.value)) {
+                return Err(Error::new(
+                    e.symbol.span,
+                    format!(
+                        "transition from {} on {:?} given twice",
+                        e.from.value, e.symbol.value
+                    ),
+                ));
+            }
+        }
+        let missing: Vec<String> = (0..self.states.value)
+            .flat_map(|s| self.alphabet.iter().map(move |c| (s, c.value)))
+            .filter(|k| !defined.contains(k))
+            .map(|(s, c)| format!("({s}, {c:?})"))
+            .collect();
+        if !missing.is_empty() {
+            return Err(Error::new(
+                Span::call_site(),
+                format!(
+                    "transition function is not total, missing {}",
+                    missing.join(", ")
+                ),
+            ));
+        }
+        Ok(())
+    }
+
+    fn parts(&self) -> (usize, usize, Vec<usize>, Vec<char>) {
+        (
+            self.states.value,
+            self.start.value,
+            self.accept.iter().map(|s| s.value).collect(),
+            self.alphabet.iter().map(|c| c.value).collect(),
+        )
+    }
+}
+
+pub(crate) fn expand_dfa(input: TokenStream) -> Result<TokenStream> {
+    let machine: Machine = syn::parse2(input)?;
+    machine.validate_dfa()?;
+    let (states, start, accept, alphabet) = machine.parts();
+    let transitions = machine.edges.iter().map(|e| {
+        let (from, symbol, to) = (e.from.value, e.symbol.value, e.targets[0].value);
+        quote! { ((#from, #symbol), #to) }
+    });
+    Ok(quote! {
+        ::fsim::dfa::DFA::new(
+            #states,
+            #start,
+            ::std::collections::HashSet::from([#(#accept),*]),
+            ::std::collections::HashSet::from([#(#alphabet),*]),
+            ::std::collections::HashMap::from([#(#transitions),*]),
+        )
+        .expect("checked at compile time by dfa!")
+    })
+}
+
+pub(crate) fn expand_nfa(input: TokenStream) -> Result<TokenStream> {
+    let machine: Machine = syn::parse2(input)?;
+    machine.validate_common(true)?;
+    let (states, start, accept, alphabet) = machine.parts();
+
+    // merge rows that mention the same (state, symbol) more than once
+    let mut merged: Vec<((usize, char), Vec<usize>)> = Vec::new();
+    for e in &machine.edges {
+        let key = (e.from.value, e.symbol.value);
+        let targets = e.targets.iter().map(|t| t.value);
+        match merged.iter_mut().find(|(k, _)| *k == key) {
+            Some((_, existing)) => existing.extend(targets),
+            None => merged.push((key, targets.collect())),
+        }
+    }
+    let transitions = merged.iter().map(|((from, symbol), targets)| {
+        quote! { ((#from, #symbol), ::std::collections::HashSet::from([#(#targets),*])) }
+    });
+    Ok(quote! {
+        ::fsim::nfa::NFA::new(
+            #states,
+            #start,
+            ::std::collections::HashSet::from([#(#accept),*]),
+            ::std::collections::HashSet::from([#(#alphabet),*]),
+            ::std::collections::HashMap::from([#(#transitions),*]),
+        )
+        .expect("checked at compile time by nfa!")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn dfa_error(input: TokenStream) -> String {
+        expand_dfa(input).unwrap_err().to_string()
+    }
+
+    #[test]
+    fn valid_dfa_expands() {
+        let out = expand_dfa(quote! {
+            states: 2, start: 0, accept: [0], alphabet: ['0', '1'],
+            transitions: { 0 => { '0' | '1' => 1 }, 1 => { '0' | '1' => 0 } }
+        })
+        .unwrap()
+        .to_string();
+        assert!(out.contains(":: fsim :: dfa :: DFA :: new"));
+    }
+
+    #[test]
+    fn invalid_start_state_fails() {
+        let err = dfa_error(quote! {
+            states: 1, start: 1, alphabet: [], transitions: {}
+        });
+        assert_eq!(err, "start state 1 is out of range for 1 states");
+    }
+
+    #[test]
+    fn non_total_transition_fn_fails() {
+        let err = dfa_error(quote! {
+            states: 2, start: 0, accept: [0], alphabet: ['0', '1'],
+            transitions: { 0 => { '0' | '1' => 1 }, 1 => { '0' => 0 } }
+        });
+        assert_eq!(err, "transition function is not total, missing (1, '1')");
+    }
+
+    #[test]
+    fn symbol_outside_alphabet_fails() {
+        let err = dfa_error(quote! {
+            states: 1, start: 0, alphabet: ['a'], transitions: { 0 => { 'a' => 0, 'b' => 0 } }
+        });
+        assert_eq!(err, "'b' is not in the alphabet");
+    }
+
+    #[test]
+    fn dfa_rejects_multiple_targets_and_epsilon() {
+        let err = dfa_error(quote! {
+            states: 2, start: 0, alphabet: ['a'], transitions: { 0 => { 'a' => [0, 1] } }
+        });
+        assert_eq!(err, "a DFA transition has exactly one target");
+        let err = dfa_error(quote! {
+            states: 1, start: 0, alphabet: ['a'], transitions: { 0 => { 'a' => 0, '~' => 0 } }
+        });
+        assert_eq!(err, "'~' is not in the alphabet");
+    }
+
+    #[test]
+    fn dfa_allows_tilde_as_a_symbol() {
+        assert!(
+            expand_dfa(quote! {
+                states: 1, start: 0, alphabet: ['~'], transitions: { 0 => { '~' => 0 } }
+            })
+            .is_ok()
+        );
+    }
+
+    #[test]
+    fn nfa_allows_epsilon_but_not_in_alphabet() {
+        assert!(
+            expand_nfa(quote! {
+                states: 2, start: 0, accept: [1], alphabet: ['a'],
+                transitions: { 0 => { '~' => 1, 'a' => [0, 1] } }
+            })
+            .is_ok()
+        );
+        let err = expand_nfa(quote! {
+            states: 1, start: 0, alphabet: ['~'], transitions: {}
+        })
+        .unwrap_err()
+        .to_string();
+        assert_eq!(err, "'~' is reserved for epsilon transitions");
+    }
+}
@@ -0,0 +1,46 @@
+use fsim_macros::{dfa, nfa};
+
+#[test]
+fn dfa_macro_builds_working_machine() {
+    let even = dfa! {
+        states: 2,
+        start: 0,
+        accept: [0],
+        alphabet: ['0', '1'],
+        transitions: {
+            0 => { '0' | '1' => 1 },
+            1 => { '0' | '1' => 0 },
+        },
+    };
+    assert!(matches!(
+        even.simulate("0110"),
+        Ok(fsim::dfa::SimulationResult::Accepted)
+    ));
+    assert!(matches!(
+        even.simulate("011"),
+        Ok(fsim::dfa::SimulationResult::Rejected)
+    ));
+}
+
+#[test]
+fn nfa_macro_builds_working_machine() {
+    let ends_with_11 = nfa! {
+        states: 4,
+        start: 3,
+        accept: [2],
+        alphabet: ['0', '1'],
+        transitions: {
+            3 => { '~' => 0 },
+            0 => { '0' => 0, '1' => [0, 1] },
+            1 => { '1' => 2 },
+        },
+    };
+    assert!(matches!(
+        ends_with_11.simulate("0011"),
+        Ok(fsim::nfa::SimulationResult::Accepted)
+    ));
+    assert!(matches!(
+        ends_with_11.simulate("0110"),
+        Ok(fsim::nfa::SimulationResult::Rejected)
+    ));
+}
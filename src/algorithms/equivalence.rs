@@ -0,0 +1,157 @@
+//! Language equivalence of DFAs through their product automaton, with the shortest strings on
+//! which two machines disagree as witnesses. Grading and the exact L* teacher both build on it.
+
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use num_bigint::BigUint;
+
+use crate::algorithms::language::Language;
+use crate::dfa::{DFA, State};
+
+/// A shortest (then shortlex-least) string on which the two DFAs disagree, or `None` if they
+/// accept the same language. Symbols only one of them knows are rejected by the other.
+pub fn counterexample(a: &DFA, b: &DFA) -> Option<String> {
+    let alphabet: BTreeSet<char> = a.alphabet.union(&b.alphabet).copied().collect();
+    let difference = product(&complete(a, &alphabet), &complete(b, &alphabet), |x, y| {
+        x != y
+    });
+    shortest(&difference, 1).pop()
+}
+
+/// `dfa` with every symbol of `alphabet` it lacks sent to a fresh trap state.
+pub(crate) fn complete(dfa: &DFA, alphabet: &BTreeSet<char>) -> DFA {
+    let missing: Vec<char> = alphabet
+        .iter()
+        .copied()
+        .filter(|c| !dfa.alphabet.contains(c))
+        .collect();
+    if missing.is_empty() {
+        return dfa.clone();
+    }
+    let mut states: Vec<State> = dfa.states.iter().copied().collect();
+    states.sort();
+    let index: HashMap<State, State> = states.iter().enumerate().map(|(i, &s)| (s, i)).collect();
+    let trap = states.len();
+    let mut tfn: HashMap<(State, char), State> = dfa
+        .tfn
+        .iter()
+        .map(|(&(s, c), t)| ((index[&s], c), index[t]))
+        .collect();
+    for s in 0..=trap {
+        for &c in alphabet {
+            tfn.entry((s, c)).or_insert(trap);
+        }
+    }
+    DFA::new(
+        trap + 1,
+        index[&dfa.start],
+        dfa.accept.iter().map(|s| index[s]).collect(),
+        alphabet.iter().copied().collect(),
+        tfn,
+    )
+    .expect("completion yields a total DFA")
+}
+
+/// The reachable part of the product of two DFAs over the same alphabet, accepting where
+/// `accept(a accepts, b accepts)` holds.
+pub(crate) fn product(a: &DFA, b: &DFA, accept: impl Fn(bool, bool) -> bool) -> DFA {
+    let mut alphabet: Vec<char> = a.alphabet.iter().copied().collect();
+    alphabet.sort();
+    let start = (a.start, b.start);
+    let mut index: HashMap<(State, State), State> = HashMap::from([(start, 0)]);
+    let mut pairs = vec![start];
+    let mut work_queue = VecDeque::from([start]);
+    let mut tfn = HashMap::new();
+    while let Some((p, q)) = work_queue.pop_front() {
+        let from = index[&(p, q)];
+        for &c in &alphabet {
+            let next = (a.tfn[&(p, c)], b.tfn[&(q, c)]);
+            let to = *index.entry(next).or_insert_with(|| {
+                pairs.push(next);
+                work_queue.push_back(next);
+                pairs.len() - 1
+            });
+            tfn.insert((from, c), to);
+        }
+    }
+    let accept: HashSet<State> = pairs
+        .iter()
+        .enumerate()
+        .filter(|(_, (p, q))| accept(a.accept.contains(p), b.accept.contains(q)))
+        .map(|(i, _)| i)
+        .collect();
+    DFA::new(pairs.len(), 0, accept, alphabet.into_iter().collect(), tfn)
+        .expect("the product of total DFAs is total")
+}
+
+/// The first `k` accepted strings in shortlex order, or all of them if the language has fewer.
+/// The length bound doubles until `k` are found or the language is known to hold nothing
+/// longer: an `n`-state DFA accepting a string longer than `m + n` also accepts one whose length
+/// is in `m + 1..=m + n`, by cutting out a cycle.
+pub(crate) fn shortest(dfa: &DFA, k: usize) -> Vec<String> {
+    if dfa.accept.is_empty() || k == 0 {
+        return Vec::new();
+    }
+    let n = dfa.states.len();
+    let mut language = Language::of_dfa(dfa);
+    let mut max_len = n + k;
+    loop {
+        let words: Vec<String> = language.enumerate(max_len).take(k).collect();
+        let finished =
+            (max_len + 1..=max_len + n).all(|len| language.count_accepted(len) == BigUint::ZERO);
+        if words.len() == k || finished {
+            return words;
+        }
+        max_len *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::subset_construction::determinize;
+    use crate::regex::Regex;
+    use fsim_macros::dfa;
+
+    #[test]
+    fn counterexample_is_shortest() {
+        // strings over {a, b} ending in bb
+        let ends_in_bb = dfa! {
+            states: 3,
+            start: 0,
+            accept: [2],
+            alphabet: ['a', 'b'],
+            transitions: {
+                0 => { 'a' => 0, 'b' => 1 },
+                1 => { 'a' => 0, 'b' => 2 },
+                2 => { 'a' => 0, 'b' => 2 },
+            },
+        };
+        let reference: Regex = "(a|b)*abb".parse().unwrap();
+        let reference = determinize(&reference.to_nfa().unwrap());
+        assert_eq!(
+            counterexample(&reference, &ends_in_bb).as_deref(),
+            Some("bb")
+        );
+        assert_eq!(counterexample(&ends_in_bb, &ends_in_bb.clone()), None);
+    }
+
+    #[test]
+    fn shortest_looks_past_sparse_lengths() {
+        // (aaaa)*: only every fourth length has a string
+        let dfa = dfa! {
+            states: 4,
+            start: 0,
+            accept: [0],
+            alphabet: ['a'],
+            transitions: {
+                0 => { 'a' => 1 },
+                1 => { 'a' => 2 },
+                2 => { 'a' => 3 },
+                3 => { 'a' => 0 },
+            },
+        };
+        assert_eq!(shortest(&dfa, 3), ["", "aaaa", "aaaaaaaa"]);
+        assert_eq!(shortest(&dfa, 0), Vec::<String>::new());
+    }
+}
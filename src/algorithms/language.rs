@@ -0,0 +1,297 @@
+use std::collections::HashMap;
+
+use num_bigint::BigUint;
+
+use crate::algorithms::subset_construction::determinize;
+use crate::dfa::{DFA, State};
+use crate::nfa::NFA;
+use crate::random::SplitMix64;
+
+/// The language of a machine, viewed through per-length path counts. `counts[k][q]` is the exact
+/// number of strings of length `k` accepted from state `q`; it is filled in lazily up to the
+/// longest length asked for. Since a DFA has one run per string, counting paths counts strings,
+/// which is why an NFA is determinized first.
+#[derive(Clone, Debug)]
+pub struct Language {
+    symbols: Vec<char>,
+    start: usize,
+    accept: Vec<bool>,
+    delta: Vec<Vec<Option<usize>>>,
+    counts: Vec<Vec<BigUint>>,
+}
+
+impl Language {
+    pub fn of_dfa(dfa: &DFA) -> Self {
+        let mut symbols: Vec<char> = dfa.alphabet.iter().copied().collect();
+        symbols.sort();
+        let mut states: Vec<State> = dfa.states.iter().copied().collect();
+        states.sort();
+        let index: HashMap<State, usize> =
+            states.iter().enumerate().map(|(i, &s)| (s, i)).collect();
+        let delta = states
+            .iter()
+            .map(|&s| {
+                symbols
+                    .iter()
+                    .map(|&c| dfa.tfn.get(&(s, c)).map(|t| index[t]))
+                    .collect()
+            })
+            .collect();
+        let accept: Vec<bool> = states.iter().map(|s| dfa.accept.contains(s)).collect();
+        let counts = vec![accept.iter().map(|&a| BigUint::from(u8::from(a))).collect()];
+        Self {
+            symbols,
+            start: index[&dfa.start],
+            accept,
+            delta,
+            counts,
+        }
+    }
+
+    pub fn of_nfa(nfa: &NFA) -> Self {
+        Self::of_dfa(&determinize(nfa))
+    }
+
+    fn extend_to(&mut self, n: usize) {
+        while self.counts.len() <= n {
+            let prev = self.counts.last().expect("length 0 is always present");
+            let next = self
+                .delta
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .flatten()
+                        .fold(BigUint::ZERO, |sum, &t| sum + &prev[t])
+                })
+                .collect();
+            self.counts.push(next);
+        }
+    }
+
+    fn count_from(&self, state: usize, len: usize) -> &BigUint {
+        &self.counts[len][state]
+    }
+
+    /// The exact number of accepted strings of length `n`.
+    pub fn count_accepted(&mut self, n: usize) -> BigUint {
+        self.extend_to(n);
+        self.count_from(self.start, n).clone()
+    }
+
+    /// A uniformly random accepted string of length `n`, or `None` if there is none. Each symbol
+    /// is chosen with probability proportional to the number of accepted completions behind it.
+    pub fn sample(&mut self, n: usize, rng: &mut SplitMix64) -> Option<String> {
+        self.extend_to(n);
+        let total = self.count_from(self.start, n);
+        if *total == BigUint::ZERO {
+            return None;
+        }
+        let mut r = random_below(total, rng);
+        let mut state = self.start;
+        let mut out = String::with_capacity(n);
+        for remaining in (0..n).rev() {
+            for (i, next) in self.delta[state].iter().enumerate() {
+                let Some(next) = *next else { continue };
+                let weight = self.count_from(next, remaining);
+                if r < *weight {
+                    out.push(self.symbols[i]);
+                    state = next;
+                    break;
+                }
+                r -= weight;
+            }
+        }
+        debug_assert!(self.accept[state]);
+        Some(out)
+    }
+
+    /// All accepted strings of length at most `max_len`, in shortlex order (by length, then
+    /// lexicographically by symbol). Branches with no accepted completion of the required length
+    /// are never entered, so the work is proportional to the output.
+    pub fn enumerate(&mut self, max_len: usize) -> Enumerate<'_> {
+        self.extend_to(max_len);
+        let mut it = Enumerate {
+            language: self,
+            max_len,
+            len: 0,
+            stack: Vec::new(),
+            word: String::new(),
+        };
+        it.begin_length();
+        it
+    }
+}
+
+/// Iterator returned by [`Language::enumerate`].
+pub struct Enumerate<'a> {
+    language: &'a Language,
+    max_len: usize,
+    len: usize,
+    // (state, index of the next symbol to try) for each prefix of `word`
+    stack: Vec<(usize, usize)>,
+    word: String,
+}
+
+impl Enumerate<'_> {
+    fn begin_length(&mut self) {
+        let start = self.language.start;
+        if *self.language.count_from(start, self.len) != BigUint::ZERO {
+            self.stack.push((start, 0));
+        }
+    }
+
+    fn pop(&mut self) {
+        self.stack.pop();
+        if !self.stack.is_empty() {
+            self.word.pop();
+        }
+    }
+}
+
+impl Iterator for Enumerate<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        loop {
+            let Some(&(state, from)) = self.stack.last() else {
+                if self.len == self.max_len {
+                    return None;
+                }
+                self.len += 1;
+                self.begin_length();
+                continue;
+            };
+            let remaining = self.len + 1 - self.stack.len();
+            if remaining == 0 {
+                let word = self.word.clone();
+                self.pop();
+                return Some(word);
+            }
+            let language = self.language;
+            let found = language.delta[state][from..]
+                .iter()
+                .enumerate()
+                .find_map(|(i, next)| {
+                    next.filter(|&t| *language.count_from(t, remaining - 1) != BigUint::ZERO)
+                        .map(|t| (from + i, t))
+                });
+            match found {
+                Some((i, next)) => {
+                    self.stack.last_mut().unwrap().1 = i + 1;
+                    self.word.push(language.symbols[i]);
+                    self.stack.push((next, 0));
+                }
+                None => self.pop(),
+            }
+        }
+    }
+}
+
+/// Uniform in `0..bound` by rejection sampling on the bit length of `bound`.
+fn random_below(bound: &BigUint, rng: &mut SplitMix64) -> BigUint {
+    let bits = bound.bits() as usize;
+    let num_bytes = bits.div_ceil(8);
+    let mut bytes = vec![0u8; num_bytes];
+    loop {
+        for chunk in bytes.chunks_mut(8) {
+            let word = rng.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&word[..chunk.len()]);
+        }
+        bytes[num_bytes - 1] &= 0xff >> (num_bytes * 8 - bits);
+        let v = BigUint::from_bytes_le(&bytes);
+        if v < *bound {
+            return v;
+        }
+    }
+}
+
+impl DFA {
+    /// The exact number of accepted strings of length `n`.
+    pub fn count_accepted(&self, n: usize) -> BigUint {
+        Language::of_dfa(self).count_accepted(n)
+    }
+}
+
+impl NFA {
+    /// The exact number of accepted strings of length `n`, counted on the determinized machine so
+    /// that strings with several accepting runs are counted once.
+    pub fn count_accepted(&self, n: usize) -> BigUint {
+        Language::of_nfa(self).count_accepted(n)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::SimulationResult;
+    use crate::nfa::EPSILON;
+    use fsim_macros::dfa;
+    use std::collections::HashSet;
+
+    // even number of a's over {a, b}
+    fn even_as() -> DFA {
+        dfa! {
+            states: 2,
+            start: 0,
+            accept: [0],
+            alphabet: ['a', 'b'],
+            transitions: {
+                0 => { 'a' => 1, 'b' => 0 },
+                1 => { 'a' => 0, 'b' => 1 },
+            },
+        }
+    }
+
+    #[test]
+    fn counts_are_exact_beyond_u64() {
+        let dfa = even_as();
+        assert_eq!(dfa.count_accepted(0), BigUint::from(1u8));
+        assert_eq!(dfa.count_accepted(5), BigUint::from(16u8));
+        assert_eq!(dfa.count_accepted(200), BigUint::from(1u8) << 199usize);
+    }
+
+    #[test]
+    fn enumerate_in_shortlex_order() {
+        let mut language = Language::of_dfa(&even_as());
+        let words: Vec<String> = language.enumerate(3).collect();
+        assert_eq!(words, ["", "b", "aa", "bb", "aab", "aba", "baa", "bbb"]);
+        let empty: Vec<String> = Language::of_dfa(&even_as()).enumerate(0).collect();
+        assert_eq!(empty, [""]);
+    }
+
+    #[test]
+    fn samples_are_accepted_and_cover_the_language() {
+        let dfa = even_as();
+        let mut language = Language::of_dfa(&dfa);
+        let mut rng = SplitMix64::new(39);
+        let mut seen = HashSet::new();
+        for _ in 0..400 {
+            let word = language.sample(4, &mut rng).unwrap();
+            assert_eq!(word.len(), 4);
+            assert!(matches!(
+                dfa.simulate(&word),
+                Ok(SimulationResult::Accepted)
+            ));
+            seen.insert(word);
+        }
+        assert_eq!(seen.len(), 8);
+        let long = language.sample(300, &mut rng).unwrap();
+        assert_eq!(long.chars().filter(|&c| c == 'a').count() % 2, 0);
+    }
+
+    #[test]
+    fn nfa_counts_strings_not_runs() {
+        // (a|aa)* with ambiguous runs: 0 -a-> 0, 0 -a-> 1, 1 -a-> 0, 1 -~-> 0
+        let mut tfn = HashMap::new();
+        tfn.insert((0, 'a'), HashSet::from([0, 1]));
+        tfn.insert((1, 'a'), HashSet::from([0]));
+        tfn.insert((1, EPSILON), HashSet::from([0]));
+        let nfa = NFA::new(2, 0, HashSet::from([0]), HashSet::from(['a']), tfn).unwrap();
+        for n in 0..6 {
+            assert_eq!(nfa.count_accepted(n), BigUint::from(1u8));
+        }
+        let mut language = Language::of_nfa(&nfa);
+        assert_eq!(language.enumerate(2).collect::<Vec<_>>(), ["", "a", "aa"]);
+        assert!(language.sample(3, &mut SplitMix64::new(1)).is_some());
+    }
+}
@@ -0,0 +1,610 @@
+//! Lints for machines that are valid but probably not what the author meant. Unlike the checks in
+//! `DFA::new` / `NFA::new` these never reject a machine; they return findings, each naming the
+//! states and edges involved so a front end can highlight them.
+
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use crate::dfa::{DFA, State};
+use crate::nfa::{EPSILON, NFA};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Edge {
+    pub from: State,
+    pub symbol: char,
+    pub to: State,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LintKind {
+    /// No path from the start state reaches the state.
+    UnreachableState,
+    /// No accept state can be reached from the state.
+    DeadState,
+    /// The states accept the same language, so they could be merged.
+    EquivalentStates,
+    /// The symbol never occurs in an accepted string.
+    UnusedSymbol,
+    /// The states reach each other by epsilon moves alone.
+    EpsilonCycle,
+    /// A state has several targets on one symbol, but they all accept the same language.
+    RedundantNondeterminism,
+    /// The NFA is nondeterministic, but an equivalent DFA needs no more states.
+    Determinizable,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Finding {
+    pub kind: LintKind,
+    pub states: Vec<State>,
+    pub edges: Vec<Edge>,
+    /// The symbol concerned, for `UnusedSymbol` and `RedundantNondeterminism`.
+    pub symbol: Option<char>,
+    pub message: String,
+}
+
+impl fmt::Display for Finding {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "warning: {}", self.message)
+    }
+}
+
+/// The parts of a DFA or NFA the lints look at, with DFA transitions as single edges and
+/// epsilon moves as edges labelled `EPSILON`. `epsilon` is false for DFAs, where `EPSILON` is an
+/// ordinary symbol.
+struct View {
+    states: Vec<State>,
+    start: State,
+    accept: HashSet<State>,
+    alphabet: Vec<char>,
+    edges: Vec<Edge>,
+    /// The targets of each `(state, symbol)` pair, built once from `edges`.
+    targets: HashMap<(State, char), Vec<State>>,
+    epsilon: bool,
+}
+
+/// The language-equivalence classes found by `View::equivalence_classes`.
+struct Equivalence {
+    class: HashMap<State, usize>,
+    /// The number of subsets reachable from the start state's closure, which is the number of
+    /// states `determinize` would build.
+    determinized: usize,
+}
+
+impl View {
+    fn of_dfa(dfa: &DFA) -> Self {
+        let edges = dfa
+            .tfn
+            .iter()
+            .map(|(&(from, symbol), &to)| Edge { from, symbol, to })
+            .collect();
+        Self::new(
+            &dfa.states,
+            dfa.start,
+            &dfa.accept,
+            &dfa.alphabet,
+            edges,
+            false,
+        )
+    }
+
+    fn of_nfa(nfa: &NFA) -> Self {
+        let edges = nfa
+            .tfn
+            .iter()
+            .flat_map(|(&(from, symbol), targets)| {
+                targets.iter().map(move |&to| Edge { from, symbol, to })
+            })
+            .collect();
+        Self::new(
+            &nfa.states,
+            nfa.start,
+            &nfa.accept,
+            &nfa.alphabet,
+            edges,
+            true,
+        )
+    }
+
+    fn new(
+        states: &HashSet<State>,
+        start: State,
+        accept: &HashSet<State>,
+        alphabet: &HashSet<char>,
+        mut edges: Vec<Edge>,
+        epsilon: bool,
+    ) -> Self {
+        let mut states: Vec<State> = states.iter().copied().collect();
+        states.sort();
+        let mut alphabet: Vec<char> = alphabet
+            .iter()
+            .copied()
+            .filter(|&c| !epsilon || c != EPSILON)
+            .collect();
+        alphabet.sort();
+        edges.sort();
+        let mut targets: HashMap<(State, char), Vec<State>> = HashMap::new();
+        for e in &edges {
+            targets.entry((e.from, e.symbol)).or_default().push(e.to);
+        }
+        Self {
+            states,
+            start,
+            accept: accept.clone(),
+            alphabet,
+            edges,
+            targets,
+            epsilon,
+        }
+    }
+
+    fn search(&self, from: impl IntoIterator<Item = State>, forward: bool) -> HashSet<State> {
+        let mut adjacent: HashMap<State, Vec<State>> = HashMap::new();
+        for e in &self.edges {
+            let (a, b) = if forward {
+                (e.from, e.to)
+            } else {
+                (e.to, e.from)
+            };
+            adjacent.entry(a).or_default().push(b);
+        }
+        let mut seen: HashSet<State> = HashSet::new();
+        let mut work_queue: VecDeque<State> = VecDeque::new();
+        for s in from {
+            if seen.insert(s) {
+                work_queue.push_back(s);
+            }
+        }
+        while let Some(s) = work_queue.pop_front() {
+            for &t in adjacent.get(&s).into_iter().flatten() {
+                if seen.insert(t) {
+                    work_queue.push_back(t);
+                }
+            }
+        }
+        seen
+    }
+
+    fn step(&self, s: State, c: char) -> impl Iterator<Item = State> + '_ {
+        self.targets.get(&(s, c)).into_iter().flatten().copied()
+    }
+
+    fn epsilon_closure(&self, states: &BTreeSet<State>) -> BTreeSet<State> {
+        let mut closure = states.clone();
+        if !self.epsilon {
+            return closure;
+        }
+        let mut work_queue: Vec<State> = states.iter().copied().collect();
+        while let Some(s) = work_queue.pop() {
+            for t in self.step(s, EPSILON) {
+                if closure.insert(t) {
+                    work_queue.push(t);
+                }
+            }
+        }
+        closure
+    }
+
+    /// Language-equivalence class of each of `states`. The subset construction is run from every
+    /// state's epsilon closure at once, and the resulting DFA is partitioned by Moore refinement;
+    /// two states are equivalent iff their closures land in the same block. `states` must include
+    /// the start state.
+    fn equivalence_classes(&self, states: &[State]) -> Equivalence {
+        let seeds: Vec<BTreeSet<State>> = states
+            .iter()
+            .map(|&s| self.epsilon_closure(&BTreeSet::from([s])))
+            .collect();
+        let mut index: HashMap<BTreeSet<State>, usize> = HashMap::new();
+        let mut subsets: Vec<BTreeSet<State>> = Vec::new();
+        let mut work_queue: VecDeque<usize> = VecDeque::new();
+        let mut intern = |set: BTreeSet<State>,
+                          subsets: &mut Vec<BTreeSet<State>>,
+                          work_queue: &mut VecDeque<usize>| {
+            *index.entry(set.clone()).or_insert_with(|| {
+                subsets.push(set);
+                work_queue.push_back(subsets.len() - 1);
+                subsets.len() - 1
+            })
+        };
+        for seed in &seeds {
+            intern(seed.clone(), &mut subsets, &mut work_queue);
+        }
+        let mut delta: HashMap<(usize, char), usize> = HashMap::new();
+        while let Some(i) = work_queue.pop_front() {
+            for &c in &self.alphabet {
+                let moved: BTreeSet<State> =
+                    subsets[i].iter().flat_map(|&s| self.step(s, c)).collect();
+                let next = self.epsilon_closure(&moved);
+                let j = intern(next, &mut subsets, &mut work_queue);
+                delta.insert((i, c), j);
+            }
+        }
+
+        let mut class: Vec<usize> = subsets
+            .iter()
+            .map(|set| usize::from(set.iter().any(|s| self.accept.contains(s))))
+            .collect();
+        let mut count = 0;
+        loop {
+            let mut ids: HashMap<Vec<usize>, usize> = HashMap::new();
+            let refined: Vec<usize> = (0..subsets.len())
+                .map(|i| {
+                    let mut key = vec![class[i]];
+                    key.extend(self.alphabet.iter().map(|&c| class[delta[&(i, c)]]));
+                    let next = ids.len();
+                    *ids.entry(key).or_insert(next)
+                })
+                .collect();
+            class = refined;
+            if ids.len() == count {
+                break;
+            }
+            count = ids.len();
+        }
+
+        let start = index[&self.epsilon_closure(&BTreeSet::from([self.start]))];
+        let mut seen: HashSet<usize> = HashSet::from([start]);
+        let mut work_queue: VecDeque<usize> = VecDeque::from([start]);
+        while let Some(i) = work_queue.pop_front() {
+            for &c in &self.alphabet {
+                let j = delta[&(i, c)];
+                if seen.insert(j) {
+                    work_queue.push_back(j);
+                }
+            }
+        }
+        Equivalence {
+            class: states
+                .iter()
+                .zip(&seeds)
+                .map(|(&s, seed)| (s, class[index[seed]]))
+                .collect(),
+            determinized: seen.len(),
+        }
+    }
+}
+
+fn state_list(states: &[State]) -> String {
+    states
+        .iter()
+        .map(|s| format!("q{s}"))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// The lints shared by DFAs and NFAs, with the equivalence classes of the reachable states so
+/// `lint_nfa` can reuse them.
+fn common_lints(view: &View, is_dfa: bool) -> (Vec<Finding>, HashSet<State>, Equivalence) {
+    let mut findings = Vec::new();
+    let reachable = view.search([view.start], true);
+    let live = view.search(view.accept.iter().copied(), false);
+
+    for &s in &view.states {
+        if !reachable.contains(&s) {
+            findings.push(Finding {
+                kind: LintKind::UnreachableState,
+                states: vec![s],
+                edges: view.edges.iter().filter(|e| e.from == s).copied().collect(),
+                symbol: None,
+                message: format!("state q{s} is unreachable from the start state"),
+            });
+        }
+    }
+    for &s in &view.states {
+        if reachable.contains(&s) && !live.contains(&s) {
+            let trap = if is_dfa {
+                " (a trap state; a DFA needs at most one)"
+            } else {
+                ""
+            };
+            findings.push(Finding {
+                kind: LintKind::DeadState,
+                states: vec![s],
+                edges: view
+                    .edges
+                    .iter()
+                    .filter(|e| e.to == s && e.from != s && reachable.contains(&e.from))
+                    .copied()
+                    .collect(),
+                symbol: None,
+                message: format!("no accept state can be reached from state q{s}{trap}"),
+            });
+        }
+    }
+
+    let reachable_states: Vec<State> = view
+        .states
+        .iter()
+        .copied()
+        .filter(|s| reachable.contains(s))
+        .collect();
+    let equivalence = view.equivalence_classes(&reachable_states);
+    let mut groups: HashMap<usize, Vec<State>> = HashMap::new();
+    for &s in &reachable_states {
+        groups.entry(equivalence.class[&s]).or_default().push(s);
+    }
+    let mut groups: Vec<Vec<State>> = groups.into_values().filter(|g| g.len() > 1).collect();
+    groups.sort();
+    for group in groups {
+        findings.push(Finding {
+            kind: LintKind::EquivalentStates,
+            message: format!(
+                "states {} accept the same language and could be merged",
+                state_list(&group)
+            ),
+            states: group,
+            edges: Vec::new(),
+            symbol: None,
+        });
+    }
+
+    let useful = |s: &State| reachable.contains(s) && live.contains(s);
+    for &c in &view.alphabet {
+        let used = view
+            .edges
+            .iter()
+            .any(|e| e.symbol == c && useful(&e.from) && useful(&e.to));
+        if !used {
+            findings.push(Finding {
+                kind: LintKind::UnusedSymbol,
+                states: Vec::new(),
+                edges: Vec::new(),
+                symbol: Some(c),
+                message: format!("symbol '{c}' never occurs in an accepted string"),
+            });
+        }
+    }
+    (findings, reachable, equivalence)
+}
+
+pub fn lint_dfa(dfa: &DFA) -> Vec<Finding> {
+    common_lints(&View::of_dfa(dfa), true).0
+}
+
+pub fn lint_nfa(nfa: &NFA) -> Vec<Finding> {
+    let view = View::of_nfa(nfa);
+    let (mut findings, reachable, equivalence) = common_lints(&view, false);
+
+    // epsilon cycles: groups of states that reach each other by epsilon moves alone
+    let closures: HashMap<State, BTreeSet<State>> = view
+        .states
+        .iter()
+        .map(|&s| {
+            let moved = view.step(s, EPSILON).collect();
+            (s, view.epsilon_closure(&moved))
+        })
+        .collect();
+    let mut assigned: HashSet<State> = HashSet::new();
+    for &s in &view.states {
+        if assigned.contains(&s) || !closures[&s].contains(&s) {
+            continue;
+        }
+        let cycle: Vec<State> = closures[&s]
+            .iter()
+            .copied()
+            .filter(|t| closures[t].contains(&s))
+            .collect();
+        assigned.extend(cycle.iter().copied());
+        findings.push(Finding {
+            kind: LintKind::EpsilonCycle,
+            message: format!(
+                "states {} form a cycle of epsilon moves",
+                state_list(&cycle)
+            ),
+            edges: view
+                .edges
+                .iter()
+                .filter(|e| e.symbol == EPSILON && cycle.contains(&e.from) && cycle.contains(&e.to))
+                .copied()
+                .collect(),
+            states: cycle,
+            symbol: None,
+        });
+    }
+
+    // branch points whose targets are all equivalent
+    let mut branches: Vec<(&(State, char), &HashSet<State>)> = nfa
+        .tfn
+        .iter()
+        .filter(|((_, c), targets)| *c != EPSILON && targets.len() > 1)
+        .collect();
+    branches.sort_by_key(|(key, _)| **key);
+    let classes = &equivalence.class;
+    for &(&(from, symbol), targets) in &branches {
+        if !reachable.contains(&from) {
+            continue;
+        }
+        let mut targets: Vec<State> = targets.iter().copied().collect();
+        targets.sort();
+        if targets.iter().all(|t| classes[t] == classes[&targets[0]]) {
+            findings.push(Finding {
+                kind: LintKind::RedundantNondeterminism,
+                message: format!(
+                    "state q{from} has equivalent targets {} on '{symbol}'; one would do",
+                    state_list(&targets)
+                ),
+                edges: targets
+                    .iter()
+                    .map(|&to| Edge { from, symbol, to })
+                    .collect(),
+                states: targets,
+                symbol: Some(symbol),
+            });
+        }
+    }
+
+    // the whole machine is nondeterministic, yet no bigger as a DFA
+    let nondeterministic: Vec<Edge> = view
+        .edges
+        .iter()
+        .filter(|e| {
+            e.symbol == EPSILON
+                || nfa
+                    .tfn
+                    .get(&(e.from, e.symbol))
+                    .is_some_and(|t| t.len() > 1)
+        })
+        .copied()
+        .collect();
+    if !nondeterministic.is_empty() {
+        let dfa_states = equivalence.determinized;
+        if dfa_states <= view.states.len() {
+            let mut states: Vec<State> = nondeterministic.iter().map(|e| e.from).collect();
+            states.dedup();
+            findings.push(Finding {
+                kind: LintKind::Determinizable,
+                states,
+                edges: nondeterministic,
+                symbol: None,
+                message: format!(
+                    "the NFA has {} states but an equivalent DFA needs only {dfa_states}; \
+                    its nondeterminism buys nothing",
+                    view.states.len()
+                ),
+            });
+        }
+    }
+    findings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(findings: &[Finding]) -> Vec<LintKind> {
+        findings.iter().map(|f| f.kind).collect()
+    }
+
+    #[test]
+    fn clean_dfa_has_no_findings() {
+        // even number of 1s
+        let mut tfn = HashMap::new();
+        tfn.insert((0, '0'), 0);
+        tfn.insert((0, '1'), 1);
+        tfn.insert((1, '0'), 1);
+        tfn.insert((1, '1'), 0);
+        let dfa = DFA::new(2, 0, HashSet::from([0]), HashSet::from(['0', '1']), tfn).unwrap();
+        assert_eq!(lint_dfa(&dfa), []);
+    }
+
+    #[test]
+    fn dfa_unreachable_dead_equivalent_and_unused() {
+        // 0 -a-> 1 (accept) -a-> 2 (accept) -a-> 2; b always goes to the trap 3; 4 unreachable
+        let mut tfn = HashMap::new();
+        for s in 0..5 {
+            tfn.insert((s, 'b'), 3);
+        }
+        tfn.insert((0, 'a'), 1);
+        tfn.insert((1, 'a'), 2);
+        tfn.insert((2, 'a'), 2);
+        tfn.insert((3, 'a'), 3);
+        tfn.insert((4, 'a'), 0);
+        let dfa = DFA::new(5, 0, HashSet::from([1, 2]), HashSet::from(['a', 'b']), tfn).unwrap();
+        let findings = lint_dfa(&dfa);
+        assert_eq!(
+            kinds(&findings),
+            [
+                LintKind::UnreachableState,
+                LintKind::DeadState,
+                LintKind::EquivalentStates,
+                LintKind::UnusedSymbol,
+            ]
+        );
+        assert_eq!(findings[0].states, [4]);
+        assert_eq!(findings[0].edges.len(), 2);
+        assert_eq!(findings[1].states, [3]);
+        assert_eq!(findings[2].states, [1, 2]);
+        assert_eq!(findings[3].symbol, Some('b'));
+        assert_eq!(
+            findings[2].to_string(),
+            "warning: states q1, q2 accept the same language and could be merged"
+        );
+    }
+
+    #[test]
+    fn dfa_tilde_is_an_ordinary_symbol() {
+        // strings over {a, ~} containing a '~'
+        let mut tfn = HashMap::new();
+        tfn.insert((0, 'a'), 0);
+        tfn.insert((0, EPSILON), 1);
+        tfn.insert((1, 'a'), 1);
+        tfn.insert((1, EPSILON), 1);
+        let dfa = DFA::new(2, 0, HashSet::from([1]), HashSet::from(['a', EPSILON]), tfn).unwrap();
+        assert_eq!(lint_dfa(&dfa), []);
+    }
+
+    #[test]
+    fn nfa_epsilon_cycle_and_redundant_branch() {
+        // 0 -~-> 1 -~-> 0, 1 -a-> {2, 3}, 2 and 3 accepting sinks
+        let mut tfn = HashMap::new();
+        tfn.insert((0, EPSILON), HashSet::from([1]));
+        tfn.insert((1, EPSILON), HashSet::from([0]));
+        tfn.insert((1, 'a'), HashSet::from([2, 3]));
+        let nfa = NFA::new(4, 0, HashSet::from([2, 3]), HashSet::from(['a']), tfn).unwrap();
+        let findings = lint_nfa(&nfa);
+        assert_eq!(
+            kinds(&findings),
+            [
+                LintKind::EquivalentStates,
+                LintKind::EquivalentStates,
+                LintKind::EpsilonCycle,
+                LintKind::RedundantNondeterminism,
+                LintKind::Determinizable,
+            ]
+        );
+        assert_eq!(findings[0].states, [0, 1]);
+        assert_eq!(findings[1].states, [2, 3]);
+        assert_eq!(findings[2].states, [0, 1]);
+        assert_eq!(
+            findings[2].edges,
+            [
+                Edge {
+                    from: 0,
+                    symbol: EPSILON,
+                    to: 1
+                },
+                Edge {
+                    from: 1,
+                    symbol: EPSILON,
+                    to: 0
+                },
+            ]
+        );
+        assert_eq!(findings[3].edges.len(), 2);
+        assert_eq!(findings[3].symbol, Some('a'));
+    }
+
+    #[test]
+    fn useful_nondeterminism_is_not_flagged() {
+        // strings over {0, 1} whose second-to-last symbol is 1: 3 NFA states, 4 DFA states
+        let mut tfn = HashMap::new();
+        tfn.insert((0, '0'), HashSet::from([0]));
+        tfn.insert((0, '1'), HashSet::from([0, 1]));
+        tfn.insert((1, '0'), HashSet::from([2]));
+        tfn.insert((1, '1'), HashSet::from([2]));
+        let nfa = NFA::new(3, 0, HashSet::from([2]), HashSet::from(['0', '1']), tfn).unwrap();
+        assert_eq!(lint_nfa(&nfa), []);
+    }
+
+    #[test]
+    fn subset_count_matches_determinize() {
+        // seeds from states 0 and 1 add subsets the start state never reaches
+        let mut tfn = HashMap::new();
+        tfn.insert((2, 'a'), HashSet::from([0, 2]));
+        tfn.insert((2, EPSILON), HashSet::from([1]));
+        tfn.insert((0, 'b'), HashSet::from([1]));
+        tfn.insert((1, 'a'), HashSet::from([0]));
+        let nfa = NFA::new(3, 2, HashSet::from([1]), HashSet::from(['a', 'b']), tfn).unwrap();
+        let view = View::of_nfa(&nfa);
+        let equivalence = view.equivalence_classes(&view.states);
+        assert_eq!(
+            equivalence.determinized,
+            crate::algorithms::subset_construction::determinize(&nfa)
+                .states
+                .len()
+        );
+    }
+}
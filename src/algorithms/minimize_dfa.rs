@@ -1,9 +1,61 @@
+use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet, VecDeque};
 
 use crate::dfa::{DFA, State};
 
+type Pair = (State, State);
+
+/// A pair of states marked distinguishable by the table-filling algorithm. Initial marks (one
+/// state accepting, the other not) have no `symbol` or `because`; every later mark records the
+/// symbol on which the pair moves to the already-marked pair `because`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Mark {
+    pub pair: (State, State),
+    pub symbol: Option<char>,
+    pub because: Option<(State, State)>,
+}
+
+/// A pair of distinguishable states and a shortest suffix accepted from exactly one of them.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Distinction {
+    pub pair: (State, State),
+    pub suffix: String,
+}
+
+/// `removed` was found equivalent to `kept` and merged into it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Merge {
+    pub kept: State,
+    pub removed: State,
+}
+
+/// Every step of a minimization, in the order it happened, for replaying in a UI. States are
+/// those of the input DFA except in `renumbering`, which maps them to the states of the result.
+#[derive(Clone, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MinimizationTrace {
+    pub unreachable: Vec<State>,
+    pub initial_marks: Vec<Mark>,
+    pub marks: Vec<Mark>,
+    pub classes: Vec<Vec<State>>,
+    pub distinctions: Vec<Distinction>,
+    pub merges: Vec<Merge>,
+    /// `renumbering[new] = old`, with new states numbered in breadth-first order from the start.
+    pub renumbering: Vec<State>,
+}
+
 pub fn minimize_dfa(dfa: &DFA) -> DFA {
-    let mut minimized_dfa = dfa.clone();
+    minimize_dfa_recorded(dfa).0
+}
+
+/// `minimize_dfa`, also returning a trace of every step.
+pub fn minimize_dfa_recorded(dfa: &DFA) -> (DFA, MinimizationTrace) {
+    let mut trace = MinimizationTrace::default();
+    let mut alphabet: Vec<char> = dfa.alphabet.iter().copied().collect();
+    alphabet.sort();
     // minimization using the table filling algorithm based on the myhill nerode theorem
     // remove unreachable states
     let mut reachable = vec![false; dfa.states.len()];
@@ -11,9 +63,8 @@ pub fn minimize_dfa(dfa: &DFA) -> DFA {
 
     reachable[dfa.start] = true;
 
-    while !work_queue.is_empty() {
-        let curr = work_queue.pop_front().unwrap();
-        for &sym in &dfa.alphabet {
+    while let Some(curr) = work_queue.pop_front() {
+        for &sym in &alphabet {
             let &next = dfa.tfn.get(&(curr, sym)).unwrap();
             if !reachable[next] {
                 work_queue.push_back(next);
@@ -22,33 +73,43 @@ pub fn minimize_dfa(dfa: &DFA) -> DFA {
         }
     }
 
-    let mut m_states: HashSet<usize> = dfa.states.clone();
-    m_states.retain(|&s| reachable[s]);
+    let mut m_states: Vec<State> = dfa
+        .states
+        .iter()
+        .copied()
+        .filter(|&s| reachable[s])
+        .collect();
+    m_states.sort();
+    trace.unreachable = (0..dfa.states.len()).filter(|&s| !reachable[s]).collect();
     let mut m_tfn = dfa.tfn.clone();
-    m_tfn.retain(|k, _| m_states.contains(&k.0));
+    m_tfn.retain(|k, _| reachable[k.0]);
     let mut m_accept = dfa.accept.clone();
     m_accept.retain(|&s| reachable[s]);
-    let m_alphabet = dfa.alphabet.clone();
     let mut m_start = dfa.start;
 
     // precompute inverse transition map: for each ((q0, q1), a) map it to {(qi, qj) | tfn(qi, a) = q0 and tfn(qj, a) = q1}
-    let mut inverse_transition_map: HashMap<(State, State), HashSet<(State, State)>> =
-        HashMap::new();
-    for (&(p, a), &r) in &m_tfn {
-        for (&(q, b), &s) in &m_tfn {
-            if a == b && p < q {
+    let mut inverse_transition_map: HashMap<Pair, Vec<(Pair, char)>> = HashMap::new();
+    for &p in &m_states {
+        for &q in &m_states {
+            if p >= q {
+                continue;
+            }
+            for &a in &alphabet {
+                let (r, s) = (m_tfn[&(p, a)], m_tfn[&(q, a)]);
                 let canonical = if r < s { (r, s) } else { (s, r) };
                 inverse_transition_map
                     .entry(canonical)
                     .or_default()
-                    .insert((p, q));
+                    .push(((p, q), a));
             }
         }
     }
 
-    // mark distinguishable states
+    // mark distinguishable states, remembering a shortest distinguishing suffix for each pair;
+    // the queue is processed breadth first so the first mark of a pair uses a shortest suffix
     let mut work_queue: VecDeque<(State, State)> = VecDeque::new();
     let mut distinguishable: HashMap<(State, State), bool> = HashMap::new();
+    let mut suffix: HashMap<(State, State), String> = HashMap::new();
 
     for &s1 in &m_states {
         for &s2 in &m_states {
@@ -58,32 +119,54 @@ pub fn minimize_dfa(dfa: &DFA) -> DFA {
             if m_accept.contains(&s1) != m_accept.contains(&s2) {
                 distinguishable.insert((s1, s2), true);
                 work_queue.push_back((s1, s2));
+                suffix.insert((s1, s2), String::new());
+                trace.initial_marks.push(Mark {
+                    pair: (s1, s2),
+                    symbol: None,
+                    because: None,
+                });
             } else {
                 distinguishable.insert((s1, s2), false);
             }
         }
     }
 
-    while !work_queue.is_empty() {
-        let top = work_queue.pop_front().unwrap();
+    while let Some(top) = work_queue.pop_front() {
         if let Some(incoming) = inverse_transition_map.get(&top) {
-            for &(s1, s2) in incoming {
-                if let Some(v) = distinguishable.get_mut(&(s1, s2)) {
-                    if !*v {
-                        *v = true;
-                        work_queue.push_back((s1, s2));
-                    }
+            for &((s1, s2), a) in incoming {
+                if let Some(v) = distinguishable.get_mut(&(s1, s2))
+                    && !*v
+                {
+                    *v = true;
+                    work_queue.push_back((s1, s2));
+                    suffix.insert((s1, s2), format!("{a}{}", suffix[&top]));
+                    trace.marks.push(Mark {
+                        pair: (s1, s2),
+                        symbol: Some(a),
+                        because: Some(top),
+                    });
                 }
             }
         }
     }
+    let mut distinctions: Vec<Distinction> = suffix
+        .into_iter()
+        .map(|(pair, suffix)| Distinction { pair, suffix })
+        .collect();
+    distinctions.sort_by_key(|d| d.pair);
+    trace.distinctions = distinctions;
+
     // merge indistinguishable states, update tfn and accept and whatever else
-    distinguishable.retain(|_, v| !*v); //todo: update var name, this is now tracking indistinguishable states
-    let mut indistinguishable = distinguishable.iter().collect::<Vec<_>>();
-    indistinguishable.sort_by_key(|(s1, _)| s1.0);
+    let mut indistinguishable: Vec<(State, State)> = distinguishable
+        .iter()
+        .filter(|(_, v)| !**v)
+        .map(|(&pair, _)| pair)
+        .collect();
+    indistinguishable.sort();
 
     let mut removed = vec![false; dfa.states.len()];
-    for (&(s1, s2), _) in indistinguishable {
+    let mut class_of: HashMap<State, State> = m_states.iter().map(|&s| (s, s)).collect();
+    for (s1, s2) in indistinguishable {
         if removed[s2] {
             continue; // early exit
         }
@@ -92,7 +175,6 @@ pub fn minimize_dfa(dfa: &DFA) -> DFA {
             m_start = s1;
         }
         m_accept.remove(&s2);
-        m_states.remove(&s2);
         m_tfn.retain(|k, _| k.0 != s2);
         for (_, dst) in m_tfn.iter_mut() {
             if *dst == s2 {
@@ -100,17 +182,49 @@ pub fn minimize_dfa(dfa: &DFA) -> DFA {
             }
         }
         removed[s2] = true;
+        class_of.insert(s2, s1);
+        trace.merges.push(Merge {
+            kept: s1,
+            removed: s2,
+        });
     }
+    let mut classes: HashMap<State, Vec<State>> = HashMap::new();
+    for &s in &m_states {
+        classes.entry(class_of[&s]).or_default().push(s);
+    }
+    let mut classes: Vec<Vec<State>> = classes.into_values().collect();
+    classes.sort();
+    trace.classes = classes;
 
-    minimized_dfa.states = m_states;
-    minimized_dfa.start = m_start;
-    minimized_dfa.accept = m_accept;
-    minimized_dfa.alphabet = m_alphabet;
-    minimized_dfa.tfn = m_tfn;
-
-    println!("{}", minimized_dfa);
+    // renumber canonically: breadth first from the start, symbols in order
+    let mut new_id: HashMap<State, State> = HashMap::from([(m_start, 0)]);
+    trace.renumbering.push(m_start);
+    let mut work_queue = VecDeque::from([m_start]);
+    while let Some(curr) = work_queue.pop_front() {
+        for &sym in &alphabet {
+            let next = m_tfn[&(curr, sym)];
+            if let Entry::Vacant(entry) = new_id.entry(next) {
+                entry.insert(trace.renumbering.len());
+                trace.renumbering.push(next);
+                work_queue.push_back(next);
+            }
+        }
+    }
+    let tfn: HashMap<(State, char), State> = m_tfn
+        .iter()
+        .map(|(&(s, a), t)| ((new_id[&s], a), new_id[t]))
+        .collect();
+    let accept: HashSet<State> = m_accept.iter().map(|s| new_id[s]).collect();
 
-    minimized_dfa
+    let minimized_dfa = DFA::new(
+        trace.renumbering.len(),
+        0,
+        accept,
+        dfa.alphabet.clone(),
+        tfn,
+    )
+    .expect("merging equivalent states keeps the DFA total");
+    (minimized_dfa, trace)
 }
 
 #[cfg(test)]
@@ -129,7 +243,10 @@ mod tests {
         tfn.insert((1, '0'), 0);
         tfn.insert((1, '1'), 0);
         let dfa = DFA::new(2, 0, HashSet::from([0]), HashSet::from(['0', '1']), tfn).unwrap();
-        minimize_dfa(&dfa); // expect (0, 1) printed as distinguishable
+        let (minimized, trace) = minimize_dfa_recorded(&dfa);
+        assert_eq!(minimized.states.len(), 2);
+        assert_eq!(trace.distinctions[0].pair, (0, 1));
+        assert_eq!(trace.distinctions[0].suffix, "");
     }
 
     #[test]
@@ -145,7 +262,17 @@ mod tests {
         tfn.insert((2, '0'), 0);
         tfn.insert((2, '1'), 0);
         let dfa = DFA::new(3, 0, HashSet::from([0]), HashSet::from(['0', '1']), tfn).unwrap();
-        minimize_dfa(&dfa); // expect (0,1) and (0,2) distinguishable, (1,2) not printed
+        let (minimized, trace) = minimize_dfa_recorded(&dfa);
+        assert_eq!(minimized.states.len(), 2);
+        let pairs: Vec<_> = trace.distinctions.iter().map(|d| d.pair).collect();
+        assert_eq!(pairs, [(0, 1), (0, 2)]);
+        assert_eq!(
+            trace.merges,
+            [Merge {
+                kept: 1,
+                removed: 2
+            }]
+        );
     }
 
     #[test]
@@ -163,4 +290,67 @@ mod tests {
         let minimized = minimize_dfa(&dfa);
         assert_eq!(minimized.states.len(), 3); // all states are distinguishable, no merging should occur
     }
+
+    #[test]
+    fn recorded_marks_suffixes_and_renumbering() {
+        // same machine as above: the pair (1, 2) is marked on 'a' because of (0, 2)
+        let mut tfn = HashMap::new();
+        tfn.insert((0, 'a'), 0);
+        tfn.insert((1, 'a'), 2);
+        tfn.insert((2, 'a'), 0);
+        let dfa = DFA::new(3, 1, HashSet::from([0]), HashSet::from(['a']), tfn).unwrap();
+        let (minimized, trace) = minimize_dfa_recorded(&dfa);
+        assert_eq!(trace.initial_marks.len(), 2);
+        assert_eq!(
+            trace.marks,
+            [Mark {
+                pair: (1, 2),
+                symbol: Some('a'),
+                because: Some((0, 2)),
+            }]
+        );
+        let suffixes: Vec<&str> = trace
+            .distinctions
+            .iter()
+            .map(|d| d.suffix.as_str())
+            .collect();
+        assert_eq!(suffixes, ["", "", "a"]);
+        assert_eq!(trace.classes, [vec![0], vec![1], vec![2]]);
+        // breadth first from the start state 1
+        assert_eq!(trace.renumbering, [1, 2, 0]);
+        assert_eq!(minimized.start, 0);
+        assert_eq!(minimized.accept, HashSet::from([2]));
+    }
+
+    #[test]
+    fn minimizing_twice_changes_nothing() {
+        // unreachable state 3 and equivalent states 1 and 2
+        let mut tfn = HashMap::new();
+        tfn.insert((0, '0'), 1);
+        tfn.insert((0, '1'), 2);
+        for s in 1..4 {
+            tfn.insert((s, '0'), 0);
+            tfn.insert((s, '1'), 0);
+        }
+        let dfa = DFA::new(4, 0, HashSet::from([0]), HashSet::from(['0', '1']), tfn).unwrap();
+        let (once, trace) = minimize_dfa_recorded(&dfa);
+        assert_eq!(trace.unreachable, [3]);
+        let twice = minimize_dfa(&once);
+        assert_eq!(once.states, HashSet::from([0, 1]));
+        assert_eq!(once.tfn, twice.tfn);
+        assert_eq!(once.accept, twice.accept);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn trace_round_trips_through_json() {
+        let mut tfn = HashMap::new();
+        tfn.insert((0, 'a'), 1);
+        tfn.insert((1, 'a'), 0);
+        let dfa = DFA::new(2, 0, HashSet::from([1]), HashSet::from(['a']), tfn).unwrap();
+        let (_, trace) = minimize_dfa_recorded(&dfa);
+        let json = serde_json::to_string(&trace).unwrap();
+        let back: MinimizationTrace = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, trace);
+    }
 }
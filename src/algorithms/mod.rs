@@ -1 +1,7 @@
+pub mod equivalence;
+pub mod language;
+pub mod lint;
 pub mod minimize_dfa;
+pub mod myhill_nerode;
+pub mod subset_construction;
+pub mod transducers;
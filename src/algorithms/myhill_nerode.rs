@@ -0,0 +1,187 @@
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+
+use crate::algorithms::minimize_dfa::minimize_dfa_recorded;
+use crate::dfa::{DFA, State};
+
+/// A Myhill–Nerode class of the language, i.e. a state of the minimal DFA.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NerodeClass {
+    /// The shortlex-least prefix in the class.
+    pub representative: String,
+    pub accepting: bool,
+    /// The reachable states of the original DFA that fall into this class.
+    pub members: Vec<State>,
+}
+
+/// The Myhill–Nerode view of a DFA's language. Classes are numbered like the states of
+/// `minimize_dfa`'s result, and `suffixes[i][j]` is a shortest suffix accepted after exactly one
+/// of the representatives of classes `i` and `j` (empty on the diagonal). The representatives
+/// are therefore pairwise distinguishable, which proves that no DFA for the language has fewer
+/// than `classes.len()` states.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NerodeView {
+    pub classes: Vec<NerodeClass>,
+    pub suffixes: Vec<Vec<String>>,
+}
+
+impl NerodeView {
+    /// The class of a state of the original DFA, or `None` if it is unreachable.
+    pub fn class_of(&self, state: State) -> Option<usize> {
+        self.classes
+            .iter()
+            .position(|class| class.members.contains(&state))
+    }
+
+    /// Why two states of the original DFA were kept apart: a shortest suffix accepted from
+    /// exactly one of them. `None` if they are equivalent or either is unreachable.
+    pub fn distinguishing_suffix(&self, p: State, q: State) -> Option<&str> {
+        let (i, j) = (self.class_of(p)?, self.class_of(q)?);
+        (i != j).then(|| self.suffixes[i][j].as_str())
+    }
+}
+
+pub fn myhill_nerode(dfa: &DFA) -> NerodeView {
+    let (minimal, trace) = minimize_dfa_recorded(dfa);
+    let n = minimal.states.len();
+    let mut alphabet: Vec<char> = minimal.alphabet.iter().copied().collect();
+    alphabet.sort();
+
+    // breadth first over sorted symbols reaches each state first by its shortlex-least prefix
+    let mut representative: Vec<Option<String>> = vec![None; n];
+    representative[minimal.start] = Some(String::new());
+    let mut work_queue = VecDeque::from([minimal.start]);
+    while let Some(s) = work_queue.pop_front() {
+        for &c in &alphabet {
+            let t = minimal.tfn[&(s, c)];
+            if representative[t].is_none() {
+                representative[t] = Some(format!("{}{c}", representative[s].as_ref().unwrap()));
+                work_queue.push_back(t);
+            }
+        }
+    }
+
+    // `renumbering` maps each minimal state to the member kept by the merges
+    let new_id: HashMap<State, State> = trace
+        .renumbering
+        .iter()
+        .enumerate()
+        .map(|(new, &old)| (old, new))
+        .collect();
+    let mut members: Vec<Vec<State>> = vec![Vec::new(); n];
+    for class in &trace.classes {
+        let kept = class.iter().copied().find(|s| new_id.contains_key(s));
+        members[new_id[&kept.expect("each class keeps one state")]] = class.clone();
+    }
+
+    // all states of the minimal DFA are distinguishable, so table filling on it yields a
+    // shortest suffix for every pair
+    let (_, pairs) = minimize_dfa_recorded(&minimal);
+    let mut suffixes = vec![vec![String::new(); n]; n];
+    for d in pairs.distinctions {
+        let (i, j) = d.pair;
+        suffixes[j][i] = d.suffix.clone();
+        suffixes[i][j] = d.suffix;
+    }
+
+    let classes = (0..n)
+        .map(|s| NerodeClass {
+            representative: representative[s]
+                .take()
+                .expect("minimal states are reachable"),
+            accepting: minimal.accept.contains(&s),
+            members: std::mem::take(&mut members[s]),
+        })
+        .collect();
+    NerodeView { classes, suffixes }
+}
+
+impl fmt::Display for NerodeView {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (i, class) in self.classes.iter().enumerate() {
+            let members: Vec<String> = class.members.iter().map(|s| format!("q{s}")).collect();
+            writeln!(
+                f,
+                "[{:?}]{}: {}",
+                class.representative,
+                if class.accepting { " (accepting)" } else { "" },
+                members.join(", ")
+            )?;
+            for (j, other) in self.classes.iter().enumerate().skip(i + 1) {
+                writeln!(
+                    f,
+                    "  vs [{:?}]: {:?}",
+                    other.representative, self.suffixes[i][j]
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fsim_macros::dfa;
+
+    // strings over {a, b} ending in "ab", with a redundant copy (3) of the start state and an
+    // unreachable state 4
+    fn ends_in_ab() -> DFA {
+        dfa! {
+            states: 5,
+            start: 0,
+            accept: [2],
+            alphabet: ['a', 'b'],
+            transitions: {
+                0 => { 'a' => 1, 'b' => 3 },
+                1 => { 'a' => 1, 'b' => 2 },
+                2 => { 'a' => 1, 'b' => 3 },
+                3 => { 'a' => 1, 'b' => 0 },
+                4 => { 'a' | 'b' => 4 },
+            },
+        }
+    }
+
+    #[test]
+    fn classes_have_shortest_representatives() {
+        let view = myhill_nerode(&ends_in_ab());
+        let reps: Vec<&str> = view
+            .classes
+            .iter()
+            .map(|c| c.representative.as_str())
+            .collect();
+        assert_eq!(reps, ["", "a", "ab"]);
+        assert_eq!(view.classes[0].members, [0, 3]);
+        assert!(view.classes[2].accepting);
+        assert_eq!(view.class_of(4), None);
+    }
+
+    #[test]
+    fn suffixes_separate_every_pair() {
+        let dfa = ends_in_ab();
+        let view = myhill_nerode(&dfa);
+        let accepts =
+            |w: &str| matches!(dfa.simulate(w), Ok(crate::dfa::SimulationResult::Accepted));
+        assert_eq!(view.suffixes[0][1], "b");
+        assert_eq!(view.suffixes[1][2], "");
+        for i in 0..3 {
+            for j in 0..3 {
+                let suffix = &view.suffixes[i][j];
+                let wi = format!("{}{suffix}", view.classes[i].representative);
+                let wj = format!("{}{suffix}", view.classes[j].representative);
+                assert_eq!(accepts(&wi) != accepts(&wj), i != j);
+            }
+        }
+        assert_eq!(view.distinguishing_suffix(3, 1), Some("b"));
+        assert_eq!(view.distinguishing_suffix(0, 3), None);
+    }
+
+    #[test]
+    fn renders_classes_and_suffixes() {
+        let text = myhill_nerode(&ends_in_ab()).to_string();
+        assert!(text.starts_with("[\"\"]: q0, q3\n  vs [\"a\"]: \"b\"\n"));
+        assert!(text.ends_with("[\"ab\"] (accepting): q2\n"));
+    }
+}
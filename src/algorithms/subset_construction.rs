@@ -0,0 +1,186 @@
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+
+use crate::dfa::{DFA, State};
+use crate::nfa::{EPSILON, NFA};
+
+/// The move of one subset on one symbol: the states reached directly, their epsilon closure,
+/// and the DFA state that closure is, which is `new` if this is where it was discovered.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubsetMove {
+    pub symbol: char,
+    pub moved: Vec<State>,
+    pub closure: Vec<State>,
+    pub target: State,
+    pub new: bool,
+}
+
+/// One DFA state of the subset construction, in the order the states were processed.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubsetStep {
+    pub state: State,
+    /// The NFA states it stands for, an epsilon-closed set.
+    pub subset: Vec<State>,
+    pub accepting: bool,
+    pub moves: Vec<SubsetMove>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SubsetTrace {
+    /// The NFA start state, whose epsilon closure is the subset of DFA state 0.
+    pub nfa_start: State,
+    pub alphabet: Vec<char>,
+    pub steps: Vec<SubsetStep>,
+}
+
+fn sorted(states: &HashSet<State>) -> Vec<State> {
+    let mut states: Vec<State> = states.iter().copied().collect();
+    states.sort();
+    states
+}
+
+/// Determinizes an NFA by the subset construction. Only subsets reachable from the epsilon
+/// closure of the start state are built, in breadth-first order over the sorted alphabet, so
+/// the start subset is state 0 and the numbering is deterministic. The empty subset, if
+/// reachable, becomes an ordinary dead state so the result is total.
+pub fn determinize(nfa: &NFA) -> DFA {
+    determinize_recorded(nfa).0
+}
+
+/// `determinize`, also returning a trace of every subset and move for teaching.
+pub fn determinize_recorded(nfa: &NFA) -> (DFA, SubsetTrace) {
+    let mut alphabet: Vec<char> = nfa
+        .alphabet
+        .iter()
+        .copied()
+        .filter(|&c| c != EPSILON)
+        .collect();
+    alphabet.sort();
+
+    let start: BTreeSet<State> = nfa
+        .epsilon_closure(&HashSet::from([nfa.start]))
+        .into_iter()
+        .collect();
+    let mut index: HashMap<BTreeSet<State>, State> = HashMap::from([(start.clone(), 0)]);
+    let mut subsets = vec![start.clone()];
+    let mut work_queue = VecDeque::from([start]);
+    let mut tfn = HashMap::new();
+    let mut steps = Vec::new();
+
+    while let Some(subset) = work_queue.pop_front() {
+        let from = index[&subset];
+        let mut moves = Vec::new();
+        for &c in &alphabet {
+            let moved: HashSet<State> = subset
+                .iter()
+                .flat_map(|&s| nfa.tfn.get(&(s, c)).into_iter().flatten().copied())
+                .collect();
+            let closure = nfa.epsilon_closure(&moved);
+            let next: BTreeSet<State> = closure.iter().copied().collect();
+            let (to, new) = match index.get(&next) {
+                Some(&to) => (to, false),
+                None => {
+                    let to = subsets.len();
+                    index.insert(next.clone(), to);
+                    subsets.push(next.clone());
+                    work_queue.push_back(next);
+                    (to, true)
+                }
+            };
+            tfn.insert((from, c), to);
+            moves.push(SubsetMove {
+                symbol: c,
+                moved: sorted(&moved),
+                closure: sorted(&closure),
+                target: to,
+                new,
+            });
+        }
+        steps.push(SubsetStep {
+            state: from,
+            accepting: subset.iter().any(|s| nfa.accept.contains(s)),
+            subset: subset.into_iter().collect(),
+            moves,
+        });
+    }
+
+    let accept = steps
+        .iter()
+        .filter(|step| step.accepting)
+        .map(|step| step.state)
+        .collect();
+    let dfa = DFA::new(
+        subsets.len(),
+        0,
+        accept,
+        alphabet.iter().copied().collect(),
+        tfn,
+    )
+    .expect("the subset construction always yields a total DFA");
+    let trace = SubsetTrace {
+        nfa_start: nfa.start,
+        alphabet,
+        steps,
+    };
+    (dfa, trace)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::SimulationResult;
+
+    #[test]
+    fn determinize_ends_with_11() {
+        let mut tfn = HashMap::new();
+        tfn.insert((0, '0'), HashSet::from([0]));
+        tfn.insert((0, '1'), HashSet::from([0, 1]));
+        tfn.insert((1, '1'), HashSet::from([2]));
+        let nfa = NFA::new(3, 0, HashSet::from([2]), HashSet::from(['0', '1']), tfn).unwrap();
+        let dfa = determinize(&nfa);
+        // {0}, {0, 1}, {0, 1, 2}
+        assert_eq!(dfa.states.len(), 3);
+        for (input, accepted) in [("", false), ("11", true), ("0110", false), ("1011", true)] {
+            let sim = dfa.simulate(input);
+            assert_eq!(matches!(sim, Ok(SimulationResult::Accepted)), accepted);
+        }
+    }
+
+    #[test]
+    fn determinize_follows_epsilon_and_adds_dead_state() {
+        // 0 -~-> 1 -a-> 2, accept 2
+        let mut tfn = HashMap::new();
+        tfn.insert((0, EPSILON), HashSet::from([1]));
+        tfn.insert((1, 'a'), HashSet::from([2]));
+        let nfa = NFA::new(3, 0, HashSet::from([2]), HashSet::from(['a']), tfn).unwrap();
+        let dfa = determinize(&nfa);
+        // {0, 1}, {2}, {}
+        assert_eq!(dfa.states.len(), 3);
+        assert!(!dfa.alphabet.contains(&EPSILON));
+        assert!(matches!(dfa.simulate("a"), Ok(SimulationResult::Accepted)));
+        assert!(matches!(dfa.simulate("aa"), Ok(SimulationResult::Rejected)));
+    }
+
+    #[test]
+    fn recorded_steps_note_new_and_seen_subsets() {
+        // 0 -a-> {0, 1}, 1 -~-> 2, 2 -b-> 0; accept 2
+        let mut tfn = HashMap::new();
+        tfn.insert((0, 'a'), HashSet::from([0, 1]));
+        tfn.insert((1, EPSILON), HashSet::from([2]));
+        tfn.insert((2, 'b'), HashSet::from([0]));
+        let nfa = NFA::new(3, 0, HashSet::from([2]), HashSet::from(['a', 'b']), tfn).unwrap();
+        let (dfa, trace) = determinize_recorded(&nfa);
+        assert_eq!(trace.steps.len(), dfa.states.len());
+        assert_eq!(trace.steps[0].subset, [0]);
+        let on_a = &trace.steps[0].moves[0];
+        assert_eq!(on_a.moved, [0, 1]);
+        assert_eq!(on_a.closure, [0, 1, 2]);
+        assert_eq!((on_a.target, on_a.new), (1, true));
+        // {0, 1, 2} on a reaches itself again
+        let again = &trace.steps[1].moves[0];
+        assert_eq!((again.target, again.new), (1, false));
+        assert!(trace.steps[1].accepting);
+    }
+}
@@ -0,0 +1,290 @@
+//! Minimization and equivalence checking for Mealy and Moore machines. Two machines are
+//! equivalent when they produce the same output on every input; minimization merges states
+//! that are output-equivalent by partition refinement, then numbers the classes breadth first
+//! from the start state over the sorted input alphabet.
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::dfa::State;
+use crate::mealy::MealyMachine;
+use crate::moore::MooreMachine;
+
+fn sorted(alphabet: &HashSet<char>) -> Vec<char> {
+    let mut alphabet: Vec<char> = alphabet.iter().copied().collect();
+    alphabet.sort();
+    alphabet
+}
+
+/// The states reachable from `start`, in breadth-first order.
+fn reachable(start: State, alphabet: &[char], next: impl Fn(State, char) -> State) -> Vec<State> {
+    let mut seen = HashSet::from([start]);
+    let mut order = vec![start];
+    let mut i = 0;
+    while i < order.len() {
+        for &a in alphabet {
+            let t = next(order[i], a);
+            if seen.insert(t) {
+                order.push(t);
+            }
+        }
+        i += 1;
+    }
+    order
+}
+
+/// Splits `states` into output-equivalence classes, starting from the partition by `signature`
+/// and refining by the classes of successors until it is stable.
+fn refine(
+    states: &[State],
+    alphabet: &[char],
+    signature: impl Fn(State) -> Vec<char>,
+    next: impl Fn(State, char) -> State,
+) -> HashMap<State, usize> {
+    let mut ids = HashMap::new();
+    let mut class: HashMap<State, usize> = states
+        .iter()
+        .map(|&s| {
+            let n = ids.len();
+            (s, *ids.entry(signature(s)).or_insert(n))
+        })
+        .collect();
+    loop {
+        let count = ids.len();
+        let mut ids = HashMap::new();
+        let refined: HashMap<State, usize> = states
+            .iter()
+            .map(|&s| {
+                let key: (usize, Vec<usize>) = (
+                    class[&s],
+                    alphabet.iter().map(|&a| class[&next(s, a)]).collect(),
+                );
+                let n = ids.len();
+                (s, *ids.entry(key).or_insert(n))
+            })
+            .collect();
+        class = refined;
+        if ids.len() == count {
+            return class;
+        }
+    }
+}
+
+/// Numbers the classes breadth first from the start state's class, returning each state's new
+/// number and a representative state per new number.
+fn renumber(
+    start: State,
+    alphabet: &[char],
+    class: &HashMap<State, usize>,
+    next: impl Fn(State, char) -> State,
+) -> (HashMap<usize, State>, Vec<State>) {
+    let mut number = HashMap::from([(class[&start], 0)]);
+    let mut representatives = vec![start];
+    let mut i = 0;
+    while i < representatives.len() {
+        for &a in alphabet {
+            let t = next(representatives[i], a);
+            if let Entry::Vacant(e) = number.entry(class[&t]) {
+                e.insert(representatives.len());
+                representatives.push(t);
+            }
+        }
+        i += 1;
+    }
+    (number, representatives)
+}
+
+/// The minimal Mealy machine with the same output on every input.
+pub fn minimize_mealy(mealy: &MealyMachine) -> MealyMachine {
+    let alphabet = sorted(&mealy.input_alphabet);
+    let next = |s, a| mealy.tfn[&(s, a)].0;
+    let states = reachable(mealy.start, &alphabet, next);
+    let signature = |s| alphabet.iter().map(|&a| mealy.tfn[&(s, a)].1).collect();
+    let class = refine(&states, &alphabet, signature, next);
+    let (number, representatives) = renumber(mealy.start, &alphabet, &class, next);
+
+    let mut tfn = HashMap::new();
+    for (i, &s) in representatives.iter().enumerate() {
+        for &a in &alphabet {
+            let (t, o) = mealy.tfn[&(s, a)];
+            tfn.insert((i, a), (number[&class[&t]], o));
+        }
+    }
+    MealyMachine::new(
+        representatives.len(),
+        0,
+        mealy.input_alphabet.clone(),
+        mealy.output_alphabet.clone(),
+        tfn,
+    )
+    .expect("quotient of a valid Mealy machine is valid")
+}
+
+/// The minimal Moore machine with the same output on every input.
+pub fn minimize_moore(moore: &MooreMachine) -> MooreMachine {
+    let alphabet = sorted(&moore.input_alphabet);
+    let next = |s, a| moore.tfn[&(s, a)];
+    let states = reachable(moore.start, &alphabet, next);
+    let class = refine(&states, &alphabet, |s| vec![moore.output[&s]], next);
+    let (number, representatives) = renumber(moore.start, &alphabet, &class, next);
+
+    let mut tfn = HashMap::new();
+    let mut output = HashMap::new();
+    for (i, &s) in representatives.iter().enumerate() {
+        output.insert(i, moore.output[&s]);
+        for &a in &alphabet {
+            tfn.insert((i, a), number[&class[&moore.tfn[&(s, a)]]]);
+        }
+    }
+    MooreMachine::new(
+        representatives.len(),
+        0,
+        moore.input_alphabet.clone(),
+        moore.output_alphabet.clone(),
+        tfn,
+        output,
+    )
+    .expect("quotient of a valid Moore machine is valid")
+}
+
+type Pair = (State, State);
+
+/// Breadth first search over pairs of states, reconstructing the input that led to the first
+/// pair for which `differs` reports a distinguishing symbol (`None` meaning the pair itself).
+fn shortest_difference(
+    start: (State, State),
+    alphabet: &[char],
+    differs: impl Fn((State, State)) -> Option<Option<char>>,
+    next: impl Fn((State, State), char) -> Option<(State, State)>,
+) -> Option<String> {
+    let mut parent: HashMap<Pair, Option<(Pair, char)>> = HashMap::from([(start, None)]);
+    let mut work_queue = VecDeque::from([start]);
+    while let Some(pair) = work_queue.pop_front() {
+        if let Some(last) = differs(pair) {
+            let mut input: Vec<char> = last.into_iter().collect();
+            let mut current = pair;
+            while let Some((previous, a)) = parent[&current] {
+                input.push(a);
+                current = previous;
+            }
+            return Some(input.into_iter().rev().collect());
+        }
+        for &a in alphabet {
+            if let Some(t) = next(pair, a) {
+                parent.entry(t).or_insert_with(|| {
+                    work_queue.push_back(t);
+                    Some((pair, a))
+                });
+            }
+        }
+    }
+    None
+}
+
+/// A shortest input on whose last symbol the two machines write different outputs, or `None` if
+/// they are equivalent. A symbol in only one input alphabet is a difference, since the other
+/// machine has no transition on it.
+pub fn mealy_counterexample(a: &MealyMachine, b: &MealyMachine) -> Option<String> {
+    let alphabet = sorted(&a.input_alphabet.union(&b.input_alphabet).copied().collect());
+    let differs = |(p, q)| {
+        alphabet
+            .iter()
+            .find(|&&c| match (a.tfn.get(&(p, c)), b.tfn.get(&(q, c))) {
+                (Some(&(_, x)), Some(&(_, y))) => x != y,
+                _ => true,
+            })
+            .map(|&c| Some(c))
+    };
+    let next = |(p, q), c| Some((a.tfn[&(p, c)].0, b.tfn[&(q, c)].0));
+    shortest_difference((a.start, b.start), &alphabet, differs, next)
+}
+
+/// A shortest input after which the two machines are in states with different outputs, or `None`
+/// if they are equivalent. The start states' outputs are compared first, so machines that differ
+/// only in their initial output give the empty string. A symbol in only one input alphabet is
+/// also a difference.
+pub fn moore_counterexample(a: &MooreMachine, b: &MooreMachine) -> Option<String> {
+    let alphabet = sorted(&a.input_alphabet.union(&b.input_alphabet).copied().collect());
+    let differs = |(p, q)| {
+        if a.output[&p] != b.output[&q] {
+            return Some(None);
+        }
+        alphabet
+            .iter()
+            .find(|&&c| !(a.input_alphabet.contains(&c) && b.input_alphabet.contains(&c)))
+            .map(|&c| Some(c))
+    };
+    let next = |(p, q), c| Some((a.tfn[&(p, c)], b.tfn[&(q, c)]));
+    shortest_difference((a.start, b.start), &alphabet, differs, next)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // outputs the parity of a's read so far, with redundant copies of both parities and an
+    // unreachable state 4
+    fn parity_mealy() -> MealyMachine {
+        let mut tfn = HashMap::new();
+        tfn.insert((0, 'a'), (1, '1'));
+        tfn.insert((0, 'b'), (2, '0'));
+        tfn.insert((1, 'a'), (2, '0'));
+        tfn.insert((1, 'b'), (3, '1'));
+        tfn.insert((2, 'a'), (3, '1'));
+        tfn.insert((2, 'b'), (0, '0'));
+        tfn.insert((3, 'a'), (0, '0'));
+        tfn.insert((3, 'b'), (1, '1'));
+        tfn.insert((4, 'a'), (4, '1'));
+        tfn.insert((4, 'b'), (4, '1'));
+        MealyMachine::new(
+            5,
+            0,
+            HashSet::from(['a', 'b']),
+            HashSet::from(['0', '1']),
+            tfn,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn minimizes_mealy() {
+        let mealy = parity_mealy();
+        let minimal = minimize_mealy(&mealy);
+        assert_eq!(minimal.states.len(), 2);
+        assert_eq!(minimal.tfn[&(0, 'a')], (1, '1'));
+        assert_eq!(minimal.tfn[&(1, 'b')], (1, '1'));
+        assert_eq!(mealy_counterexample(&mealy, &minimal), None);
+    }
+
+    #[test]
+    fn minimizes_moore() {
+        let moore = parity_mealy().to_moore('0');
+        let minimal = minimize_moore(&moore);
+        assert_eq!(minimal.states.len(), 2);
+        assert_eq!(moore_counterexample(&moore, &minimal), None);
+        assert_eq!(minimal.simulate("abab").unwrap(), "01100");
+    }
+
+    #[test]
+    fn mealy_counterexample_is_shortest() {
+        let a = parity_mealy();
+        let mut b = minimize_mealy(&a);
+        b.tfn.insert((1, 'b'), (0, '1'));
+        // "ab" still agrees; "aba" outputs 101 vs 111
+        assert_eq!(mealy_counterexample(&a, &b).as_deref(), Some("aba"));
+
+        let mut c = b.clone();
+        c.input_alphabet.insert('c');
+        c.tfn.insert((0, 'c'), (0, '0'));
+        c.tfn.insert((1, 'c'), (1, '0'));
+        assert_eq!(mealy_counterexample(&b, &c).as_deref(), Some("c"));
+    }
+
+    #[test]
+    fn moore_counterexample_checks_initial_output() {
+        let a = parity_mealy().to_moore('0');
+        let b = parity_mealy().to_moore('1');
+        assert_eq!(moore_counterexample(&a, &b).as_deref(), Some(""));
+        assert_eq!(moore_counterexample(&a, &a.clone()), None);
+    }
+}
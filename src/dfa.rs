@@ -5,6 +5,8 @@ use std::{
 
 use itertools::Itertools;
 
+use crate::formats::table::{TableFormat, dfa_table};
+
 pub enum SimulationResult {
     Accepted,
     Rejected,
@@ -43,7 +45,7 @@ impl DFA {
         alphabet: &HashSet<char>,
         tfn: &HashMap<(usize, char), usize>,
     ) -> Result<(), DFATypeError> {
-        if !(start < states) {
+        if start >= states {
             return Err(DFATypeError::InvalidStartState);
         }
         if !(accept.iter().all(|&s| s < states)) {
@@ -56,14 +58,14 @@ impl DFA {
         {
             return Err(DFATypeError::NonTotalTransitionFunction);
         }
-        if !(tfn.len() == states * alphabet.len()) || !(tfn.values().all(|&v| v < states)) {
+        if tfn.len() != states * alphabet.len() || !tfn.values().all(|&v| v < states) {
             return Err(DFATypeError::InvalidTransitionFunction);
         }
 
         Ok(())
     }
 
-    fn validate_input(&self, input: &String) -> Result<(), InputError> {
+    fn validate_input(&self, input: &str) -> Result<(), InputError> {
         if input.chars().all(|c| self.alphabet.contains(&c)) {
             return Ok(());
         }
@@ -92,17 +94,14 @@ impl DFA {
         Ok(dfa)
     }
 
-    pub fn simulate(&self, input: &String) -> Result<SimulationResult, InputError> {
+    pub fn simulate(&self, input: &str) -> Result<SimulationResult, InputError> {
         self.validate_input(input)?;
         // TODO: understand better what is going on here. is self.start moved? cloned? what happens in the loop?
         let mut current_state = self.start;
         for s in input.chars() {
-            let new_state = self.tfn.get(&(current_state, s));
-            match new_state {
-                Some(&s) => {
-                    current_state = s;
-                }
-                None => (), // we know the tfn is valid so we won't actually reach this state
+            // we know the tfn is valid so the lookup never misses
+            if let Some(&s) = self.tfn.get(&(current_state, s)) {
+                current_state = s;
             }
         }
         if self.accept.contains(&current_state) {
@@ -114,15 +113,12 @@ impl DFA {
 
 impl fmt::Display for DFA {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "states: {:?}", self.states)?;
-        writeln!(f, "start: {}", self.start)?;
-        writeln!(f, "accept: {:?}", self.accept)?;
-        writeln!(f, "alphabet: {:?}", self.alphabet)?;
-        write!(f, "tfn: {:?}", self.tfn)
+        write!(f, "{}", dfa_table(self, TableFormat::Plain))
     }
 }
 
 #[cfg(test)]
+#[allow(clippy::assertions_on_constants)]
 mod tests {
     use super::*;
 
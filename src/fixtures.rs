@@ -0,0 +1,34 @@
+//! Small machines shared by the test modules.
+
+use fsim_macros::{dfa, nfa};
+
+use crate::dfa::DFA;
+use crate::nfa::NFA;
+
+/// Strings over {0, 1} of even length.
+pub(crate) fn even_length() -> DFA {
+    dfa! {
+        states: 2,
+        start: 0,
+        accept: [0],
+        alphabet: ['0', '1'],
+        transitions: {
+            0 => { '0' | '1' => 1 },
+            1 => { '0' | '1' => 0 },
+        },
+    }
+}
+
+/// Strings over {0, 1} ending in "11".
+pub(crate) fn ends_with_11() -> NFA {
+    nfa! {
+        states: 3,
+        start: 0,
+        accept: [2],
+        alphabet: ['0', '1'],
+        transitions: {
+            0 => { '0' => 0, '1' => [0, 1] },
+            1 => { '1' => 2 },
+        },
+    }
+}
@@ -0,0 +1,436 @@
+//! Compact binary encoding of machines.
+//!
+//! Layout (all integers are LEB128 varints unless noted):
+//!
+//! ```text
+//! magic "FS" (2 bytes) | version (1 byte) | kind (1 byte: 0 = dfa, 1 = nfa)
+//! states | start | alphabet size | symbols as code points, ascending
+//! accept count | accept states, ascending, each stored as the gap from the previous one
+//! dfa: one target per (state, symbol) in row-major order
+//! nfa: edge count | (from, symbol index + 1 or 0 for epsilon, to) per edge, sorted
+//! checksum (4 bytes, FNV-1a over everything before it, little-endian)
+//! ```
+
+use std::collections::{HashMap, HashSet};
+
+use crate::dfa::{DFA, DFATypeError};
+use crate::nfa::{EPSILON, NFA, NFATypeError};
+
+const MAGIC: &[u8; 2] = b"FS";
+const VERSION: u8 = 1;
+const KIND_DFA: u8 = 0;
+const KIND_NFA: u8 = 1;
+
+/// Limits applied while decoding. A state can take no bytes at all (a DFA over the empty
+/// alphabet, an NFA state without edges), so the state count is checked against `max_states`
+/// before any machine is built.
+#[derive(Clone, Debug)]
+pub struct DecodeOptions {
+    pub max_states: usize,
+}
+
+impl Default for DecodeOptions {
+    fn default() -> Self {
+        Self {
+            max_states: 1 << 16,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum DecodeError {
+    Truncated,
+    BadMagic,
+    UnsupportedVersion(u8),
+    WrongKind,
+    VarintOverflow,
+    TooManyStates(usize),
+    InvalidSymbol(u32),
+    TrailingBytes,
+    ChecksumMismatch,
+    InvalidBase64,
+    InvalidDFA(DFATypeError),
+    InvalidNFA(NFATypeError),
+}
+
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .fold(0x811c9dc5, |h, &b| (h ^ b as u32).wrapping_mul(0x01000193))
+}
+
+fn write_varint(out: &mut Vec<u8>, mut v: u64) {
+    while v >= 0x80 {
+        out.push((v as u8) | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Result<u8, DecodeError> {
+        let &b = self.bytes.get(self.pos).ok_or(DecodeError::Truncated)?;
+        self.pos += 1;
+        Ok(b)
+    }
+
+    fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut v = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            let bits = (b & 0x7f) as u64;
+            if shift == 63 && bits > 1 {
+                return Err(DecodeError::VarintOverflow);
+            }
+            v |= bits << shift;
+            if b & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+        Err(DecodeError::VarintOverflow)
+    }
+
+    fn usize(&mut self) -> Result<usize, DecodeError> {
+        usize::try_from(self.varint()?).map_err(|_| DecodeError::VarintOverflow)
+    }
+
+    // a count of items that each take at least one more byte, so it can't exceed what's left;
+    // this keeps corrupt lengths from triggering huge allocations
+    fn count(&mut self) -> Result<usize, DecodeError> {
+        let n = self.usize()?;
+        if n > self.bytes.len() - self.pos {
+            return Err(DecodeError::Truncated);
+        }
+        Ok(n)
+    }
+}
+
+struct Header {
+    states: usize,
+    start: usize,
+    alphabet: Vec<char>,
+    accept: HashSet<usize>,
+}
+
+fn encode_header(out: &mut Vec<u8>, kind: u8, h: &Header) {
+    out.extend_from_slice(MAGIC);
+    out.push(VERSION);
+    out.push(kind);
+    write_varint(out, h.states as u64);
+    write_varint(out, h.start as u64);
+    write_varint(out, h.alphabet.len() as u64);
+    for &c in &h.alphabet {
+        write_varint(out, c as u64);
+    }
+    let mut accept: Vec<usize> = h.accept.iter().copied().collect();
+    accept.sort();
+    write_varint(out, accept.len() as u64);
+    let mut prev = 0;
+    for s in accept {
+        write_varint(out, (s - prev) as u64);
+        prev = s;
+    }
+}
+
+fn finish(mut out: Vec<u8>) -> Vec<u8> {
+    let checksum = fnv1a(&out);
+    out.extend_from_slice(&checksum.to_le_bytes());
+    out
+}
+
+/// Checks framing and checksum, and reads the header of a machine of the given kind.
+fn decode_header<'a>(
+    bytes: &'a [u8],
+    kind: u8,
+    options: &DecodeOptions,
+) -> Result<(Reader<'a>, Header), DecodeError> {
+    if bytes.len() < MAGIC.len() {
+        return Err(DecodeError::Truncated);
+    }
+    if &bytes[..MAGIC.len()] != MAGIC {
+        return Err(DecodeError::BadMagic);
+    }
+    if bytes.len() < MAGIC.len() + 2 + 4 {
+        return Err(DecodeError::Truncated);
+    }
+    if bytes[2] != VERSION {
+        return Err(DecodeError::UnsupportedVersion(bytes[2]));
+    }
+    let (body, checksum) = bytes.split_at(bytes.len() - 4);
+    if fnv1a(body) != u32::from_le_bytes(checksum.try_into().unwrap()) {
+        return Err(DecodeError::ChecksumMismatch);
+    }
+    if body[3] != kind {
+        return Err(DecodeError::WrongKind);
+    }
+
+    let mut r = Reader {
+        bytes: body,
+        pos: 4,
+    };
+    let states = r.usize()?;
+    if states > options.max_states {
+        return Err(DecodeError::TooManyStates(states));
+    }
+    let start = r.usize()?;
+    let alphabet = (0..r.count()?)
+        .map(|_| {
+            let code = u32::try_from(r.varint()?).map_err(|_| DecodeError::VarintOverflow)?;
+            char::from_u32(code).ok_or(DecodeError::InvalidSymbol(code))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let mut accept = HashSet::new();
+    let mut prev = 0usize;
+    for _ in 0..r.count()? {
+        prev = prev
+            .checked_add(r.usize()?)
+            .ok_or(DecodeError::VarintOverflow)?;
+        accept.insert(prev);
+    }
+    Ok((
+        r,
+        Header {
+            states,
+            start,
+            alphabet,
+            accept,
+        },
+    ))
+}
+
+// a DFA may use EPSILON as an ordinary symbol; only an NFA's alphabet leaves it out
+fn sorted_alphabet(alphabet: impl Iterator<Item = char>) -> Vec<char> {
+    let mut alphabet: Vec<char> = alphabet.collect();
+    alphabet.sort();
+    alphabet
+}
+
+pub fn encode_dfa(dfa: &DFA) -> Vec<u8> {
+    let header = Header {
+        states: dfa.states.len(),
+        start: dfa.start,
+        alphabet: sorted_alphabet(dfa.alphabet.iter().copied()),
+        accept: dfa.accept.clone(),
+    };
+    let mut out = Vec::new();
+    encode_header(&mut out, KIND_DFA, &header);
+    for s in 0..header.states {
+        for &c in &header.alphabet {
+            write_varint(&mut out, dfa.tfn[&(s, c)] as u64);
+        }
+    }
+    finish(out)
+}
+
+pub fn decode_dfa(bytes: &[u8]) -> Result<DFA, DecodeError> {
+    decode_dfa_with(bytes, &DecodeOptions::default())
+}
+
+pub fn decode_dfa_with(bytes: &[u8], options: &DecodeOptions) -> Result<DFA, DecodeError> {
+    let (mut r, h) = decode_header(bytes, KIND_DFA, options)?;
+    // every target takes at least one byte
+    let cells = h
+        .states
+        .checked_mul(h.alphabet.len())
+        .filter(|&n| n <= r.bytes.len() - r.pos)
+        .ok_or(DecodeError::Truncated)?;
+    let mut tfn = HashMap::with_capacity(cells);
+    for s in 0..h.states {
+        for &c in &h.alphabet {
+            tfn.insert((s, c), r.usize()?);
+        }
+    }
+    if r.pos != r.bytes.len() {
+        return Err(DecodeError::TrailingBytes);
+    }
+    DFA::new(
+        h.states,
+        h.start,
+        h.accept,
+        h.alphabet.into_iter().collect(),
+        tfn,
+    )
+    .map_err(DecodeError::InvalidDFA)
+}
+
+pub fn encode_nfa(nfa: &NFA) -> Vec<u8> {
+    let header = Header {
+        states: nfa.states.len(),
+        start: nfa.start,
+        alphabet: sorted_alphabet(nfa.alphabet.iter().copied().filter(|&c| c != EPSILON)),
+        accept: nfa.accept.clone(),
+    };
+    let symbol_index: HashMap<char, usize> = header
+        .alphabet
+        .iter()
+        .enumerate()
+        .map(|(i, &c)| (c, i + 1))
+        .collect();
+    let mut edges: Vec<(usize, usize, usize)> = nfa
+        .tfn
+        .iter()
+        .flat_map(|(&(from, c), targets)| {
+            let symbol = if c == EPSILON { 0 } else { symbol_index[&c] };
+            targets.iter().map(move |&to| (from, symbol, to))
+        })
+        .collect();
+    edges.sort();
+
+    let mut out = Vec::new();
+    encode_header(&mut out, KIND_NFA, &header);
+    write_varint(&mut out, edges.len() as u64);
+    for (from, symbol, to) in edges {
+        write_varint(&mut out, from as u64);
+        write_varint(&mut out, symbol as u64);
+        write_varint(&mut out, to as u64);
+    }
+    finish(out)
+}
+
+pub fn decode_nfa(bytes: &[u8]) -> Result<NFA, DecodeError> {
+    decode_nfa_with(bytes, &DecodeOptions::default())
+}
+
+pub fn decode_nfa_with(bytes: &[u8], options: &DecodeOptions) -> Result<NFA, DecodeError> {
+    let (mut r, h) = decode_header(bytes, KIND_NFA, options)?;
+    let mut tfn: HashMap<(usize, char), HashSet<usize>> = HashMap::new();
+    for _ in 0..r.count()? {
+        let from = r.usize()?;
+        let symbol = r.usize()?;
+        let to = r.usize()?;
+        let c = match symbol {
+            0 => EPSILON,
+            i => *h
+                .alphabet
+                .get(i - 1)
+                .ok_or(DecodeError::InvalidSymbol(i as u32))?,
+        };
+        tfn.entry((from, c)).or_default().insert(to);
+    }
+    if r.pos != r.bytes.len() {
+        return Err(DecodeError::TrailingBytes);
+    }
+    NFA::new(
+        h.states,
+        h.start,
+        h.accept,
+        h.alphabet.into_iter().collect(),
+        tfn,
+    )
+    .map_err(DecodeError::InvalidNFA)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::SimulationResult;
+    use crate::fixtures::even_length;
+
+    #[test]
+    fn dfa_round_trips() {
+        let bytes = encode_dfa(&even_length());
+        assert_eq!(&bytes[..4], b"FS\x01\x00");
+        let dfa = decode_dfa(&bytes).unwrap();
+        assert!(matches!(dfa.simulate("01"), Ok(SimulationResult::Accepted)));
+        assert!(matches!(dfa.simulate("0"), Ok(SimulationResult::Rejected)));
+        assert_eq!(encode_dfa(&dfa), bytes);
+    }
+
+    #[test]
+    fn nfa_round_trips_with_epsilon_and_unicode() {
+        let mut tfn = HashMap::new();
+        tfn.insert((0, EPSILON), HashSet::from([1]));
+        tfn.insert((1, 'λ'), HashSet::from([0, 300]));
+        let nfa = NFA::new(301, 0, HashSet::from([300]), HashSet::from(['λ']), tfn).unwrap();
+        let bytes = encode_nfa(&nfa);
+        let back = decode_nfa(&bytes).unwrap();
+        assert_eq!(encode_nfa(&back), bytes);
+        assert!(matches!(
+            back.simulate("λ"),
+            Ok(crate::nfa::SimulationResult::Accepted)
+        ));
+    }
+
+    #[test]
+    fn corrupt_input_is_rejected() {
+        let bytes = encode_dfa(&even_length());
+        assert!(matches!(
+            decode_dfa(&bytes[..1]),
+            Err(DecodeError::Truncated)
+        ));
+        assert!(matches!(
+            decode_dfa(&bytes[..bytes.len() - 1]),
+            Err(DecodeError::ChecksumMismatch)
+        ));
+        assert!(matches!(decode_nfa(&bytes), Err(DecodeError::WrongKind)));
+        assert!(matches!(
+            decode_dfa(b"XX\x01\x00"),
+            Err(DecodeError::BadMagic)
+        ));
+
+        let mut flipped = bytes.clone();
+        flipped[6] ^= 1;
+        assert!(matches!(
+            decode_dfa(&flipped),
+            Err(DecodeError::ChecksumMismatch)
+        ));
+    }
+
+    #[test]
+    fn invalid_machine_with_valid_framing_is_rejected() {
+        // start state out of range, with a correct checksum
+        let mut out = Vec::new();
+        encode_header(
+            &mut out,
+            KIND_DFA,
+            &Header {
+                states: 1,
+                start: 3,
+                alphabet: vec![],
+                accept: HashSet::new(),
+            },
+        );
+        assert!(matches!(
+            decode_dfa(&finish(out)),
+            Err(DecodeError::InvalidDFA(DFATypeError::InvalidStartState))
+        ));
+    }
+
+    #[test]
+    fn state_count_is_limited() {
+        // 16M states over the empty alphabet fit in a 20-character link
+        let bytes = crate::formats::link::base64_decode("RlMBAP___wcAAAA5MAl9").unwrap();
+        assert!(matches!(
+            decode_dfa(&bytes),
+            Err(DecodeError::TooManyStates(16777215))
+        ));
+
+        let bytes = encode_dfa(&even_length());
+        let options = DecodeOptions { max_states: 1 };
+        assert!(matches!(
+            decode_dfa_with(&bytes, &options),
+            Err(DecodeError::TooManyStates(2))
+        ));
+    }
+
+    #[test]
+    fn tilde_round_trips_as_a_dfa_symbol() {
+        let mut tfn = HashMap::new();
+        tfn.insert((0, '~'), 1);
+        tfn.insert((0, 'a'), 0);
+        tfn.insert((1, '~'), 0);
+        tfn.insert((1, 'a'), 1);
+        let dfa = DFA::new(2, 0, HashSet::from([1]), HashSet::from(['~', 'a']), tfn).unwrap();
+        let back = decode_dfa(&encode_dfa(&dfa)).unwrap();
+        assert!(matches!(
+            back.simulate("~a"),
+            Ok(SimulationResult::Accepted)
+        ));
+        assert!(matches!(back.simulate("a"), Ok(SimulationResult::Rejected)));
+    }
+}
@@ -0,0 +1,327 @@
+//! Standalone Rust source for a `DFA`. The generated recognizer has no dependency on fsim:
+//!
+//! ```text
+//! pub fn <name>(input: &str) -> Option<bool>
+//! ```
+//!
+//! returning `None` when the input contains a symbol outside the alphabet, mirroring
+//! `InputError::InvalidSymbol`, and `Some(accepted)` otherwise.
+
+use std::fmt::Write;
+
+use crate::dfa::{DFA, SimulationResult};
+use crate::random::SplitMix64;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RustStyle {
+    /// One `match` arm per transition. Readable, and fine for small machines.
+    Match,
+    /// A `const` transition table indexed by state and symbol.
+    Table,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CodegenError {
+    /// The function name is not a plain ASCII Rust identifier, or is a keyword.
+    InvalidIdentifier(String),
+}
+
+const KEYWORDS: &[&str] = &[
+    "_", "abstract", "as", "async", "await", "become", "box", "break", "const", "continue",
+    "crate", "do", "dyn", "else", "enum", "extern", "false", "final", "fn", "for", "gen", "if",
+    "impl", "in", "let", "loop", "macro", "match", "mod", "move", "mut", "override", "priv", "pub",
+    "ref", "return", "self", "Self", "static", "struct", "super", "trait", "true", "try", "type",
+    "typeof", "unsafe", "unsized", "use", "virtual", "where", "while", "yield",
+];
+
+fn check_identifier(name: &str) -> Result<(), CodegenError> {
+    let mut chars = name.chars();
+    let valid = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name);
+    if valid {
+        Ok(())
+    } else {
+        Err(CodegenError::InvalidIdentifier(name.to_string()))
+    }
+}
+
+fn sorted_alphabet(dfa: &DFA) -> Vec<char> {
+    let mut alphabet: Vec<char> = dfa.alphabet.iter().copied().collect();
+    alphabet.sort();
+    alphabet
+}
+
+fn state_type(states: usize) -> &'static str {
+    match states {
+        0..=0x100 => "u8",
+        0x101..=0x10000 => "u16",
+        _ => "u32",
+    }
+}
+
+/// The recognizer's constants are named after `fn_name` in upper case, so recognizers generated
+/// into one module need names that differ in more than case.
+pub fn dfa_to_rust(dfa: &DFA, fn_name: &str, style: RustStyle) -> Result<String, CodegenError> {
+    check_identifier(fn_name)?;
+    let alphabet = sorted_alphabet(dfa);
+    let n = dfa.states.len();
+    let ty = state_type(n);
+    let prefix = fn_name.to_uppercase();
+    let mut out = String::new();
+
+    let accept: Vec<&str> = (0..n)
+        .map(|s| {
+            if dfa.accept.contains(&s) {
+                "true"
+            } else {
+                "false"
+            }
+        })
+        .collect();
+    writeln!(
+        out,
+        "const {prefix}_ACCEPT: [bool; {n}] = [{}];",
+        accept.join(", ")
+    )
+    .unwrap();
+
+    if style == RustStyle::Table && !alphabet.is_empty() {
+        let rows: Vec<String> = (0..n)
+            .map(|s| {
+                let row: Vec<String> = alphabet
+                    .iter()
+                    .map(|&c| dfa.tfn[&(s, c)].to_string())
+                    .collect();
+                format!("    [{}],", row.join(", "))
+            })
+            .collect();
+        writeln!(
+            out,
+            "const {prefix}_TRANSITIONS: [[{ty}; {}]; {n}] = [\n{}\n];",
+            alphabet.len(),
+            rows.join("\n")
+        )
+        .unwrap();
+    }
+    out.push('\n');
+
+    let symbols: Vec<String> = alphabet.iter().map(|c| format!("{c:?}")).collect();
+    writeln!(
+        out,
+        "/// Recognizer generated by fsim from a {n}-state DFA over {{{}}}.",
+        symbols.join(", ")
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "/// Returns `None` if the input contains a symbol outside the alphabet."
+    )
+    .unwrap();
+    writeln!(out, "pub fn {fn_name}(input: &str) -> Option<bool> {{").unwrap();
+
+    if alphabet.is_empty() {
+        writeln!(
+            out,
+            "    if !input.is_empty() {{\n        return None;\n    }}"
+        )
+        .unwrap();
+        writeln!(out, "    Some({prefix}_ACCEPT[{}])\n}}", dfa.start).unwrap();
+        return Ok(out);
+    }
+
+    writeln!(out, "    let mut state: {ty} = {};", dfa.start).unwrap();
+    writeln!(out, "    for c in input.chars() {{").unwrap();
+    match style {
+        RustStyle::Match => {
+            writeln!(out, "        state = match (state, c) {{").unwrap();
+            for s in 0..n {
+                for &c in &alphabet {
+                    writeln!(out, "            ({s}, {c:?}) => {},", dfa.tfn[&(s, c)]).unwrap();
+                }
+            }
+            writeln!(out, "            _ => return None,\n        }};").unwrap();
+        }
+        RustStyle::Table => {
+            writeln!(out, "        let symbol = match c {{").unwrap();
+            for (i, c) in alphabet.iter().enumerate() {
+                writeln!(out, "            {c:?} => {i},").unwrap();
+            }
+            writeln!(out, "            _ => return None,\n        }};").unwrap();
+            writeln!(
+                out,
+                "        state = {prefix}_TRANSITIONS[state as usize][symbol];"
+            )
+            .unwrap();
+        }
+    }
+    writeln!(out, "    }}").unwrap();
+    writeln!(out, "    Some({prefix}_ACCEPT[state as usize])\n}}").unwrap();
+    Ok(out)
+}
+
+/// Inputs for checking generated code: every string up to length 3, then random strings of up
+/// to 16 symbols, plus one string containing a symbol outside the alphabet.
+pub fn sample_inputs(dfa: &DFA, samples: usize, seed: u64) -> Vec<String> {
+    let alphabet = sorted_alphabet(dfa);
+    let mut inputs = vec![String::new()];
+    let mut frontier = vec![String::new()];
+    for _ in 0..3 {
+        frontier = frontier
+            .iter()
+            .flat_map(|w| alphabet.iter().map(move |&c| format!("{w}{c}")))
+            .collect();
+        if inputs.len() + frontier.len() > samples {
+            break;
+        }
+        inputs.extend(frontier.iter().cloned());
+    }
+
+    let mut rng = SplitMix64::new(seed);
+    while !alphabet.is_empty() && inputs.len() < samples {
+        let len = rng.below(17);
+        inputs.push((0..len).map(|_| *rng.choose(&alphabet).unwrap()).collect());
+    }
+
+    if let Some(outside) = ['#', 'x', '\u{fffd}']
+        .into_iter()
+        .find(|c| !dfa.alphabet.contains(c))
+    {
+        let mut bad: String = alphabet.iter().take(2).collect();
+        bad.push(outside);
+        inputs.push(bad);
+    }
+    inputs
+}
+
+/// A `#[cfg(test)]` module checking the function emitted by `dfa_to_rust` against
+/// `DFA::simulate` on `sample_inputs`. Append it to the generated recognizer.
+pub fn dfa_to_rust_test(
+    dfa: &DFA,
+    fn_name: &str,
+    samples: usize,
+    seed: u64,
+) -> Result<String, CodegenError> {
+    check_identifier(fn_name)?;
+    let mut out = String::new();
+    writeln!(out, "#[cfg(test)]\nmod {fn_name}_tests {{").unwrap();
+    writeln!(out, "    use super::*;\n").unwrap();
+    writeln!(out, "    #[test]\n    fn {fn_name}_agrees_with_fsim() {{").unwrap();
+    writeln!(out, "        let cases: &[(&str, Option<bool>)] = &[").unwrap();
+    for input in sample_inputs(dfa, samples, seed) {
+        let expected = match dfa.simulate(&input) {
+            Ok(SimulationResult::Accepted) => "Some(true)",
+            Ok(SimulationResult::Rejected) => "Some(false)",
+            Err(_) => "None",
+        };
+        writeln!(out, "            ({input:?}, {expected}),").unwrap();
+    }
+    writeln!(out, "        ];").unwrap();
+    writeln!(out, "        for &(input, expected) in cases {{").unwrap();
+    writeln!(
+        out,
+        "            assert_eq!({fn_name}(input), expected, \"input {{input:?}}\");"
+    )
+    .unwrap();
+    writeln!(out, "        }}\n    }}\n}}").unwrap();
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fsim_macros::dfa;
+    use std::process::Command;
+
+    // strings over {a, b} whose number of b's is divisible by 3; the start state 3 is a copy of
+    // state 0, so the start is not state 0
+    fn bs_mod_3() -> DFA {
+        dfa! {
+            states: 4,
+            start: 3,
+            accept: [0, 3],
+            alphabet: ['a', 'b'],
+            transitions: {
+                0 => { 'a' => 0, 'b' => 1 },
+                1 => { 'a' => 1, 'b' => 2 },
+                2 => { 'a' => 2, 'b' => 0 },
+                3 => { 'a' => 0, 'b' => 1 },
+            },
+        }
+    }
+
+    #[test]
+    fn match_style_lists_every_transition() {
+        let code = dfa_to_rust(&bs_mod_3(), "bs_mod_3", RustStyle::Match).unwrap();
+        assert!(code.contains("pub fn bs_mod_3(input: &str) -> Option<bool>"));
+        assert!(code.contains("let mut state: u8 = 3;"));
+        assert!(code.contains("(2, 'b') => 0,"));
+        assert!(code.contains("const BS_MOD_3_ACCEPT: [bool; 4] = [true, false, false, true];"));
+    }
+
+    #[test]
+    fn invalid_function_names_are_rejected() {
+        let dfa = bs_mod_3();
+        for name in ["", "1st", "has space", "fn", "r#x", "x-y", "_"] {
+            assert_eq!(
+                dfa_to_rust(&dfa, name, RustStyle::Table),
+                Err(CodegenError::InvalidIdentifier(name.to_string()))
+            );
+        }
+        assert!(dfa_to_rust_test(&dfa, "match", 10, 0).is_err());
+        assert!(dfa_to_rust(&dfa, "_private2", RustStyle::Match).is_ok());
+    }
+
+    #[test]
+    fn sample_inputs_are_deterministic() {
+        let dfa = bs_mod_3();
+        let a = sample_inputs(&dfa, 40, 1);
+        assert_eq!(a, sample_inputs(&dfa, 40, 1));
+        assert_eq!(a.len(), 41);
+        assert_eq!(&a[..3], &["", "a", "b"]);
+        assert!(a.last().unwrap().ends_with('#'));
+    }
+
+    #[test]
+    fn generated_code_passes_its_harness() {
+        // compiles both styles with their harnesses into a test binary and runs it
+        let dfa = bs_mod_3();
+        let mut source = String::new();
+        for (name, style) in [
+            ("by_match", RustStyle::Match),
+            ("by_table", RustStyle::Table),
+        ] {
+            source.push_str(&dfa_to_rust(&dfa, name, style).unwrap());
+            source.push_str(&dfa_to_rust_test(&dfa, name, 200, 42).unwrap());
+        }
+
+        let dir = std::env::temp_dir().join(format!("fsim-codegen-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let src = dir.join("recognizer.rs");
+        let bin = dir.join("recognizer");
+        std::fs::write(&src, source).unwrap();
+
+        let rustc = std::env::var("RUSTC").unwrap_or_else(|_| "rustc".to_string());
+        let compiled = Command::new(rustc)
+            .args(["--edition", "2021", "--test", "-D", "warnings", "-o"])
+            .arg(&bin)
+            .arg(&src)
+            .output()
+            .unwrap();
+        assert!(
+            compiled.status.success(),
+            "{}",
+            String::from_utf8_lossy(&compiled.stderr)
+        );
+        let run = Command::new(&bin).output().unwrap();
+        assert!(
+            run.status.success(),
+            "{}",
+            String::from_utf8_lossy(&run.stdout)
+        );
+        assert!(String::from_utf8_lossy(&run.stdout).contains("2 passed"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
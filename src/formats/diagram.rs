@@ -0,0 +1,147 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use crate::dfa::{DFA, State};
+use crate::nfa::{EPSILON, NFA};
+
+/// A machine as a drawable graph: one edge per ordered pair of states, carrying every symbol
+/// that moves between them. Epsilon transitions keep the `EPSILON` symbol. Shared by the
+/// diagram exporters so they agree on what an edge is.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Diagram {
+    pub states: usize,
+    pub start: State,
+    pub accept: BTreeSet<State>,
+    pub edges: Vec<DiagramEdge>,
+    /// Whether `EPSILON` stands for epsilon moves. False for a DFA, where it is an ordinary
+    /// symbol.
+    pub epsilon: bool,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct DiagramEdge {
+    pub from: State,
+    pub to: State,
+    pub symbols: Vec<char>,
+}
+
+impl DiagramEdge {
+    pub fn is_loop(&self) -> bool {
+        self.from == self.to
+    }
+}
+
+impl Diagram {
+    fn from_edges(
+        states: usize,
+        start: State,
+        accept: BTreeSet<State>,
+        edges: impl Iterator<Item = (State, char, State)>,
+        epsilon: bool,
+    ) -> Self {
+        let mut merged: BTreeMap<(State, State), Vec<char>> = BTreeMap::new();
+        for (from, c, to) in edges {
+            merged.entry((from, to)).or_default().push(c);
+        }
+        let edges = merged
+            .into_iter()
+            .map(|((from, to), mut symbols)| {
+                // epsilon last, the rest in order
+                symbols.sort_by_key(|&c| (epsilon && c == EPSILON, c));
+                DiagramEdge { from, to, symbols }
+            })
+            .collect();
+        Self {
+            states,
+            start,
+            accept,
+            edges,
+            epsilon,
+        }
+    }
+
+    pub fn from_dfa(dfa: &DFA) -> Self {
+        Self::from_edges(
+            dfa.states.len(),
+            dfa.start,
+            dfa.accept.iter().copied().collect(),
+            dfa.tfn.iter().map(|(&(from, c), &to)| (from, c, to)),
+            false,
+        )
+    }
+
+    pub fn from_nfa(nfa: &NFA) -> Self {
+        Self::from_edges(
+            nfa.states.len(),
+            nfa.start,
+            nfa.accept.iter().copied().collect(),
+            nfa.tfn
+                .iter()
+                .flat_map(|(&(from, c), targets)| targets.iter().map(move |&to| (from, c, to))),
+            true,
+        )
+    }
+
+    /// The symbols of `edge` joined with `", "`, with epsilon moves spelled `epsilon`.
+    pub fn label(&self, edge: &DiagramEdge, epsilon: &str) -> String {
+        edge.symbols
+            .iter()
+            .map(|&c| {
+                if self.epsilon && c == EPSILON {
+                    epsilon.to_string()
+                } else {
+                    c.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Whether there is also an edge in the opposite direction, which renderers draw bent so
+    /// the two don't overlap.
+    pub fn has_reverse(&self, edge: &DiagramEdge) -> bool {
+        !edge.is_loop()
+            && self
+                .edges
+                .iter()
+                .any(|e| e.from == edge.to && e.to == edge.from)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn parallel_symbols_are_merged() {
+        let mut tfn = HashMap::new();
+        tfn.insert((0, 'b'), HashSet::from([1]));
+        tfn.insert((0, 'a'), HashSet::from([1, 0]));
+        tfn.insert((0, EPSILON), HashSet::from([1]));
+        tfn.insert((1, 'a'), HashSet::from([0]));
+        let nfa = NFA::new(2, 0, HashSet::from([1]), HashSet::from(['a', 'b']), tfn).unwrap();
+        let d = Diagram::from_nfa(&nfa);
+        assert_eq!(d.edges.len(), 3);
+        assert_eq!(d.label(&d.edges[1], "ε"), "a, b, ε");
+        assert!(d.edges[0].is_loop());
+        assert!(d.has_reverse(&d.edges[1]));
+        assert!(!d.has_reverse(&d.edges[0]));
+    }
+
+    #[test]
+    fn dfa_tilde_is_an_ordinary_symbol() {
+        let mut tfn = HashMap::new();
+        tfn.insert((0, 'a'), 1);
+        tfn.insert((0, EPSILON), 1);
+        tfn.insert((0, 'λ'), 1);
+        tfn.insert((1, 'a'), 1);
+        tfn.insert((1, EPSILON), 1);
+        tfn.insert((1, 'λ'), 1);
+        let alphabet = HashSet::from(['a', 'λ', EPSILON]);
+        let dfa = DFA::new(2, 0, HashSet::from([1]), alphabet, tfn).unwrap();
+        let d = Diagram::from_dfa(&dfa);
+        // '~' sorts before 'λ' rather than last
+        assert_eq!(d.edges[0].symbols, ['a', EPSILON, 'λ']);
+        assert_eq!(d.label(&d.edges[0], "ε"), "a, ~, λ");
+    }
+}
@@ -0,0 +1,633 @@
+//! The textbook 5-tuple (Q, Σ, δ, q0, F). The plain-text form looks like
+//!
+//! ```text
+//! M = (Q, Σ, δ, q0, F) where
+//! Q = {q0, q1}
+//! Σ = {0, 1}
+//! δ is given by the table
+//!     | 0  | 1
+//!  q0 | q1 | q1
+//!  q1 | q0 | q0
+//! q0 is the start state
+//! F = {q0}
+//! ```
+//!
+//! and is read back by `parse_dfa` / `parse_nfa`. In an NFA table the cells are sets of states
+//! (`∅` when empty) and an `ε` column holds the epsilon transitions. Symbols that would be
+//! ambiguous in this syntax (`,`, `{`, `}`, `|`, `'` and whitespace) are written quoted, e.g. `','`.
+//! The LaTeX form is export-only.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::dfa::{DFA, DFATypeError};
+use crate::nfa::{EPSILON, NFA, NFATypeError};
+
+#[derive(Debug)]
+pub enum FormalParseError {
+    MissingSection(&'static str),
+    Syntax { line: usize, message: String },
+    UnknownState { line: usize, name: String },
+    UnknownSymbol { line: usize, symbol: String },
+    InvalidDFA(DFATypeError),
+    InvalidNFA(NFATypeError),
+}
+
+fn state_name(s: usize) -> String {
+    format!("q{s}")
+}
+
+pub(crate) fn symbol_text(c: char) -> String {
+    // `ε` is quoted so that it does not read back as the epsilon column
+    if c.is_whitespace() || ",{}|'ε".contains(c) {
+        format!("'{c}'")
+    } else {
+        c.to_string()
+    }
+}
+
+fn set_text(items: impl IntoIterator<Item = String>) -> String {
+    let items: Vec<String> = items.into_iter().collect();
+    if items.is_empty() {
+        return "∅".to_string();
+    }
+    format!("{{{}}}", items.join(", "))
+}
+
+fn sorted<T: Ord + Copy>(items: &HashSet<T>) -> Vec<T> {
+    let mut items: Vec<T> = items.iter().copied().collect();
+    items.sort();
+    items
+}
+
+fn render_table(header: Vec<String>, rows: Vec<Vec<String>>) -> String {
+    let columns = header.len();
+    let widths: Vec<usize> = (0..columns)
+        .map(|i| {
+            rows.iter()
+                .chain(std::iter::once(&header))
+                .map(|r| r[i].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+    let line = |cells: &Vec<String>| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .enumerate()
+            .map(|(i, (c, &w))| {
+                if i == 0 {
+                    format!("{c:>w$}")
+                } else {
+                    format!("{c:<w$}")
+                }
+            })
+            .collect();
+        if columns == 1 {
+            // a lone separator still marks the line as part of the table
+            format!(" {} |", padded.join(" | "))
+        } else {
+            format!(" {}", padded.join(" | ")).trim_end().to_string()
+        }
+    };
+    let mut out = line(&header);
+    for r in &rows {
+        out.push('\n');
+        out.push_str(&line(r));
+    }
+    out
+}
+
+struct Tuple {
+    states: usize,
+    alphabet: Vec<char>,
+    start: usize,
+    accept: Vec<usize>,
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+fn tuple_text(t: &Tuple) -> String {
+    let mut out = format!("M = (Q, Σ, δ, {}, F) where\n", state_name(t.start));
+    out += &format!("Q = {}\n", set_text((0..t.states).map(state_name)));
+    out += &format!(
+        "Σ = {}\n",
+        set_text(t.alphabet.iter().map(|&c| symbol_text(c)))
+    );
+    out += "δ is given by the table\n";
+    out += &render_table(t.header.clone(), t.rows.clone());
+    out += &format!("\n{} is the start state\n", state_name(t.start));
+    out += &format!(
+        "F = {}\n",
+        set_text(t.accept.iter().map(|&s| state_name(s)))
+    );
+    out
+}
+
+fn dfa_tuple(dfa: &DFA) -> Tuple {
+    let alphabet = sorted(&dfa.alphabet);
+    let mut header = vec![String::new()];
+    header.extend(alphabet.iter().map(|&c| symbol_text(c)));
+    let rows = (0..dfa.states.len())
+        .map(|s| {
+            let mut row = vec![state_name(s)];
+            row.extend(alphabet.iter().map(|&c| state_name(dfa.tfn[&(s, c)])));
+            row
+        })
+        .collect();
+    Tuple {
+        states: dfa.states.len(),
+        start: dfa.start,
+        accept: sorted(&dfa.accept),
+        alphabet,
+        header,
+        rows,
+    }
+}
+
+fn nfa_tuple(nfa: &NFA) -> Tuple {
+    let alphabet: Vec<char> = sorted(&nfa.alphabet)
+        .into_iter()
+        .filter(|&c| c != EPSILON)
+        .collect();
+    let has_epsilon = nfa.tfn.keys().any(|&(_, c)| c == EPSILON);
+    let mut columns = alphabet.clone();
+    if has_epsilon {
+        columns.push(EPSILON);
+    }
+    let mut header = vec![String::new()];
+    header.extend(columns.iter().map(|&c| {
+        if c == EPSILON {
+            "ε".to_string()
+        } else {
+            symbol_text(c)
+        }
+    }));
+    let rows = (0..nfa.states.len())
+        .map(|s| {
+            let mut row = vec![state_name(s)];
+            row.extend(columns.iter().map(|&c| {
+                let targets = nfa.tfn.get(&(s, c)).map(sorted).unwrap_or_default();
+                set_text(targets.into_iter().map(state_name))
+            }));
+            row
+        })
+        .collect();
+    Tuple {
+        states: nfa.states.len(),
+        start: nfa.start,
+        accept: sorted(&nfa.accept),
+        alphabet,
+        header,
+        rows,
+    }
+}
+
+pub fn dfa_to_formal(dfa: &DFA) -> String {
+    tuple_text(&dfa_tuple(dfa))
+}
+
+pub fn nfa_to_formal(nfa: &NFA) -> String {
+    tuple_text(&nfa_tuple(nfa))
+}
+
+pub(crate) fn latex_escape(s: &str) -> String {
+    s.chars()
+        .map(|c| match c {
+            '{' | '}' | '_' | '#' | '%' | '&' | '$' => format!("\\{c}"),
+            '\\' => "\\backslash ".to_string(),
+            '~' => "\\sim ".to_string(),
+            '^' => "\\hat{}".to_string(),
+            ' ' => "\\text{\\textvisiblespace}".to_string(),
+            _ => c.to_string(),
+        })
+        .collect()
+}
+
+fn latex_state(s: usize) -> String {
+    format!("q_{{{s}}}")
+}
+
+fn latex_set(items: Vec<String>) -> String {
+    if items.is_empty() {
+        return "\\emptyset".to_string();
+    }
+    format!("\\{{{}\\}}", items.join(", "))
+}
+
+fn tuple_latex(t: &Tuple, cell: impl Fn(&str) -> String) -> String {
+    let mut out = format!(
+        "$M = (Q, \\Sigma, \\delta, {}, F)$ where\n\\[\n",
+        latex_state(t.start)
+    );
+    out += &format!(
+        "Q = {}, \\quad \\Sigma = {}, \\quad F = {}\n\\]\n",
+        latex_set((0..t.states).map(latex_state).collect()),
+        latex_set(
+            t.alphabet
+                .iter()
+                .map(|c| latex_escape(&c.to_string()))
+                .collect()
+        ),
+        latex_set(t.accept.iter().map(|&s| latex_state(s)).collect()),
+    );
+    out += "and $\\delta$ is given by\n\\[\n";
+    out += &format!("\\begin{{array}}{{c|{}}}\n", "c".repeat(t.header.len() - 1));
+    let header: Vec<String> = t.header[1..]
+        .iter()
+        .map(|h| match h.as_str() {
+            "ε" => "\\varepsilon".to_string(),
+            h => latex_escape(unquote(h)),
+        })
+        .collect();
+    out += &format!("\\delta & {} \\\\ \\hline\n", header.join(" & "));
+    for (s, row) in t.rows.iter().enumerate() {
+        let cells: Vec<String> = row[1..].iter().map(|c| cell(c)).collect();
+        out += &format!("{} & {} \\\\\n", latex_state(s), cells.join(" & "));
+    }
+    out += "\\end{array}\n\\]\n";
+    out
+}
+
+/// A header symbol without the one pair of quotes `symbol_text` may have put around it.
+fn unquote(h: &str) -> &str {
+    match h.strip_prefix('\'').and_then(|h| h.strip_suffix('\'')) {
+        Some(inner) if !inner.is_empty() => inner,
+        _ => h,
+    }
+}
+
+// cells are rendered from the plain-text tuple: `qN`, `{qN, ...}` or `∅`
+fn latex_cell(cell: &str) -> String {
+    let names = |s: &str| -> Vec<String> {
+        split_outside_quotes(s, ',')
+            .iter()
+            .map(|n| latex_state(n.trim().trim_start_matches('q').parse().unwrap()))
+            .collect()
+    };
+    match cell {
+        "∅" => "\\emptyset".to_string(),
+        c if c.starts_with('{') => latex_set(names(&c[1..c.len() - 1])),
+        c => names(c).remove(0),
+    }
+}
+
+pub fn dfa_to_latex(dfa: &DFA) -> String {
+    tuple_latex(&dfa_tuple(dfa), latex_cell)
+}
+
+pub fn nfa_to_latex(nfa: &NFA) -> String {
+    tuple_latex(&nfa_tuple(nfa), latex_cell)
+}
+
+pub(crate) fn split_outside_quotes(s: &str, sep: char) -> Vec<String> {
+    let mut parts = vec![String::new()];
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c == '\'' {
+            // a quoted symbol is exactly three characters, 'x'
+            let inner = chars.next();
+            let close = chars.next();
+            let last = parts.last_mut().unwrap();
+            last.push(c);
+            last.extend(inner);
+            last.extend(close);
+        } else if c == sep {
+            parts.push(String::new());
+        } else {
+            parts.last_mut().unwrap().push(c);
+        }
+    }
+    parts
+}
+
+struct Parser {
+    states: HashMap<String, usize>,
+    symbols: Vec<char>,
+}
+
+fn syntax(line: usize, message: &str) -> FormalParseError {
+    FormalParseError::Syntax {
+        line,
+        message: message.to_string(),
+    }
+}
+
+fn parse_set(line: usize, text: &str) -> Result<Vec<String>, FormalParseError> {
+    let text = text.trim();
+    if text == "∅" {
+        return Ok(Vec::new());
+    }
+    let inner = text
+        .strip_prefix('{')
+        .and_then(|t| t.strip_suffix('}'))
+        .ok_or_else(|| syntax(line, "expected a set in braces"))?;
+    if inner.trim().is_empty() {
+        return Ok(Vec::new());
+    }
+    Ok(split_outside_quotes(inner, ',')
+        .into_iter()
+        .map(|s| s.trim().to_string())
+        .collect())
+}
+
+fn parse_symbol(line: usize, text: &str) -> Result<char, FormalParseError> {
+    let chars: Vec<char> = text.chars().collect();
+    match chars[..] {
+        [c] => Ok(c),
+        ['\'', c, '\''] => Ok(c),
+        _ => Err(FormalParseError::UnknownSymbol {
+            line,
+            symbol: text.to_string(),
+        }),
+    }
+}
+
+impl Parser {
+    fn state(&self, line: usize, name: &str) -> Result<usize, FormalParseError> {
+        self.states
+            .get(name.trim())
+            .copied()
+            .ok_or_else(|| FormalParseError::UnknownState {
+                line,
+                name: name.trim().to_string(),
+            })
+    }
+}
+
+/// The pieces of a parsed tuple; table cells are left as text for the caller to interpret.
+struct ParsedTuple {
+    parser: Parser,
+    start: usize,
+    accept: HashSet<usize>,
+    columns: Vec<Option<char>>,             // None is the epsilon column
+    rows: Vec<(usize, usize, Vec<String>)>, // (line, state, cells)
+}
+
+fn parse_tuple(text: &str) -> Result<ParsedTuple, FormalParseError> {
+    let lines: Vec<(usize, &str)> = text
+        .lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.trim()))
+        .filter(|(_, l)| !l.is_empty())
+        .collect();
+
+    // `<name> = <rest>`, so a table row for a state named e.g. `F1` is not mistaken for F
+    let find = |prefixes: &[&str]| {
+        lines.iter().find_map(|&(n, l)| {
+            prefixes
+                .iter()
+                .find_map(|p| l.strip_prefix(p)?.trim_start().strip_prefix('='))
+                .map(|rest| (n, rest.trim()))
+        })
+    };
+
+    let (q_line, q) = find(&["Q"]).ok_or(FormalParseError::MissingSection("Q"))?;
+    let names = parse_set(q_line, q)?;
+    let mut states = HashMap::new();
+    for name in names {
+        let next = states.len();
+        if states.insert(name, next).is_some() {
+            return Err(syntax(q_line, "state listed twice"));
+        }
+    }
+
+    let (s_line, sigma) = find(&["Σ", "Sigma"]).ok_or(FormalParseError::MissingSection("Σ"))?;
+    let symbols = parse_set(s_line, sigma)?
+        .iter()
+        .map(|s| parse_symbol(s_line, s))
+        .collect::<Result<Vec<_>, _>>()?;
+    let parser = Parser { states, symbols };
+
+    let (f_line, f) = find(&["F"]).ok_or(FormalParseError::MissingSection("F"))?;
+    let accept = parse_set(f_line, f)?
+        .iter()
+        .map(|s| parser.state(f_line, s))
+        .collect::<Result<HashSet<_>, _>>()?;
+
+    let (start_line, start) = lines
+        .iter()
+        .find_map(|&(n, l)| l.strip_suffix("is the start state").map(|s| (n, s)))
+        .ok_or(FormalParseError::MissingSection("start state"))?;
+    let start = parser.state(start_line, start)?;
+
+    // the table is the run of lines containing `|` after the δ line
+    let delta = lines
+        .iter()
+        .position(|(_, l)| l.starts_with('δ') || l.starts_with("delta"))
+        .ok_or(FormalParseError::MissingSection("δ"))?;
+    // a table without symbol columns ends every line in a lone `|`
+    let mut table = lines[delta + 1..]
+        .iter()
+        .take_while(|(_, l)| l.contains('|'))
+        .map(|&(n, l)| (n, l.strip_suffix('|').unwrap_or(l)));
+    let (h_line, header) = table
+        .next()
+        .ok_or_else(|| syntax(lines[delta].0, "expected a transition table"))?;
+    let columns = split_outside_quotes(header, '|')
+        .iter()
+        .skip(1)
+        .map(|c| match c.trim() {
+            "ε" | "eps" => Ok(None),
+            c => {
+                let c = parse_symbol(h_line, c)?;
+                if parser.symbols.contains(&c) {
+                    Ok(Some(c))
+                } else {
+                    Err(FormalParseError::UnknownSymbol {
+                        line: h_line,
+                        symbol: c.to_string(),
+                    })
+                }
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let rows = table
+        .map(|(n, l)| {
+            let cells = split_outside_quotes(l, '|');
+            if cells.len() != columns.len() + 1 {
+                return Err(syntax(n, "row has the wrong number of cells"));
+            }
+            let state = parser.state(n, &cells[0])?;
+            Ok((
+                n,
+                state,
+                cells[1..].iter().map(|c| c.trim().to_string()).collect(),
+            ))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(ParsedTuple {
+        parser,
+        start,
+        accept,
+        columns,
+        rows,
+    })
+}
+
+pub fn parse_dfa(text: &str) -> Result<DFA, FormalParseError> {
+    let t = parse_tuple(text)?;
+    let mut tfn = HashMap::new();
+    for (line, state, cells) in &t.rows {
+        for (column, cell) in t.columns.iter().zip(cells) {
+            let c = column.ok_or_else(|| syntax(*line, "a DFA has no ε transitions"))?;
+            tfn.insert((*state, c), t.parser.state(*line, cell)?);
+        }
+    }
+    DFA::new(
+        t.parser.states.len(),
+        t.start,
+        t.accept,
+        t.parser.symbols.into_iter().collect(),
+        tfn,
+    )
+    .map_err(FormalParseError::InvalidDFA)
+}
+
+pub fn parse_nfa(text: &str) -> Result<NFA, FormalParseError> {
+    let t = parse_tuple(text)?;
+    let mut tfn: HashMap<(usize, char), HashSet<usize>> = HashMap::new();
+    for (line, state, cells) in &t.rows {
+        for (column, cell) in t.columns.iter().zip(cells) {
+            let targets = parse_set(*line, cell)?
+                .iter()
+                .map(|s| t.parser.state(*line, s))
+                .collect::<Result<HashSet<_>, _>>()?;
+            if !targets.is_empty() {
+                tfn.insert((*state, column.unwrap_or(EPSILON)), targets);
+            }
+        }
+    }
+    NFA::new(
+        t.parser.states.len(),
+        t.start,
+        t.accept,
+        t.parser.symbols.into_iter().collect(),
+        tfn,
+    )
+    .map_err(FormalParseError::InvalidNFA)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::SimulationResult;
+    use crate::fixtures::even_length;
+    use fsim_macros::dfa;
+
+    #[test]
+    fn dfa_formal_text() {
+        let expected = "\
+M = (Q, Σ, δ, q0, F) where
+Q = {q0, q1}
+Σ = {0, 1}
+δ is given by the table
+    | 0  | 1
+ q0 | q1 | q1
+ q1 | q0 | q0
+q0 is the start state
+F = {q0}
+";
+        assert_eq!(dfa_to_formal(&even_length()), expected);
+    }
+
+    #[test]
+    fn dfa_round_trips() {
+        let text = dfa_to_formal(&even_length());
+        let dfa = parse_dfa(&text).unwrap();
+        assert_eq!(dfa_to_formal(&dfa), text);
+        assert!(matches!(dfa.simulate("10"), Ok(SimulationResult::Accepted)));
+    }
+
+    #[test]
+    fn nfa_round_trips_with_epsilon_and_quoted_symbols() {
+        let mut tfn = HashMap::new();
+        tfn.insert((0, EPSILON), HashSet::from([1]));
+        tfn.insert((1, ','), HashSet::from([0, 1]));
+        tfn.insert((1, 'a'), HashSet::from([2]));
+        let nfa = NFA::new(3, 0, HashSet::from([2]), HashSet::from([',', 'a']), tfn).unwrap();
+        let text = nfa_to_formal(&nfa);
+        assert!(text.contains("Σ = {',', a}"));
+        assert!(text.contains(" q1 | {q0, q1} | {q2} | ∅"));
+        let back = parse_nfa(&text).unwrap();
+        assert_eq!(nfa_to_formal(&back), text);
+    }
+
+    #[test]
+    fn parse_accepts_hand_written_names() {
+        let text = "
+            Q = {even, odd}
+            Sigma = {a}
+            delta:
+                 | a
+            even | odd
+            odd  | even
+            even is the start state
+            F = {odd}
+        ";
+        let dfa = parse_dfa(text).unwrap();
+        assert!(matches!(
+            dfa.simulate("aaa"),
+            Ok(SimulationResult::Accepted)
+        ));
+    }
+
+    #[test]
+    fn parse_reports_errors() {
+        let text = dfa_to_formal(&even_length()).replace(" q1 | q0 | q0", " q1 | q0 | q7");
+        assert!(matches!(
+            parse_dfa(&text),
+            Err(FormalParseError::UnknownState { line: 7, .. })
+        ));
+        let text = dfa_to_formal(&even_length()).replace(" q1 | q0 | q0\n", "");
+        assert!(matches!(
+            parse_dfa(&text),
+            Err(FormalParseError::InvalidDFA(
+                DFATypeError::NonTotalTransitionFunction
+            ))
+        ));
+        assert!(matches!(
+            parse_dfa("Q = {a}"),
+            Err(FormalParseError::MissingSection("Σ"))
+        ));
+    }
+
+    #[test]
+    fn latex_uses_array_table() {
+        let latex = dfa_to_latex(&even_length());
+        assert!(latex.starts_with("$M = (Q, \\Sigma, \\delta, q_{0}, F)$ where"));
+        assert!(latex.contains("Q = \\{q_{0}, q_{1}\\}"));
+        assert!(latex.contains("\\delta & 0 & 1 \\\\ \\hline"));
+        assert!(latex.contains("q_{1} & q_{0} & q_{0} \\\\"));
+    }
+    #[test]
+    fn empty_alphabet_and_awkward_symbols_round_trip() {
+        let empty = dfa! {
+            states: 2,
+            start: 1,
+            accept: [0],
+            alphabet: [],
+            transitions: {},
+        };
+        let text = dfa_to_formal(&empty);
+        assert!(text.contains("δ is given by the table\n    |\n q0 |\n q1 |\n"));
+        assert_eq!(dfa_to_formal(&parse_dfa(&text).unwrap()), text);
+
+        let awkward = dfa! {
+            states: 1,
+            start: 0,
+            accept: [0],
+            alphabet: ['ε', '\''],
+            transitions: {
+                0 => { 'ε' | '\'' => 0 },
+            },
+        };
+        let text = dfa_to_formal(&awkward);
+        assert!(text.contains("Σ = {''', 'ε'}"));
+        assert_eq!(dfa_to_formal(&parse_dfa(&text).unwrap()), text);
+        let latex = dfa_to_latex(&awkward);
+        assert!(latex.contains("\\delta & ' & ε \\\\ \\hline"));
+    }
+}
@@ -0,0 +1,119 @@
+//! Shareable text form of the binary encoding: unpadded URL-safe base64, suitable for query
+//! strings and chat messages. Links are decoded with the default `DecodeOptions`; decode
+//! `base64_decode`'s bytes with `binary::decode_dfa_with` to choose other limits.
+
+use crate::dfa::DFA;
+use crate::formats::binary::{self, DecodeError};
+use crate::nfa::NFA;
+
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+pub fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..=chunk.len() {
+            out.push(ALPHABET[(n >> (18 - 6 * i) & 0x3f) as usize] as char);
+        }
+    }
+    out
+}
+
+pub fn base64_decode(text: &str) -> Result<Vec<u8>, DecodeError> {
+    let digits = text
+        .bytes()
+        .map(|c| {
+            ALPHABET
+                .iter()
+                .position(|&a| a == c)
+                .map(|d| d as u32)
+                .ok_or(DecodeError::InvalidBase64)
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    let mut out = Vec::with_capacity(digits.len() * 3 / 4);
+    for chunk in digits.chunks(4) {
+        if chunk.len() == 1 {
+            return Err(DecodeError::InvalidBase64);
+        }
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &d)| n | d << (18 - 6 * i));
+        let len = chunk.len() - 1;
+        // leftover bits in a partial chunk must be zero, or two texts would decode the same
+        if n & ((1 << (8 * (3 - len))) - 1) != 0 {
+            return Err(DecodeError::InvalidBase64);
+        }
+        out.extend((0..len).map(|i| (n >> (16 - 8 * i)) as u8));
+    }
+    Ok(out)
+}
+
+pub fn dfa_to_link(dfa: &DFA) -> String {
+    base64_encode(&binary::encode_dfa(dfa))
+}
+
+pub fn dfa_from_link(link: &str) -> Result<DFA, DecodeError> {
+    binary::decode_dfa(&base64_decode(link)?)
+}
+
+pub fn nfa_to_link(nfa: &NFA) -> String {
+    base64_encode(&binary::encode_nfa(nfa))
+}
+
+pub fn nfa_from_link(link: &str) -> Result<NFA, DecodeError> {
+    binary::decode_nfa(&base64_decode(link)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn base64_matches_known_vectors() {
+        assert_eq!(base64_encode(b""), "");
+        assert_eq!(base64_encode(b"f"), "Zg");
+        assert_eq!(base64_encode(b"fo"), "Zm8");
+        assert_eq!(base64_encode(b"foo"), "Zm9v");
+        assert_eq!(base64_encode(&[0xfb, 0xff]), "-_8");
+        for s in ["", "f", "fo", "foo", "foob", "fooba", "foobar"] {
+            assert_eq!(
+                base64_decode(&base64_encode(s.as_bytes())).unwrap(),
+                s.as_bytes()
+            );
+        }
+        assert!(matches!(
+            base64_decode("Zm9v="),
+            Err(DecodeError::InvalidBase64)
+        ));
+        assert!(matches!(
+            base64_decode("Z"),
+            Err(DecodeError::InvalidBase64)
+        ));
+        assert!(matches!(
+            base64_decode("Zh"),
+            Err(DecodeError::InvalidBase64)
+        ));
+    }
+
+    #[test]
+    fn link_round_trips() {
+        let mut tfn = HashMap::new();
+        tfn.insert((0, 'a'), HashSet::from([0, 1]));
+        let nfa = NFA::new(2, 0, HashSet::from([1]), HashSet::from(['a']), tfn).unwrap();
+        let link = nfa_to_link(&nfa);
+        assert!(
+            link.chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+        );
+        let back = nfa_from_link(&link).unwrap();
+        assert_eq!(nfa_to_link(&back), link);
+        assert!(dfa_from_link(&link).is_err());
+        assert!(nfa_from_link(&link[..link.len() - 2]).is_err());
+    }
+}
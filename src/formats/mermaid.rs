@@ -0,0 +1,430 @@
+//! Mermaid `stateDiagram-v2` export and import.
+//!
+//! Exported diagrams list the alphabet in a `%% alphabet: ...` comment, mark the start state with
+//! `[*] -->`, put a `note right of q : accepting` on every accept state, and carry one transition
+//! per pair of states with its symbols joined by `", "` (`ε` for an NFA's epsilon moves, and the
+//! same quoting as the formal definition for awkward symbols).
+//!
+//! The importer reads the subset of the syntax that describes a finite automaton: transitions
+//! `a --> b : symbols`, one `[*] --> a` start marker, accept states given either as `a --> [*]`
+//! or by a note reading `accept` or `accepting`, and `state` declarations. Comments, `direction`,
+//! `classDef` and `class` lines are skipped; anything else (composite states, forks, choices) is
+//! rejected. When every state is named `q<N>` for `N` in `0..n` the numbering is kept, so an
+//! export reads back as the same automaton; otherwise states are numbered in order of first
+//! appearance. Without an alphabet comment the alphabet is the set of symbols used on edges.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt::Write;
+
+use crate::dfa::{DFA, DFATypeError};
+use crate::formats::diagram::Diagram;
+use crate::formats::formal::{split_outside_quotes, symbol_text};
+use crate::nfa::{EPSILON, NFA, NFATypeError};
+
+#[derive(Debug)]
+pub enum DiagramParseError {
+    MissingHeader,
+    MissingStart,
+    MultipleStarts { line: usize },
+    MissingLabel { line: usize },
+    InvalidSymbol { line: usize, symbol: String },
+    Unsupported { line: usize, text: String },
+    InvalidDFA(DFATypeError),
+    InvalidNFA(NFATypeError),
+}
+
+/// Joins an edge's symbols for a label. `epsilon` says whether `EPSILON` stands for an epsilon
+/// move (NFAs) or is an ordinary symbol (DFAs); a symbol `ε` is quoted so it stays one.
+pub(crate) fn diagram_label(symbols: &[char], epsilon: bool) -> String {
+    symbols
+        .iter()
+        .map(|&c| {
+            if epsilon && c == EPSILON {
+                "ε".to_string()
+            } else {
+                symbol_text(c)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn diagram_to_mermaid(d: &Diagram, alphabet: &HashSet<char>) -> String {
+    let mut out = String::from("stateDiagram-v2\n    direction LR\n");
+    let mut symbols: Vec<char> = alphabet
+        .iter()
+        .copied()
+        .filter(|&c| !d.epsilon || c != EPSILON)
+        .collect();
+    symbols.sort();
+    writeln!(out, "    %% alphabet: {}", diagram_label(&symbols, false)).unwrap();
+    writeln!(out, "    [*] --> q{}", d.start).unwrap();
+    for e in &d.edges {
+        writeln!(
+            out,
+            "    q{} --> q{} : {}",
+            e.from,
+            e.to,
+            diagram_label(&e.symbols, d.epsilon)
+        )
+        .unwrap();
+    }
+    // states without edges still need to appear
+    for s in 0..d.states {
+        if s != d.start && !d.edges.iter().any(|e| e.from == s || e.to == s) {
+            writeln!(out, "    state q{s}").unwrap();
+        }
+    }
+    for s in &d.accept {
+        writeln!(out, "    note right of q{s} : accepting").unwrap();
+    }
+    out
+}
+
+pub fn dfa_to_mermaid(dfa: &DFA) -> String {
+    diagram_to_mermaid(&Diagram::from_dfa(dfa), &dfa.alphabet)
+}
+
+pub fn nfa_to_mermaid(nfa: &NFA) -> String {
+    diagram_to_mermaid(&Diagram::from_nfa(nfa), &nfa.alphabet)
+}
+
+struct ParsedDiagram {
+    states: usize,
+    start: usize,
+    accept: HashSet<usize>,
+    alphabet: HashSet<char>,
+    /// `None` marks an epsilon move.
+    edges: Vec<(usize, Option<char>, usize)>,
+}
+
+fn parse_label_symbol(line: usize, text: &str) -> Result<Option<char>, DiagramParseError> {
+    let chars: Vec<char> = text.chars().collect();
+    match chars[..] {
+        ['ε'] => Ok(None),
+        [c] => Ok(Some(c)),
+        ['\'', c, '\''] => Ok(Some(c)),
+        _ => Err(DiagramParseError::InvalidSymbol {
+            line,
+            symbol: text.to_string(),
+        }),
+    }
+}
+
+fn parse_mermaid(text: &str) -> Result<ParsedDiagram, DiagramParseError> {
+    let mut declared_alphabet = None;
+    let mut lines = text
+        .lines()
+        .enumerate()
+        .map(|(i, l)| (i + 1, l.trim()))
+        .filter(|(_, l)| !l.is_empty());
+
+    match lines.next() {
+        Some((_, "stateDiagram-v2" | "stateDiagram")) => {}
+        _ => return Err(DiagramParseError::MissingHeader),
+    }
+
+    let mut names: Vec<String> = Vec::new();
+    let mut ids: HashMap<String, usize> = HashMap::new();
+    let mut id = |name: &str| {
+        *ids.entry(name.to_string()).or_insert_with(|| {
+            names.push(name.to_string());
+            names.len() - 1
+        })
+    };
+    let mut start = None;
+    let mut accept = HashSet::new();
+    let mut alphabet = HashSet::new();
+    let mut edges = Vec::new();
+
+    for (n, l) in lines {
+        let unsupported = || DiagramParseError::Unsupported {
+            line: n,
+            text: l.to_string(),
+        };
+        if let Some(comment) = l.strip_prefix("%%") {
+            if let Some(symbols) = comment.trim().strip_prefix("alphabet:") {
+                let mut declared = HashSet::new();
+                for symbol in split_outside_quotes(symbols.trim(), ',') {
+                    let symbol = symbol.trim();
+                    if symbol.is_empty() {
+                        continue;
+                    }
+                    match parse_label_symbol(n, symbol)? {
+                        Some(c) => declared.insert(c),
+                        None => return Err(unsupported()),
+                    };
+                }
+                declared_alphabet = Some(declared);
+            }
+            continue;
+        }
+        if l.starts_with("direction ") || l.starts_with("classDef ") || l.starts_with("class ") {
+            continue;
+        }
+        if let Some(rest) = l.strip_prefix("note ") {
+            let (target, note) = rest.split_once(':').ok_or_else(unsupported)?;
+            let state = ["right of ", "left of "]
+                .iter()
+                .find_map(|p| target.trim().strip_prefix(p))
+                .ok_or_else(unsupported)?;
+            let state = id(state.trim());
+            if matches!(note.trim().to_lowercase().as_str(), "accept" | "accepting") {
+                accept.insert(state);
+            }
+            continue;
+        }
+        if let Some(rest) = l.strip_prefix("state ") {
+            // `state q1` or `state "description" as q1`
+            let name = match rest.rsplit_once(" as ") {
+                Some((_, name)) => name,
+                None => rest,
+            };
+            if name.contains(['{', '<', '"']) {
+                return Err(unsupported());
+            }
+            id(name.trim());
+            continue;
+        }
+
+        let (arrow, label) = match l.split_once(':') {
+            Some((arrow, label)) => (arrow, Some(label.trim())),
+            None => (l, None),
+        };
+        let (from, to) = arrow.split_once("-->").ok_or_else(unsupported)?;
+        let (from, to) = (from.trim(), to.trim());
+        if from.is_empty() || to.is_empty() || from.contains(' ') || to.contains(' ') {
+            return Err(unsupported());
+        }
+        match (from, to) {
+            ("[*]", "[*]") => return Err(unsupported()),
+            ("[*]", to) => {
+                if start.replace(id(to)).is_some() {
+                    return Err(DiagramParseError::MultipleStarts { line: n });
+                }
+            }
+            (from, "[*]") => {
+                accept.insert(id(from));
+            }
+            (from, to) => {
+                let (from, to) = (id(from), id(to));
+                let label = label
+                    .filter(|l| !l.is_empty())
+                    .ok_or(DiagramParseError::MissingLabel { line: n })?;
+                for symbol in split_outside_quotes(label, ',') {
+                    let c = parse_label_symbol(n, symbol.trim())?;
+                    alphabet.extend(c);
+                    edges.push((from, c, to));
+                }
+            }
+        }
+    }
+
+    let mut start = start.ok_or(DiagramParseError::MissingStart)?;
+    if let Some(order) = exported_numbering(&names) {
+        start = order[start];
+        accept = accept.into_iter().map(|s| order[s]).collect();
+        for (from, _, to) in &mut edges {
+            (*from, *to) = (order[*from], order[*to]);
+        }
+    }
+    if let Some(declared) = declared_alphabet {
+        alphabet.extend(declared);
+    }
+
+    Ok(ParsedDiagram {
+        states: names.len(),
+        start,
+        accept,
+        alphabet,
+        edges,
+    })
+}
+
+/// Maps first-appearance ids to `N` when the names are exactly `q0`, ..., `q<n-1>` in some order.
+fn exported_numbering(names: &[String]) -> Option<Vec<usize>> {
+    let mut seen = vec![false; names.len()];
+    let mut order = Vec::with_capacity(names.len());
+    for name in names {
+        let n: usize = name.strip_prefix('q')?.parse().ok()?;
+        if format!("q{n}") != *name || n >= names.len() || std::mem::replace(&mut seen[n], true) {
+            return None;
+        }
+        order.push(n);
+    }
+    Some(order)
+}
+
+/// Reads a Mermaid state diagram as an `NFA`, with `ε` labels as epsilon moves.
+pub fn mermaid_to_nfa(text: &str) -> Result<NFA, DiagramParseError> {
+    let p = parse_mermaid(text)?;
+    let mut tfn: HashMap<(usize, char), HashSet<usize>> = HashMap::new();
+    for (from, c, to) in p.edges {
+        tfn.entry((from, c.unwrap_or(EPSILON)))
+            .or_default()
+            .insert(to);
+    }
+    NFA::new(p.states, p.start, p.accept, p.alphabet, tfn).map_err(DiagramParseError::InvalidNFA)
+}
+
+/// Reads a Mermaid state diagram as a `DFA`; it must be deterministic and total, and `~` is an
+/// ordinary symbol.
+pub fn mermaid_to_dfa(text: &str) -> Result<DFA, DiagramParseError> {
+    let p = parse_mermaid(text)?;
+    let invalid = || DiagramParseError::InvalidDFA(DFATypeError::InvalidTransitionFunction);
+    let mut tfn = HashMap::new();
+    for (from, c, to) in p.edges {
+        let c = c.ok_or_else(invalid)?;
+        if tfn.insert((from, c), to).is_some_and(|prev| prev != to) {
+            return Err(invalid());
+        }
+    }
+    DFA::new(p.states, p.start, p.accept, p.alphabet, tfn).map_err(DiagramParseError::InvalidDFA)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::SimulationResult;
+    use crate::fixtures::even_length;
+    use fsim_macros::{dfa, nfa};
+
+    #[test]
+    fn dfa_to_mermaid_text() {
+        let expected = "\
+stateDiagram-v2
+    direction LR
+    %% alphabet: 0, 1
+    [*] --> q0
+    q0 --> q1 : 0, 1
+    q1 --> q0 : 0, 1
+    note right of q0 : accepting
+";
+        assert_eq!(dfa_to_mermaid(&even_length()), expected);
+    }
+
+    #[test]
+    fn mermaid_round_trips() {
+        let text = dfa_to_mermaid(&even_length());
+        let dfa = mermaid_to_dfa(&text).unwrap();
+        assert_eq!(dfa_to_mermaid(&dfa), text);
+
+        let mut tfn = HashMap::new();
+        tfn.insert((0, EPSILON), HashSet::from([1]));
+        tfn.insert((1, ','), HashSet::from([1]));
+        let nfa = NFA::new(3, 0, HashSet::from([1]), HashSet::from([',']), tfn).unwrap();
+        let text = nfa_to_mermaid(&nfa);
+        assert!(text.contains("q0 --> q1 : ε\n"));
+        assert!(text.contains("q1 --> q1 : ','\n"));
+        assert!(text.contains("state q2\n"));
+        assert_eq!(nfa_to_mermaid(&mermaid_to_nfa(&text).unwrap()), text);
+    }
+
+    #[test]
+    fn numbering_alphabet_and_tilde_survive_a_round_trip() {
+        let dfa = dfa! {
+            states: 2,
+            start: 1,
+            accept: [0],
+            alphabet: ['a', '~'],
+            transitions: {
+                0 => { 'a' => 0, '~' => 1 },
+                1 => { 'a' => 1, '~' => 0 },
+            },
+        };
+        let text = dfa_to_mermaid(&dfa);
+        assert!(text.contains("q1 --> q0 : ~\n"));
+        let back = mermaid_to_dfa(&text).unwrap();
+        assert_eq!(back.start, 1);
+        assert_eq!(dfa_to_mermaid(&back), text);
+
+        let epsilon_symbol = dfa! {
+            states: 1,
+            start: 0,
+            accept: [0],
+            alphabet: ['ε'],
+            transitions: {
+                0 => { 'ε' => 0 },
+            },
+        };
+        let text = dfa_to_mermaid(&epsilon_symbol);
+        assert!(text.contains("q0 --> q0 : 'ε'\n"));
+        assert_eq!(dfa_to_mermaid(&mermaid_to_dfa(&text).unwrap()), text);
+
+        let nfa = nfa! {
+            states: 2,
+            start: 1,
+            accept: [0],
+            alphabet: ['a', 'b'],
+            transitions: {
+                1 => { '~' => [0] },
+            },
+        };
+        let back = mermaid_to_nfa(&nfa_to_mermaid(&nfa)).unwrap();
+        assert_eq!(back.start, 1);
+        assert_eq!(back.alphabet, nfa.alphabet);
+        assert!(back.tfn.contains_key(&(1, EPSILON)));
+    }
+
+    #[test]
+    fn only_accept_notes_mark_accepting_states() {
+        let text = "
+            stateDiagram-v2
+            [*] --> a
+            a --> b : x
+            note right of a : non-accepting
+            note left of b : Accepting
+        ";
+        let nfa = mermaid_to_nfa(text).unwrap();
+        assert_eq!(nfa.accept, HashSet::from([1]));
+    }
+
+    #[test]
+    fn hand_written_diagram_runs() {
+        let text = "
+            stateDiagram-v2
+            %% strings over {a, b} ending in b
+            [*] --> Idle
+            Idle --> Idle : a
+            Idle --> Seen : b
+            Seen --> Idle : a
+            Seen --> Seen : b
+            Seen --> [*]
+        ";
+        let dfa = mermaid_to_dfa(text).unwrap();
+        assert!(matches!(
+            dfa.simulate("aab"),
+            Ok(SimulationResult::Accepted)
+        ));
+        assert!(matches!(
+            dfa.simulate("aba"),
+            Ok(SimulationResult::Rejected)
+        ));
+    }
+
+    #[test]
+    fn unsupported_and_invalid_input_is_rejected() {
+        assert!(matches!(
+            mermaid_to_nfa("flowchart LR"),
+            Err(DiagramParseError::MissingHeader)
+        ));
+        assert!(matches!(
+            mermaid_to_nfa("stateDiagram-v2\n[*] --> a\nstate b {\n}"),
+            Err(DiagramParseError::Unsupported { line: 3, .. })
+        ));
+        assert!(matches!(
+            mermaid_to_nfa("stateDiagram-v2\na --> b : x"),
+            Err(DiagramParseError::MissingStart)
+        ));
+        assert!(matches!(
+            mermaid_to_nfa("stateDiagram-v2\n[*] --> a\na --> b"),
+            Err(DiagramParseError::MissingLabel { line: 3 })
+        ));
+        assert!(matches!(
+            mermaid_to_dfa("stateDiagram-v2\n[*] --> a\na --> b : x"),
+            Err(DiagramParseError::InvalidDFA(
+                DFATypeError::NonTotalTransitionFunction
+            ))
+        ));
+    }
+}
@@ -0,0 +1,13 @@
+pub mod binary;
+pub mod codegen;
+pub mod diagram;
+pub mod formal;
+pub mod link;
+pub mod mermaid;
+pub mod plantuml;
+pub mod svg;
+pub mod table;
+pub mod tikz;
+
+#[cfg(feature = "serde")]
+pub mod schema;
@@ -0,0 +1,67 @@
+//! PlantUML state-diagram export, laid out the same way as the Mermaid export.
+
+use std::fmt::Write;
+
+use crate::dfa::DFA;
+use crate::formats::diagram::Diagram;
+use crate::formats::mermaid::diagram_label;
+use crate::nfa::NFA;
+
+fn diagram_to_plantuml(d: &Diagram) -> String {
+    let mut out = String::from("@startuml\nhide empty description\nleft to right direction\n");
+    for s in 0..d.states {
+        writeln!(out, "state q{s}").unwrap();
+    }
+    writeln!(out, "[*] --> q{}", d.start).unwrap();
+    for e in &d.edges {
+        writeln!(
+            out,
+            "q{} --> q{} : {}",
+            e.from,
+            e.to,
+            diagram_label(&e.symbols, d.epsilon)
+        )
+        .unwrap();
+    }
+    for s in &d.accept {
+        writeln!(out, "note right of q{s} : accepting").unwrap();
+    }
+    out.push_str("@enduml\n");
+    out
+}
+
+pub fn dfa_to_plantuml(dfa: &DFA) -> String {
+    diagram_to_plantuml(&Diagram::from_dfa(dfa))
+}
+
+pub fn nfa_to_plantuml(nfa: &NFA) -> String {
+    diagram_to_plantuml(&Diagram::from_nfa(nfa))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nfa::EPSILON;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn nfa_to_plantuml_text() {
+        let mut tfn = HashMap::new();
+        tfn.insert((0, 'a'), HashSet::from([0, 1]));
+        tfn.insert((0, EPSILON), HashSet::from([1]));
+        let nfa = NFA::new(2, 0, HashSet::from([1]), HashSet::from(['a']), tfn).unwrap();
+        let expected = "\
+@startuml
+hide empty description
+left to right direction
+state q0
+state q1
+[*] --> q0
+q0 --> q0 : a
+q0 --> q1 : a, ε
+note right of q1 : accepting
+@enduml
+";
+        assert_eq!(nfa_to_plantuml(&nfa), expected);
+    }
+}
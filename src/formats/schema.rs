@@ -0,0 +1,468 @@
+//! Versioned on-disk representation of machines, available with the `serde` feature.
+//!
+//! A document looks like this in JSON (any serde format works the same way):
+//!
+//! ```json
+//! {
+//!   "schema": "fsim/1",
+//!   "kind": "nfa",
+//!   "states": 3,
+//!   "start": 0,
+//!   "accept": [2],
+//!   "alphabet": ["0", "1"],
+//!   "transitions": [{ "from": 0, "symbol": "1", "to": 1 }],
+//!   "epsilon": [{ "from": 1, "to": 2 }],
+//!   "metadata": { "name": "example" }
+//! }
+//! ```
+//!
+//! States are `0..states`. `transitions` lists one edge per target, so an NFA transition to
+//! several states appears several times. `epsilon` is always empty for a DFA. All lists are
+//! written sorted so that equal machines serialize identically.
+//!
+//! `DFA` and `NFA` serialize as documents without metadata, and loading one from a document
+//! that has metadata fails rather than dropping it; use `Document` to keep the metadata. A DFA
+//! may use `~` as an ordinary symbol, but an NFA's `~` moves are written only in `epsilon`.
+//!
+//! The `schema` tag selects the document version. Older versions stay readable: each one is a
+//! variant of `AnyDocument` with a conversion to the current `MachineDocument`, and new versions
+//! are added by bumping `SCHEMA_VERSION` and writing that conversion.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt;
+
+use serde::ser::SerializeStruct;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::dfa::{DFA, DFATypeError};
+use crate::nfa::{EPSILON, NFA, NFATypeError};
+
+pub const SCHEMA_VERSION: u32 = 1;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MachineKind {
+    Dfa,
+    Nfa,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct Transition {
+    pub from: usize,
+    pub symbol: char,
+    pub to: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+pub struct EpsilonEdge {
+    pub from: usize,
+    pub to: usize,
+}
+
+/// The current version of the schema. It always serializes with the current `schema` tag.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+pub struct MachineDocument {
+    pub kind: MachineKind,
+    pub states: usize,
+    pub start: usize,
+    pub accept: Vec<usize>,
+    pub alphabet: Vec<char>,
+    pub transitions: Vec<Transition>,
+    #[serde(default)]
+    pub epsilon: Vec<EpsilonEdge>,
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+}
+
+/// Every schema version that can be read, keyed by the `schema` tag.
+#[derive(Deserialize)]
+#[serde(tag = "schema")]
+pub enum AnyDocument {
+    #[serde(rename = "fsim/1")]
+    V1(MachineDocument),
+}
+
+impl AnyDocument {
+    pub fn migrate(self) -> MachineDocument {
+        match self {
+            AnyDocument::V1(doc) => doc,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum SchemaError {
+    WrongKind {
+        expected: MachineKind,
+    },
+    DuplicateTransition {
+        from: usize,
+        symbol: char,
+    },
+    /// A `~` transition loaded into an NFA, where it would silently become an epsilon move.
+    EpsilonInTransitions {
+        from: usize,
+    },
+    /// Metadata in a document loaded as a bare machine, which has nowhere to keep it.
+    UnexpectedMetadata,
+    InvalidDFA(DFATypeError),
+    InvalidNFA(NFATypeError),
+}
+
+impl fmt::Display for SchemaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SchemaError::WrongKind { expected } => write!(f, "expected a {expected:?} document"),
+            SchemaError::DuplicateTransition { from, symbol } => {
+                write!(f, "duplicate transition from {from} on {symbol:?}")
+            }
+            SchemaError::EpsilonInTransitions { from } => write!(
+                f,
+                "transition from {from} on {EPSILON:?}; epsilon moves belong in `epsilon`"
+            ),
+            SchemaError::UnexpectedMetadata => {
+                write!(f, "document has metadata; load it as a `Document`")
+            }
+            SchemaError::InvalidDFA(e) => write!(f, "invalid dfa: {e:?}"),
+            SchemaError::InvalidNFA(e) => write!(f, "invalid nfa: {e:?}"),
+        }
+    }
+}
+
+impl std::error::Error for SchemaError {}
+
+impl MachineDocument {
+    pub fn from_dfa(dfa: &DFA) -> Self {
+        let mut transitions: Vec<Transition> = dfa
+            .tfn
+            .iter()
+            .map(|(&(from, symbol), &to)| Transition { from, symbol, to })
+            .collect();
+        transitions.sort();
+        Self {
+            kind: MachineKind::Dfa,
+            states: dfa.states.len(),
+            start: dfa.start,
+            accept: sorted(dfa.accept.iter().copied()),
+            alphabet: sorted(dfa.alphabet.iter().copied()),
+            transitions,
+            epsilon: Vec::new(),
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    pub fn from_nfa(nfa: &NFA) -> Self {
+        let mut transitions = Vec::new();
+        let mut epsilon = Vec::new();
+        for (&(from, symbol), targets) in &nfa.tfn {
+            for &to in targets {
+                if symbol == EPSILON {
+                    epsilon.push(EpsilonEdge { from, to });
+                } else {
+                    transitions.push(Transition { from, symbol, to });
+                }
+            }
+        }
+        transitions.sort();
+        epsilon.sort();
+        Self {
+            kind: MachineKind::Nfa,
+            states: nfa.states.len(),
+            start: nfa.start,
+            accept: sorted(nfa.accept.iter().copied()),
+            alphabet: sorted(nfa.alphabet.iter().copied().filter(|&c| c != EPSILON)),
+            transitions,
+            epsilon,
+            metadata: BTreeMap::new(),
+        }
+    }
+
+    pub fn with_metadata(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// Builds the `DFA`, running the same checks as `DFA::new`.
+    pub fn to_dfa(&self) -> Result<DFA, SchemaError> {
+        if self.kind != MachineKind::Dfa || !self.epsilon.is_empty() {
+            return Err(SchemaError::WrongKind {
+                expected: MachineKind::Dfa,
+            });
+        }
+        let mut tfn = HashMap::new();
+        for t in &self.transitions {
+            if tfn.insert((t.from, t.symbol), t.to).is_some() {
+                return Err(SchemaError::DuplicateTransition {
+                    from: t.from,
+                    symbol: t.symbol,
+                });
+            }
+        }
+        DFA::new(
+            self.states,
+            self.start,
+            self.accept.iter().copied().collect(),
+            self.alphabet.iter().copied().collect(),
+            tfn,
+        )
+        .map_err(SchemaError::InvalidDFA)
+    }
+
+    /// Builds the `NFA`, running the same checks as `NFA::new`. A DFA document is also accepted,
+    /// since every DFA is an NFA, unless it uses `~` as a symbol.
+    pub fn to_nfa(&self) -> Result<NFA, SchemaError> {
+        let mut tfn: HashMap<(usize, char), HashSet<usize>> = HashMap::new();
+        for t in &self.transitions {
+            if t.symbol == EPSILON {
+                return Err(SchemaError::EpsilonInTransitions { from: t.from });
+            }
+            tfn.entry((t.from, t.symbol)).or_default().insert(t.to);
+        }
+        for e in &self.epsilon {
+            tfn.entry((e.from, EPSILON)).or_default().insert(e.to);
+        }
+        NFA::new(
+            self.states,
+            self.start,
+            self.accept.iter().copied().collect(),
+            self.alphabet.iter().copied().collect(),
+            tfn,
+        )
+        .map_err(SchemaError::InvalidNFA)
+    }
+}
+
+fn sorted<T: Ord>(items: impl Iterator<Item = T>) -> Vec<T> {
+    let mut items: Vec<T> = items.collect();
+    items.sort();
+    items
+}
+
+// written by hand so that the schema tag can never be left out
+impl Serialize for MachineDocument {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut doc = serializer.serialize_struct("MachineDocument", 9)?;
+        doc.serialize_field("schema", &format!("fsim/{SCHEMA_VERSION}"))?;
+        doc.serialize_field("kind", &self.kind)?;
+        doc.serialize_field("states", &self.states)?;
+        doc.serialize_field("start", &self.start)?;
+        doc.serialize_field("accept", &self.accept)?;
+        doc.serialize_field("alphabet", &self.alphabet)?;
+        doc.serialize_field("transitions", &self.transitions)?;
+        doc.serialize_field("epsilon", &self.epsilon)?;
+        doc.serialize_field("metadata", &self.metadata)?;
+        doc.end()
+    }
+}
+
+impl MachineDocument {
+    /// The same as `serialize`; kept as the counterpart of `deserialize_versioned`.
+    pub fn serialize_versioned<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.serialize(serializer)
+    }
+
+    pub fn deserialize_versioned<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Self, D::Error> {
+        AnyDocument::deserialize(deserializer).map(AnyDocument::migrate)
+    }
+}
+
+/// A machine together with its document's metadata, so that loading and saving again keeps it.
+#[derive(Clone)]
+pub struct Document<M> {
+    pub machine: M,
+    pub metadata: BTreeMap<String, String>,
+}
+
+impl<M> Document<M> {
+    pub fn new(machine: M) -> Self {
+        Self {
+            machine,
+            metadata: BTreeMap::new(),
+        }
+    }
+}
+
+fn load_bare<'de, D: Deserializer<'de>>(deserializer: D) -> Result<MachineDocument, D::Error> {
+    let doc = MachineDocument::deserialize_versioned(deserializer)?;
+    if !doc.metadata.is_empty() {
+        return Err(serde::de::Error::custom(SchemaError::UnexpectedMetadata));
+    }
+    Ok(doc)
+}
+
+impl Serialize for DFA {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        MachineDocument::from_dfa(self).serialize_versioned(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DFA {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        load_bare(deserializer)?
+            .to_dfa()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for NFA {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        MachineDocument::from_nfa(self).serialize_versioned(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for NFA {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        load_bare(deserializer)?
+            .to_nfa()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Serialize for Document<DFA> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut doc = MachineDocument::from_dfa(&self.machine);
+        doc.metadata = self.metadata.clone();
+        doc.serialize_versioned(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Document<DFA> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let doc = MachineDocument::deserialize_versioned(deserializer)?;
+        let machine = doc.to_dfa().map_err(serde::de::Error::custom)?;
+        Ok(Self {
+            machine,
+            metadata: doc.metadata,
+        })
+    }
+}
+
+impl Serialize for Document<NFA> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut doc = MachineDocument::from_nfa(&self.machine);
+        doc.metadata = self.metadata.clone();
+        doc.serialize_versioned(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Document<NFA> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let doc = MachineDocument::deserialize_versioned(deserializer)?;
+        let machine = doc.to_nfa().map_err(serde::de::Error::custom)?;
+        Ok(Self {
+            machine,
+            metadata: doc.metadata,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::SimulationResult;
+    use crate::fixtures::even_length;
+
+    #[test]
+    fn dfa_round_trips_through_json() {
+        let json = serde_json::to_string(&even_length()).unwrap();
+        assert!(json.starts_with(r#"{"schema":"fsim/1","kind":"dfa""#));
+        let dfa: DFA = serde_json::from_str(&json).unwrap();
+        assert!(matches!(
+            dfa.simulate("0110"),
+            Ok(SimulationResult::Accepted)
+        ));
+        assert!(matches!(
+            dfa.simulate("011"),
+            Ok(SimulationResult::Rejected)
+        ));
+        assert_eq!(json, serde_json::to_string(&dfa).unwrap());
+    }
+
+    #[test]
+    fn nfa_round_trips_epsilon_edges() {
+        let mut tfn = HashMap::new();
+        tfn.insert((0, EPSILON), HashSet::from([1]));
+        tfn.insert((1, 'a'), HashSet::from([0, 1]));
+        let nfa = NFA::new(2, 0, HashSet::from([1]), HashSet::from(['a']), tfn).unwrap();
+        let doc = MachineDocument::from_nfa(&nfa);
+        assert_eq!(doc.epsilon, vec![EpsilonEdge { from: 0, to: 1 }]);
+        assert_eq!(doc.alphabet, vec!['a']);
+
+        let json = serde_json::to_string(&nfa).unwrap();
+        let back: NFA = serde_json::from_str(&json).unwrap();
+        assert_eq!(MachineDocument::from_nfa(&back), doc);
+    }
+
+    #[test]
+    fn loading_runs_validation() {
+        let json = r#"{"schema":"fsim/1","kind":"dfa","states":2,"start":0,"accept":[0],
+            "alphabet":["0"],"transitions":[{"from":0,"symbol":"0","to":1}]}"#;
+        let err = serde_json::from_str::<DFA>(json).map(|_| ()).unwrap_err();
+        assert!(err.to_string().contains("NonTotalTransitionFunction"));
+
+        let json = r#"{"schema":"fsim/9","kind":"dfa","states":1,"start":0,"accept":[],
+            "alphabet":[],"transitions":[]}"#;
+        assert!(serde_json::from_str::<DFA>(json).is_err());
+    }
+
+    #[test]
+    fn metadata_is_preserved() {
+        let doc = MachineDocument::from_dfa(&even_length()).with_metadata("name", "even");
+        let mut out = Vec::new();
+        doc.serialize_versioned(&mut serde_json::Serializer::new(&mut out))
+            .unwrap();
+        let back =
+            MachineDocument::deserialize_versioned(&mut serde_json::Deserializer::from_slice(&out))
+                .unwrap();
+        assert_eq!(back.metadata.get("name").map(String::as_str), Some("even"));
+        assert!(back.to_nfa().is_ok());
+        assert!(matches!(back.to_dfa().map(|d| d.states.len()), Ok(2)));
+    }
+
+    #[test]
+    fn plain_serialization_carries_the_schema_tag() {
+        let doc = MachineDocument::from_dfa(&even_length());
+        let json = serde_json::to_string(&doc).unwrap();
+        assert!(json.starts_with(r#"{"schema":"fsim/1","kind":"dfa""#));
+        let back =
+            MachineDocument::deserialize_versioned(&mut serde_json::Deserializer::from_str(&json))
+                .unwrap();
+        assert_eq!(back, doc);
+
+        let err: Box<dyn std::error::Error> = Box::new(SchemaError::UnexpectedMetadata);
+        assert!(err.to_string().contains("metadata"));
+    }
+
+    #[test]
+    fn documents_keep_metadata_and_bare_machines_refuse_it() {
+        let mut doc = Document::new(even_length());
+        doc.metadata.insert("name".into(), "even".into());
+        let json = serde_json::to_string(&doc).unwrap();
+
+        let back: Document<DFA> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back.metadata, doc.metadata);
+        assert_eq!(serde_json::to_string(&back).unwrap(), json);
+
+        let err = serde_json::from_str::<DFA>(&json).map(|_| ()).unwrap_err();
+        assert!(err.to_string().contains("has metadata"));
+    }
+
+    #[test]
+    fn tilde_is_a_symbol_for_dfas_only() {
+        let tfn = HashMap::from([((0, '~'), 0)]);
+        let dfa = DFA::new(1, 0, HashSet::from([0]), HashSet::from(['~']), tfn).unwrap();
+        let json = serde_json::to_string(&dfa).unwrap();
+        let back: DFA = serde_json::from_str(&json).unwrap();
+        assert!(matches!(
+            back.simulate("~~"),
+            Ok(SimulationResult::Accepted)
+        ));
+
+        assert!(matches!(
+            MachineDocument::from_dfa(&dfa).to_nfa(),
+            Err(SchemaError::EpsilonInTransitions { from: 0 })
+        ));
+    }
+}
@@ -0,0 +1,402 @@
+//! Native SVG rendering on top of the layout engine, plus one frame per simulation step with the
+//! active states and the transitions just taken highlighted. Frames share a layout, so they can
+//! be played back as an animation.
+
+use std::collections::{BTreeSet, HashSet};
+use std::fmt::Write;
+
+use crate::dfa::{self, DFA, State};
+use crate::formats::diagram::Diagram;
+use crate::layout::{Layout, LayoutOptions, Point, layout};
+use crate::nfa::{self, EPSILON, NFA};
+
+const PADDING: f64 = 12.0;
+const CAPTION_HEIGHT: f64 = 28.0;
+const ACTIVE_FILL: &str = "#ffd54f";
+const ACTIVE_STROKE: &str = "#e65100";
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Highlight {
+    pub states: BTreeSet<State>,
+    /// Indices into `Diagram::edges`.
+    pub edges: BTreeSet<usize>,
+}
+
+fn xml_escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// one decimal place, without printing "-0.0"
+fn num(v: f64) -> String {
+    format!("{:.1}", v + 0.0).replace("-0.0", "0.0")
+}
+
+fn pt(p: Point) -> String {
+    format!("{} {}", num(p.x), num(p.y))
+}
+
+/// Smallest box holding every node, start arrow, edge and label.
+fn bounds(l: &Layout, d: &Diagram) -> (Point, Point) {
+    let r = l.node_radius;
+    let mut points: Vec<Point> = Vec::new();
+    for p in &l.nodes {
+        points.push(Point::new(p.x - r, p.y - r));
+        points.push(Point::new(p.x + r, p.y + r));
+    }
+    if let Some(p) = l.nodes.get(d.start) {
+        points.push(Point::new(p.x - 2.0 * r, p.y));
+    }
+    for e in &l.edges {
+        points.push(e.start);
+        points.push(Point::new(e.label.x - r, e.label.y - r * 0.6));
+        points.push(Point::new(e.label.x + r, e.label.y + r * 0.6));
+        for s in &e.segments {
+            points.extend([s.c1, s.c2, s.end]);
+        }
+    }
+    let min = points.iter().fold(Point::new(0.0, 0.0), |m, p| {
+        Point::new(m.x.min(p.x), m.y.min(p.y))
+    });
+    let max = points.iter().fold(Point::new(l.width, l.height), |m, p| {
+        Point::new(m.x.max(p.x), m.y.max(p.y))
+    });
+    (
+        Point::new(min.x - PADDING, min.y - PADDING),
+        Point::new(max.x + PADDING, max.y + PADDING),
+    )
+}
+
+/// Renders a laid-out diagram. `caption` is written under the picture.
+pub fn render_svg(d: &Diagram, l: &Layout, highlight: &Highlight, caption: Option<&str>) -> String {
+    let r = l.node_radius;
+    let (min, max) = bounds(l, d);
+    let width = max.x - min.x;
+    let height = max.y - min.y
+        + if caption.is_some() {
+            CAPTION_HEIGHT
+        } else {
+            0.0
+        };
+
+    let mut out = String::new();
+    writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{}" height="{}" viewBox="{} {} {} {}" font-family="sans-serif" font-size="14">"#,
+        num(width),
+        num(height),
+        num(min.x),
+        num(min.y),
+        num(width),
+        num(height)
+    )
+    .unwrap();
+    for (id, colour) in [("arrow", "black"), ("arrow-active", ACTIVE_STROKE)] {
+        writeln!(
+            out,
+            r#"  <defs><marker id="{id}" viewBox="0 0 10 10" refX="10" refY="5" markerWidth="8" markerHeight="8" orient="auto-start-reverse"><path d="M 0 0 L 10 5 L 0 10 z" fill="{colour}"/></marker></defs>"#
+        )
+        .unwrap();
+    }
+
+    for e in &l.edges {
+        let active = highlight.edges.contains(&e.edge);
+        let (class, stroke, width, marker) = if active {
+            ("edge active", ACTIVE_STROKE, 2.5, "arrow-active")
+        } else {
+            ("edge", "black", 1.5, "arrow")
+        };
+        let mut path = format!("M {}", pt(e.start));
+        for s in &e.segments {
+            write!(path, " C {}, {}, {}", pt(s.c1), pt(s.c2), pt(s.end)).unwrap();
+        }
+        writeln!(
+            out,
+            r#"  <path class="{class}" d="{path}" fill="none" stroke="{stroke}" stroke-width="{width}" marker-end="url(#{marker})"/>"#
+        )
+        .unwrap();
+        writeln!(
+            out,
+            r#"  <text class="label" x="{}" y="{}" text-anchor="middle" dominant-baseline="middle">{}</text>"#,
+            num(e.label.x),
+            num(e.label.y),
+            xml_escape(&d.label(&d.edges[e.edge], "ε"))
+        )
+        .unwrap();
+    }
+
+    if let Some(p) = l.nodes.get(d.start) {
+        writeln!(
+            out,
+            r#"  <path class="start" d="M {} L {}" stroke="black" stroke-width="1.5" marker-end="url(#arrow)"/>"#,
+            pt(Point::new(p.x - 2.0 * r, p.y)),
+            pt(Point::new(p.x - r, p.y))
+        )
+        .unwrap();
+    }
+
+    for (s, p) in l.nodes.iter().enumerate() {
+        let active = highlight.states.contains(&s);
+        let accepting = d.accept.contains(&s);
+        let mut class = String::from("state");
+        if accepting {
+            class.push_str(" accept");
+        }
+        if active {
+            class.push_str(" active");
+        }
+        let (fill, stroke) = if active {
+            (ACTIVE_FILL, ACTIVE_STROKE)
+        } else {
+            ("white", "black")
+        };
+        writeln!(out, r#"  <g class="{class}" id="q{s}">"#).unwrap();
+        writeln!(
+            out,
+            r#"    <circle cx="{}" cy="{}" r="{}" fill="{fill}" stroke="{stroke}" stroke-width="1.5"/>"#,
+            num(p.x),
+            num(p.y),
+            num(r)
+        )
+        .unwrap();
+        if accepting {
+            writeln!(
+                out,
+                r#"    <circle cx="{}" cy="{}" r="{}" fill="none" stroke="{stroke}" stroke-width="1.5"/>"#,
+                num(p.x),
+                num(p.y),
+                num(r - 4.0)
+            )
+            .unwrap();
+        }
+        writeln!(
+            out,
+            r#"    <text x="{}" y="{}" text-anchor="middle" dominant-baseline="central">q{s}</text>"#,
+            num(p.x),
+            num(p.y)
+        )
+        .unwrap();
+        writeln!(out, "  </g>").unwrap();
+    }
+
+    if let Some(caption) = caption {
+        writeln!(
+            out,
+            r#"  <text class="caption" x="{}" y="{}" text-anchor="middle">{}</text>"#,
+            num(min.x + width / 2.0),
+            num(max.y + CAPTION_HEIGHT / 2.0),
+            xml_escape(caption)
+        )
+        .unwrap();
+    }
+    out.push_str("</svg>\n");
+    out
+}
+
+pub fn dfa_to_svg(dfa: &DFA) -> String {
+    let d = Diagram::from_dfa(dfa);
+    render_svg(
+        &d,
+        &layout(&d, &LayoutOptions::default()),
+        &Highlight::default(),
+        None,
+    )
+}
+
+pub fn nfa_to_svg(nfa: &NFA) -> String {
+    let d = Diagram::from_nfa(nfa);
+    render_svg(
+        &d,
+        &layout(&d, &LayoutOptions::default()),
+        &Highlight::default(),
+        None,
+    )
+}
+
+fn edges_taken(d: &Diagram, taken: &HashSet<(State, char, State)>) -> BTreeSet<usize> {
+    d.edges
+        .iter()
+        .enumerate()
+        .filter(|(_, e)| {
+            e.symbols
+                .iter()
+                .any(|&c| taken.contains(&(e.from, c, e.to)))
+        })
+        .map(|(i, _)| i)
+        .collect()
+}
+
+fn caption(input: &[char], step: usize, accepted: bool) -> String {
+    let read: String = input[..step].iter().collect();
+    let mut caption = if step == 0 {
+        "start".to_string()
+    } else {
+        format!(
+            "step {step}: read {:?}, consumed \"{read}\"",
+            input[step - 1]
+        )
+    };
+    if step == input.len() {
+        caption.push_str(if accepted {
+            " (accepted)"
+        } else {
+            " (rejected)"
+        });
+    }
+    caption
+}
+
+/// One SVG per configuration of the run: the start state, then the state after each symbol.
+pub fn dfa_simulation_frames(dfa: &DFA, input: &str) -> Result<Vec<String>, dfa::InputError> {
+    if !input.chars().all(|c| dfa.alphabet.contains(&c)) {
+        return Err(dfa::InputError::InvalidSymbol);
+    }
+    let d = Diagram::from_dfa(dfa);
+    let l = layout(&d, &LayoutOptions::default());
+    let input: Vec<char> = input.chars().collect();
+    let len = input.len();
+
+    let mut state = dfa.start;
+    let mut frames = Vec::with_capacity(len + 1);
+    let mut taken = HashSet::new();
+    for step in 0..=len {
+        if step > 0 {
+            let c = input[step - 1];
+            let next = dfa.tfn[&(state, c)];
+            taken = HashSet::from([(state, c, next)]);
+            state = next;
+        }
+        let highlight = Highlight {
+            states: BTreeSet::from([state]),
+            edges: edges_taken(&d, &taken),
+        };
+        let accepted = dfa.accept.contains(&state);
+        let caption = caption(&input, step, accepted);
+        frames.push(render_svg(&d, &l, &highlight, Some(&caption)));
+    }
+    Ok(frames)
+}
+
+/// One SVG per configuration of the run, highlighting the whole epsilon-closed set of active
+/// states and every transition (including epsilon moves) that led into it.
+pub fn nfa_simulation_frames(nfa: &NFA, input: &str) -> Result<Vec<String>, nfa::InputError> {
+    if !input
+        .chars()
+        .all(|c| c != EPSILON && nfa.alphabet.contains(&c))
+    {
+        return Err(nfa::InputError::InvalidSymbol);
+    }
+    let d = Diagram::from_nfa(nfa);
+    let l = layout(&d, &LayoutOptions::default());
+    let input: Vec<char> = input.chars().collect();
+    let len = input.len();
+
+    let epsilon_edges = |active: &HashSet<State>| -> HashSet<(State, char, State)> {
+        active
+            .iter()
+            .flat_map(|&s| {
+                nfa.tfn
+                    .get(&(s, EPSILON))
+                    .into_iter()
+                    .flatten()
+                    .map(move |&t| (s, EPSILON, t))
+            })
+            .collect()
+    };
+
+    let mut active = nfa.epsilon_closure(&HashSet::from([nfa.start]));
+    let mut taken = epsilon_edges(&active);
+    let mut frames = Vec::with_capacity(len + 1);
+    for step in 0..=len {
+        if step > 0 {
+            let c = input[step - 1];
+            let mut moved = HashSet::new();
+            taken.clear();
+            for &s in &active {
+                for &t in nfa.tfn.get(&(s, c)).into_iter().flatten() {
+                    moved.insert(t);
+                    taken.insert((s, c, t));
+                }
+            }
+            active = nfa.epsilon_closure(&moved);
+            taken.extend(epsilon_edges(&active));
+        }
+        let highlight = Highlight {
+            states: active.iter().copied().collect(),
+            edges: edges_taken(&d, &taken),
+        };
+        let accepted = active.iter().any(|s| nfa.accept.contains(s));
+        let caption = caption(&input, step, accepted);
+        frames.push(render_svg(&d, &l, &highlight, Some(&caption)));
+    }
+    Ok(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::even_length;
+    use std::collections::HashMap;
+
+    #[test]
+    fn dfa_svg_has_states_edges_and_start_arrow() {
+        let svg = dfa_to_svg(&even_length());
+        assert!(svg.starts_with("<svg xmlns=\"http://www.w3.org/2000/svg\""));
+        assert!(svg.ends_with("</svg>\n"));
+        assert_eq!(svg.matches("<g class=\"state").count(), 2);
+        // the accept state is a double circle
+        assert_eq!(svg.matches("<circle").count(), 3);
+        assert_eq!(svg.matches("class=\"edge\"").count(), 2);
+        assert_eq!(svg.matches("class=\"start\"").count(), 1);
+        assert!(svg.contains(">0, 1</text>"));
+    }
+
+    #[test]
+    fn labels_are_escaped() {
+        let mut tfn = HashMap::new();
+        tfn.insert((0, '<'), 0);
+        let dfa = DFA::new(1, 0, HashSet::new(), HashSet::from(['<']), tfn).unwrap();
+        let svg = dfa_to_svg(&dfa);
+        assert!(svg.contains(">&lt;</text>"));
+        // the loop above the node stays inside the view box
+        assert!(svg.contains("viewBox=\"-12.0 "));
+    }
+
+    #[test]
+    fn dfa_frames_follow_the_run() {
+        let frames = dfa_simulation_frames(&even_length(), "011").unwrap();
+        assert_eq!(frames.len(), 4);
+        assert!(frames[0].contains("<g class=\"state accept active\" id=\"q0\">"));
+        assert!(!frames[0].contains("edge active"));
+        assert!(frames[1].contains("<g class=\"state active\" id=\"q1\">"));
+        assert_eq!(frames[1].matches("edge active").count(), 1);
+        assert!(frames[3].contains("step 3: read '1', consumed &quot;011&quot; (rejected)"));
+        assert!(dfa_simulation_frames(&even_length(), "2").is_err());
+    }
+
+    #[test]
+    fn nfa_frames_highlight_every_active_state() {
+        let mut tfn = HashMap::new();
+        tfn.insert((0, '0'), HashSet::from([0]));
+        tfn.insert((0, '1'), HashSet::from([0, 1]));
+        tfn.insert((1, '1'), HashSet::from([2]));
+        let nfa = NFA::new(3, 0, HashSet::from([2]), HashSet::from(['0', '1']), tfn).unwrap();
+        let frames = nfa_simulation_frames(&nfa, "11").unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[1].matches(" active\" id=").count(), 2);
+        assert_eq!(frames[2].matches(" active\" id=").count(), 3);
+        assert!(frames[2].ends_with("(accepted)</text>\n</svg>\n"));
+    }
+    #[test]
+    fn dfa_tilde_is_labelled_as_a_symbol() {
+        let mut tfn = HashMap::new();
+        tfn.insert((0, '~'), 0);
+        let dfa = DFA::new(1, 0, HashSet::from([0]), HashSet::from(['~']), tfn).unwrap();
+        assert!(dfa_to_svg(&dfa).contains(">~</text>"));
+        let frames = dfa_simulation_frames(&dfa, "~").unwrap();
+        assert!(frames[1].contains(">~</text>"));
+        assert!(!frames[1].contains(">ε</text>"));
+    }
+}
@@ -0,0 +1,621 @@
+//! Transition tables with sorted states and symbols, `→` marking the start state and `*`
+//! marking accept states. NFA cells are sets of states, with an `ε` column for epsilon moves.
+//! CSV tables can be read back with `csv_to_dfa` / `csv_to_nfa`; the importer also accepts `->`
+//! or `>` for the start marker, `eps` for the epsilon column, and empty NFA cells. A `~` column
+//! is an ordinary DFA symbol; NFAs reserve `~` and cannot have one.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::algorithms::subset_construction::SubsetTrace;
+use crate::dfa::{DFA, DFATypeError};
+use crate::formats::formal::symbol_text;
+use crate::nfa::{EPSILON, NFA, NFATypeError};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TableFormat {
+    Plain,
+    Markdown,
+    Csv,
+}
+
+#[derive(Debug)]
+pub enum TableParseError {
+    UnterminatedQuote,
+    Empty,
+    RaggedRow { row: usize },
+    InvalidSymbol { column: usize },
+    DuplicateSymbol { column: usize },
+    MissingStart,
+    MultipleStarts { row: usize },
+    DuplicateState { row: usize },
+    UnknownState { row: usize, name: String },
+    InvalidDFA(DFATypeError),
+    InvalidNFA(NFATypeError),
+}
+
+const START: &str = "→";
+const ACCEPT: &str = "*";
+
+struct Table {
+    header: Vec<String>,
+    rows: Vec<Vec<String>>,
+}
+
+fn row_label(s: usize, start: usize, accept: &HashSet<usize>) -> String {
+    format!(
+        "{}{}q{s}",
+        if s == start { START } else { " " },
+        if accept.contains(&s) { ACCEPT } else { " " }
+    )
+}
+
+fn state_set<'a>(states: impl IntoIterator<Item = &'a usize>) -> String {
+    let mut states: Vec<usize> = states.into_iter().copied().collect();
+    if states.is_empty() {
+        return "∅".to_string();
+    }
+    states.sort();
+    let names: Vec<String> = states.iter().map(|s| format!("q{s}")).collect();
+    format!("{{{}}}", names.join(", "))
+}
+
+// columns are symbols, with `None` for the epsilon column
+
+fn dfa_cells(dfa: &DFA) -> (Vec<Option<char>>, Table) {
+    let mut alphabet: Vec<char> = dfa.alphabet.iter().copied().collect();
+    alphabet.sort();
+    let mut states: Vec<usize> = dfa.states.iter().copied().collect();
+    states.sort();
+    let rows = states
+        .into_iter()
+        .map(|s| {
+            let mut row = vec![row_label(s, dfa.start, &dfa.accept)];
+            row.extend(alphabet.iter().map(|&c| format!("q{}", dfa.tfn[&(s, c)])));
+            row
+        })
+        .collect();
+    (
+        alphabet.into_iter().map(Some).collect(),
+        Table {
+            header: Vec::new(),
+            rows,
+        },
+    )
+}
+
+fn nfa_cells(nfa: &NFA) -> (Vec<Option<char>>, Table) {
+    let mut columns: Vec<Option<char>> = nfa
+        .alphabet
+        .iter()
+        .filter(|&&c| c != EPSILON)
+        .map(|&c| Some(c))
+        .collect();
+    columns.sort();
+    if nfa.tfn.keys().any(|&(_, c)| c == EPSILON) {
+        columns.push(None);
+    }
+    let mut states: Vec<usize> = nfa.states.iter().copied().collect();
+    states.sort();
+    let rows = states
+        .into_iter()
+        .map(|s| {
+            let mut row = vec![row_label(s, nfa.start, &nfa.accept)];
+            row.extend(
+                columns
+                    .iter()
+                    .map(|c| c.unwrap_or(EPSILON))
+                    .map(|c| state_set(nfa.tfn.get(&(s, c)).into_iter().flatten())),
+            );
+            row
+        })
+        .collect();
+    (
+        columns,
+        Table {
+            header: Vec::new(),
+            rows,
+        },
+    )
+}
+
+fn header(
+    leading: &[&str],
+    columns: &[Option<char>],
+    symbol: impl Fn(char) -> String,
+) -> Vec<String> {
+    let mut header = vec![String::new()];
+    header.extend(leading.iter().map(|h| h.to_string()));
+    header.extend(columns.iter().map(|c| match c {
+        Some(c) => symbol(*c),
+        None => "ε".to_string(),
+    }));
+    header
+}
+
+/// Quotes whitespace symbols, which would otherwise be lost to trimming when read back, and `ε`,
+/// which would read back as the epsilon column.
+fn quote_symbol(c: char) -> String {
+    if c.is_whitespace() || c == 'ε' {
+        symbol_text(c)
+    } else {
+        c.to_string()
+    }
+}
+
+fn render_plain(t: &Table) -> String {
+    let widths: Vec<usize> = (0..t.header.len())
+        .map(|i| {
+            std::iter::once(&t.header)
+                .chain(&t.rows)
+                .map(|r| r[i].chars().count())
+                .max()
+                .unwrap()
+        })
+        .collect();
+    let line = |cells: &[String]| {
+        let padded: Vec<String> = cells
+            .iter()
+            .zip(&widths)
+            .map(|(c, &w)| format!("{c:<w$}"))
+            .collect();
+        padded.join(" | ").trim_end().to_string()
+    };
+    let rule: Vec<String> = widths.iter().map(|&w| "-".repeat(w)).collect();
+    let mut out = line(&t.header);
+    out.push('\n');
+    out.push_str(&rule.join("-+-"));
+    for r in &t.rows {
+        out.push('\n');
+        out.push_str(&line(r));
+    }
+    out.push('\n');
+    out
+}
+
+fn render_markdown(t: &Table) -> String {
+    let line = |cells: &[String]| {
+        let cells: Vec<String> = cells
+            .iter()
+            .map(|c| c.replace('\\', "\\\\").replace('|', "\\|"))
+            .collect();
+        format!("| {} |", cells.join(" | "))
+    };
+    let mut out = line(&t.header);
+    out.push('\n');
+    out.push_str(&format!("|{}", "---|".repeat(t.header.len())));
+    for r in &t.rows {
+        out.push('\n');
+        out.push_str(&line(r));
+    }
+    out.push('\n');
+    out
+}
+
+fn csv_field(field: &str) -> String {
+    let field = field.trim();
+    if field.contains([',', '"', '\n', '\r']) || field.starts_with(' ') {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn render_csv(t: &Table) -> String {
+    std::iter::once(&t.header)
+        .chain(&t.rows)
+        .map(|r| {
+            let fields: Vec<String> = r.iter().map(|c| csv_field(c)).collect();
+            format!("{}\n", fields.join(","))
+        })
+        .collect()
+}
+
+fn render(
+    leading: &[&str],
+    columns: Vec<Option<char>>,
+    mut t: Table,
+    format: TableFormat,
+) -> String {
+    if format != TableFormat::Plain {
+        // the padding in row labels only serves to align the plain table
+        for r in &mut t.rows {
+            r[0].retain(|c| c != ' ');
+        }
+    }
+    match format {
+        TableFormat::Plain => {
+            t.header = header(leading, &columns, symbol_text);
+            render_plain(&t)
+        }
+        TableFormat::Markdown => {
+            t.header = header(leading, &columns, quote_symbol);
+            render_markdown(&t)
+        }
+        TableFormat::Csv => {
+            t.header = header(leading, &columns, quote_symbol);
+            t.header[0] = "state".to_string();
+            render_csv(&t)
+        }
+    }
+}
+
+pub fn dfa_table(dfa: &DFA, format: TableFormat) -> String {
+    let (columns, t) = dfa_cells(dfa);
+    render(&[], columns, t, format)
+}
+
+pub fn nfa_table(nfa: &NFA, format: TableFormat) -> String {
+    let (columns, t) = nfa_cells(nfa);
+    render(&[], columns, t, format)
+}
+
+/// The subset construction as a table: one row per DFA state `D0, D1, …` in the order they were
+/// processed, with the NFA states it stands for. Each cell shows the move on that symbol, its
+/// epsilon closure after `⇒` when that adds states, the DFA state the closure is, and `(new)`
+/// where that state was discovered.
+pub fn subset_table(trace: &SubsetTrace, format: TableFormat) -> String {
+    let rows = trace
+        .steps
+        .iter()
+        .map(|step| {
+            let label = format!(
+                "{}{}D{}",
+                if step.state == 0 { START } else { " " },
+                if step.accepting { ACCEPT } else { " " },
+                step.state
+            );
+            let mut row = vec![label, state_set(&step.subset)];
+            row.extend(step.moves.iter().map(|m| {
+                let mut cell = state_set(&m.moved);
+                if m.closure != m.moved {
+                    cell.push_str(&format!(" ⇒ {}", state_set(&m.closure)));
+                }
+                cell.push_str(&format!(" = D{}", m.target));
+                if m.new {
+                    cell.push_str(" (new)");
+                }
+                cell
+            }));
+            row
+        })
+        .collect();
+    let t = Table {
+        header: Vec::new(),
+        rows,
+    };
+    let columns = trace.alphabet.iter().copied().map(Some).collect();
+    render(&["NFA states"], columns, t, format)
+}
+
+fn parse_csv(text: &str) -> Result<Vec<Vec<String>>, TableParseError> {
+    let mut records = Vec::new();
+    let mut record = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quoted, c) {
+            (true, '"') if chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            (true, '"') => quoted = false,
+            (true, c) => field.push(c),
+            (false, '"') => quoted = true,
+            (false, ',') => record.push(std::mem::take(&mut field)),
+            (false, '\r') => {}
+            (false, '\n') => {
+                record.push(std::mem::take(&mut field));
+                records.push(std::mem::take(&mut record));
+            }
+            (false, c) => field.push(c),
+        }
+    }
+    if quoted {
+        return Err(TableParseError::UnterminatedQuote);
+    }
+    if !field.is_empty() || !record.is_empty() {
+        record.push(field);
+        records.push(record);
+    }
+    records.retain(|r| r.iter().any(|f| !f.trim().is_empty()));
+    Ok(records)
+}
+
+struct ParsedTable {
+    states: HashMap<String, usize>,
+    start: usize,
+    accept: HashSet<usize>,
+    columns: Vec<Option<char>>,
+    // (row, state, cells)
+    rows: Vec<(usize, usize, Vec<String>)>,
+}
+
+impl ParsedTable {
+    fn state(&self, row: usize, name: &str) -> Result<usize, TableParseError> {
+        self.states
+            .get(name.trim())
+            .copied()
+            .ok_or_else(|| TableParseError::UnknownState {
+                row,
+                name: name.trim().to_string(),
+            })
+    }
+
+    fn alphabet(&self) -> HashSet<char> {
+        self.columns.iter().flatten().copied().collect()
+    }
+}
+
+fn parse_table(text: &str) -> Result<ParsedTable, TableParseError> {
+    let records = parse_csv(text)?;
+    let (header, body) = records.split_first().ok_or(TableParseError::Empty)?;
+    let columns = header
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(i, h)| {
+            let h = h.trim();
+            let chars: Vec<char> = h.chars().collect();
+            match chars[..] {
+                _ if h == "ε" || h == "eps" => Ok(None),
+                [c] | ['\'', c, '\''] => Ok(Some(c)),
+                _ => Err(TableParseError::InvalidSymbol { column: i }),
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    for (i, c) in columns.iter().enumerate() {
+        if columns[..i].contains(c) {
+            return Err(TableParseError::DuplicateSymbol { column: i + 1 });
+        }
+    }
+
+    let mut states = HashMap::new();
+    let mut start = None;
+    let mut accept = HashSet::new();
+    let mut rows = Vec::new();
+    for (i, record) in body.iter().enumerate() {
+        let row = i + 1;
+        if record.len() != header.len() {
+            return Err(TableParseError::RaggedRow { row });
+        }
+        let mut label = record[0].trim();
+        let mut is_start = false;
+        let mut is_accept = false;
+        loop {
+            if let Some(rest) = ["→", "->", ">"].iter().find_map(|m| label.strip_prefix(m)) {
+                is_start = true;
+                label = rest.trim_start();
+            } else if let Some(rest) = label.strip_prefix(ACCEPT) {
+                is_accept = true;
+                label = rest.trim_start();
+            } else {
+                break;
+            }
+        }
+        let s = states.len();
+        if states.insert(label.to_string(), s).is_some() {
+            return Err(TableParseError::DuplicateState { row });
+        }
+        if is_start && start.replace(s).is_some() {
+            return Err(TableParseError::MultipleStarts { row });
+        }
+        if is_accept {
+            accept.insert(s);
+        }
+        rows.push((row, s, record[1..].to_vec()));
+    }
+
+    Ok(ParsedTable {
+        states,
+        start: start.ok_or(TableParseError::MissingStart)?,
+        accept,
+        columns,
+        rows,
+    })
+}
+
+pub fn csv_to_dfa(text: &str) -> Result<DFA, TableParseError> {
+    let t = parse_table(text)?;
+    let mut tfn = HashMap::new();
+    for (row, s, cells) in &t.rows {
+        for (&c, cell) in t.columns.iter().zip(cells) {
+            let c = c.ok_or(TableParseError::InvalidDFA(
+                DFATypeError::InvalidTransitionFunction,
+            ))?;
+            tfn.insert((*s, c), t.state(*row, cell)?);
+        }
+    }
+    DFA::new(t.states.len(), t.start, t.accept.clone(), t.alphabet(), tfn)
+        .map_err(TableParseError::InvalidDFA)
+}
+
+pub fn csv_to_nfa(text: &str) -> Result<NFA, TableParseError> {
+    let t = parse_table(text)?;
+    let mut tfn: HashMap<(usize, char), HashSet<usize>> = HashMap::new();
+    for (row, s, cells) in &t.rows {
+        for (&c, cell) in t.columns.iter().zip(cells) {
+            let cell = cell.trim();
+            let inner = cell
+                .strip_prefix('{')
+                .and_then(|c| c.strip_suffix('}'))
+                .unwrap_or(cell);
+            let targets = inner
+                .split(',')
+                .map(str::trim)
+                .filter(|n| !n.is_empty() && *n != "∅")
+                .map(|n| t.state(*row, n))
+                .collect::<Result<HashSet<_>, _>>()?;
+            if !targets.is_empty() {
+                tfn.insert((*s, c.unwrap_or(EPSILON)), targets);
+            }
+        }
+    }
+    NFA::new(t.states.len(), t.start, t.accept.clone(), t.alphabet(), tfn)
+        .map_err(TableParseError::InvalidNFA)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algorithms::subset_construction::determinize_recorded;
+    use crate::dfa::SimulationResult;
+    use crate::fixtures::even_length;
+    use fsim_macros::{dfa, nfa};
+
+    fn nfa_with_epsilon() -> NFA {
+        nfa! {
+            states: 3,
+            start: 0,
+            accept: [2],
+            alphabet: ['a', ','],
+            transitions: {
+                0 => { 'a' => [0, 1] },
+                1 => { '~' => 2 },
+                2 => { ',' => 0 },
+            },
+        }
+    }
+
+    #[test]
+    fn dfa_plain_table() {
+        let expected = concat!(
+            "     | 0  | 1\n",
+            "-----+----+---\n",
+            "→*q0 | q1 | q1\n",
+            "  q1 | q0 | q0\n",
+        );
+        assert_eq!(dfa_table(&even_length(), TableFormat::Plain), expected);
+        // Display is the plain table, so it is deterministic too
+        assert_eq!(even_length().to_string(), expected);
+    }
+
+    #[test]
+    fn nfa_markdown_and_csv() {
+        let nfa = nfa_with_epsilon();
+        let expected = "\
+|  | , | a | ε |
+|---|---|---|---|
+| →q0 | ∅ | {q0, q1} | ∅ |
+| q1 | ∅ | ∅ | {q2} |
+| *q2 | {q0} | ∅ | ∅ |
+";
+        assert_eq!(nfa_table(&nfa, TableFormat::Markdown), expected);
+        let expected = "\
+state,\",\",a,ε
+→q0,∅,\"{q0, q1}\",∅
+q1,∅,∅,{q2}
+*q2,{q0},∅,∅
+";
+        assert_eq!(nfa_table(&nfa, TableFormat::Csv), expected);
+    }
+
+    #[test]
+    fn subset_construction_table() {
+        let (_, trace) = determinize_recorded(&nfa_with_epsilon());
+        let expected = concat!(
+            "     | NFA states   | ','          | a\n",
+            "-----+--------------+--------------+-----------------------------------\n",
+            "→ D0 | {q0}         | ∅ = D1 (new) | {q0, q1} ⇒ {q0, q1, q2} = D2 (new)\n",
+            "  D1 | ∅            | ∅ = D1       | ∅ = D1\n",
+            " *D2 | {q0, q1, q2} | {q0} = D0    | {q0, q1} ⇒ {q0, q1, q2} = D2\n",
+        );
+        assert_eq!(subset_table(&trace, TableFormat::Plain), expected);
+        let csv = subset_table(&trace, TableFormat::Csv);
+        assert!(csv.starts_with("state,NFA states,\",\",a\n→D0,"));
+    }
+
+    #[test]
+    fn csv_round_trips() {
+        let csv = dfa_table(&even_length(), TableFormat::Csv);
+        let dfa = csv_to_dfa(&csv).unwrap();
+        assert_eq!(dfa_table(&dfa, TableFormat::Csv), csv);
+
+        let csv = nfa_table(&nfa_with_epsilon(), TableFormat::Csv);
+        let nfa = csv_to_nfa(&csv).unwrap();
+        assert_eq!(nfa_table(&nfa, TableFormat::Csv), csv);
+    }
+
+    #[test]
+    fn hand_written_csv_imports() {
+        let csv = "state,a,b\r\n-> even,odd,even\r\n*odd,even,odd\r\n";
+        let dfa = csv_to_dfa(csv).unwrap();
+        assert!(matches!(
+            dfa.simulate("aba"),
+            Ok(SimulationResult::Rejected)
+        ));
+        assert!(matches!(dfa.simulate("ab"), Ok(SimulationResult::Accepted)));
+        let nfa = csv_to_nfa("s,a,eps\n>p,,q\n*q,p q,\n");
+        assert!(matches!(
+            nfa,
+            Err(TableParseError::UnknownState { row: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn bad_csv_is_rejected() {
+        assert!(matches!(csv_to_dfa(""), Err(TableParseError::Empty)));
+        assert!(matches!(
+            csv_to_dfa("s,a\nq0,q0\n"),
+            Err(TableParseError::MissingStart)
+        ));
+        assert!(matches!(
+            csv_to_dfa("s,a\n>q0,q0,q0\n"),
+            Err(TableParseError::RaggedRow { row: 1 })
+        ));
+        assert!(matches!(
+            csv_to_dfa("s,ab\n>q0,q0\n"),
+            Err(TableParseError::InvalidSymbol { column: 1 })
+        ));
+        assert!(matches!(
+            csv_to_dfa("s,a\n>q0,\"q0\n"),
+            Err(TableParseError::UnterminatedQuote)
+        ));
+        assert!(matches!(
+            csv_to_dfa("s,a\n>q0,q0\nq0,q0\n"),
+            Err(TableParseError::DuplicateState { row: 2 })
+        ));
+        assert!(matches!(
+            csv_to_dfa("s,a,'a'\n>q0,q0,q0\n"),
+            Err(TableParseError::DuplicateSymbol { column: 2 })
+        ));
+    }
+
+    #[test]
+    fn tilde_space_and_epsilon_columns_are_symbols() {
+        let dfa = dfa! {
+            states: 1,
+            start: 0,
+            accept: [0],
+            alphabet: [' ', '~'],
+            transitions: {
+                0 => { ' ' | '~' => 0 },
+            },
+        };
+        let markdown = dfa_table(&dfa, TableFormat::Markdown);
+        assert!(markdown.starts_with("|  | ' ' | ~ |\n"));
+        let csv = dfa_table(&dfa, TableFormat::Csv);
+        assert_eq!(csv, "state,' ',~\n→*q0,q0,q0\n");
+        assert_eq!(dfa_table(&csv_to_dfa(&csv).unwrap(), TableFormat::Csv), csv);
+        assert!(matches!(
+            csv_to_nfa(&csv),
+            Err(TableParseError::InvalidNFA(
+                NFATypeError::ReservedCharacterInAlphabet
+            ))
+        ));
+
+        let dfa = dfa! {
+            states: 1,
+            start: 0,
+            accept: [0],
+            alphabet: ['ε'],
+            transitions: {
+                0 => { 'ε' => 0 },
+            },
+        };
+        let csv = dfa_table(&dfa, TableFormat::Csv);
+        assert_eq!(csv, "state,'ε'\n→*q0,q0\n");
+        assert_eq!(dfa_table(&csv_to_dfa(&csv).unwrap(), TableFormat::Csv), csv);
+        assert!(dfa_table(&dfa, TableFormat::Markdown).starts_with("|  | 'ε' |\n"));
+    }
+}
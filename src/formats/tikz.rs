@@ -0,0 +1,183 @@
+//! TikZ export using the `automata` library. The picture needs
+//! `\usetikzlibrary{automata, arrows.meta}` in the preamble, or use `tikz_document` for a
+//! standalone file.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::dfa::{DFA, State};
+use crate::formats::diagram::Diagram;
+use crate::formats::formal::latex_escape;
+use crate::layout::{Layout, LayoutOptions, layout};
+use crate::nfa::{EPSILON, NFA};
+
+/// Node centres in TikZ units (cm), with y growing upwards.
+pub type Positions = HashMap<State, (f64, f64)>;
+
+/// Centimetres between columns; rows keep the layout's aspect ratio.
+const COLUMN_GAP: f64 = 2.5;
+
+/// Converts layout-engine geometry to TikZ positions: scaled to centimetres, with the first
+/// column at x = 0 and y flipped to point upwards.
+pub fn positions_from_layout(layout: &Layout, options: &LayoutOptions) -> Positions {
+    let k = COLUMN_GAP / options.layer_gap;
+    layout
+        .nodes
+        .iter()
+        .enumerate()
+        .map(|(s, p)| (s, ((p.x - options.margin) * k, -(p.y - options.margin) * k)))
+        .collect()
+}
+
+fn automatic_positions(d: &Diagram) -> Positions {
+    let options = LayoutOptions::default();
+    positions_from_layout(&layout(d, &options), &options)
+}
+
+fn diagram_to_tikz(d: &Diagram, positions: Option<&Positions>) -> String {
+    let automatic;
+    let positions = match positions {
+        Some(p) => p,
+        None => {
+            automatic = automatic_positions(d);
+            &automatic
+        }
+    };
+
+    let mut out = String::from(
+        "\\begin{tikzpicture}[->, >={Stealth[round]}, shorten >=1pt, auto, semithick, \
+         initial text=]\n",
+    );
+    for s in 0..d.states {
+        let mut style = vec!["state"];
+        if s == d.start {
+            style.push("initial");
+        }
+        if d.accept.contains(&s) {
+            style.push("accepting");
+        }
+        let (x, y) = positions.get(&s).copied().unwrap_or((0.0, 0.0));
+        // adding zero turns -0.0 into 0.0 so it doesn't print as "-0.00"
+        let (x, y) = (x + 0.0, y + 0.0);
+        writeln!(
+            out,
+            "  \\node[{}] (q{s}) at ({x:.2}, {y:.2}) {{$q_{{{s}}}$}};",
+            style.join(", ")
+        )
+        .unwrap();
+    }
+
+    if !d.edges.is_empty() {
+        out.push_str("  \\path");
+        for (i, e) in d.edges.iter().enumerate() {
+            let label = e
+                .symbols
+                .iter()
+                .map(|&c| {
+                    if d.epsilon && c == EPSILON {
+                        "\\varepsilon".to_string()
+                    } else {
+                        latex_escape(&c.to_string())
+                    }
+                })
+                .collect::<Vec<_>>()
+                .join(", ");
+            let shape = if e.is_loop() {
+                "[loop above] "
+            } else if d.has_reverse(e) {
+                "[bend left] "
+            } else {
+                ""
+            };
+            let indent = if i == 0 { " " } else { "\n        " };
+            write!(
+                out,
+                "{indent}(q{}) edge {shape}node {{${label}$}} (q{})",
+                e.from, e.to
+            )
+            .unwrap();
+        }
+        out.push_str(";\n");
+    }
+    out.push_str("\\end{tikzpicture}\n");
+    out
+}
+
+/// Renders the DFA as a `tikzpicture`. Nodes are placed at `positions` when given, otherwise
+/// laid out automatically.
+pub fn dfa_to_tikz(dfa: &DFA, positions: Option<&Positions>) -> String {
+    diagram_to_tikz(&Diagram::from_dfa(dfa), positions)
+}
+
+/// Renders the NFA as a `tikzpicture`, with epsilon edges labelled `ε`.
+pub fn nfa_to_tikz(nfa: &NFA, positions: Option<&Positions>) -> String {
+    diagram_to_tikz(&Diagram::from_nfa(nfa), positions)
+}
+
+/// Wraps a picture in a compilable `standalone` document.
+pub fn tikz_document(picture: &str) -> String {
+    format!(
+        "\\documentclass[tikz]{{standalone}}\n\\usetikzlibrary{{automata, arrows.meta}}\n\
+         \\begin{{document}}\n{picture}\\end{{document}}\n"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::even_length;
+    use fsim_macros::dfa;
+    use std::collections::HashSet;
+
+    #[test]
+    fn dfa_tikz_with_automatic_layout() {
+        let expected = "\
+\\begin{tikzpicture}[->, >={Stealth[round]}, shorten >=1pt, auto, semithick, initial text=]
+  \\node[state, initial, accepting] (q0) at (0.00, 0.00) {$q_{0}$};
+  \\node[state] (q1) at (2.50, 0.00) {$q_{1}$};
+  \\path (q0) edge [bend left] node {$0, 1$} (q1)
+        (q1) edge [bend left] node {$0, 1$} (q0);
+\\end{tikzpicture}
+";
+        assert_eq!(dfa_to_tikz(&even_length(), None), expected);
+    }
+
+    #[test]
+    fn stored_positions_are_used() {
+        let positions = Positions::from([(0, (1.0, 2.0)), (1, (-3.5, 0.25))]);
+        let tikz = dfa_to_tikz(&even_length(), Some(&positions));
+        assert!(tikz.contains("(q0) at (1.00, 2.00)"));
+        assert!(tikz.contains("(q1) at (-3.50, 0.25)"));
+    }
+
+    #[test]
+    fn nfa_loops_and_epsilon() {
+        let mut tfn = HashMap::new();
+        tfn.insert((0, 'a'), HashSet::from([0]));
+        tfn.insert((0, EPSILON), HashSet::from([1]));
+        tfn.insert((2, '#'), HashSet::from([2]));
+        let nfa = NFA::new(3, 0, HashSet::from([1]), HashSet::from(['a', '#']), tfn).unwrap();
+        let tikz = nfa_to_tikz(&nfa, None);
+        assert!(tikz.contains("(q0) edge [loop above] node {$a$} (q0)"));
+        assert!(tikz.contains("(q0) edge node {$\\varepsilon$} (q1)"));
+        assert!(tikz.contains("(q2) edge [loop above] node {$\\#$} (q2)"));
+        // unreachable q2 goes in its own column
+        assert!(tikz.contains("(q2) at (5.00, 0.00)"));
+        assert!(tikz_document(&tikz).contains("\\usetikzlibrary{automata, arrows.meta}"));
+    }
+    #[test]
+    fn dfa_tilde_is_drawn_as_a_symbol() {
+        let dfa = dfa! {
+            states: 1,
+            start: 0,
+            accept: [0],
+            alphabet: ['~'],
+            transitions: {
+                0 => { '~' => 0 },
+            },
+        };
+        let tikz = dfa_to_tikz(&dfa, None);
+        assert!(tikz.contains("(q0) edge [loop above] node {$\\sim $} (q0)"));
+        assert!(!tikz.contains("varepsilon"));
+    }
+}
@@ -0,0 +1,628 @@
+//! Nondeterministic finite-state transducers. Each transition reads an input symbol or
+//! `EPSILON` and writes an output symbol or `EPSILON`, so a transducer relates input strings to
+//! possibly many output strings. Transducers compose, which is how rewrite rules are chained
+//! into a normalization pipeline.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use crate::dfa::State;
+use crate::nfa::{EPSILON, NFA};
+
+#[derive(Debug)]
+pub enum FSTTypeError {
+    InvalidStartState,
+    InvalidAcceptState,
+    InvalidTransitionFunction,
+    ReservedCharacterInAlphabet,
+}
+
+#[derive(Debug)]
+pub enum InputError {
+    InvalidSymbol,
+    /// An epsilon-input cycle that writes output can be taken on the way to acceptance, so the
+    /// input has infinitely many outputs.
+    InfiniteOutputs,
+}
+
+/// Maps a state and an input symbol (or `EPSILON`) to the target states, each paired with the
+/// symbol (or `EPSILON`) written.
+pub type TransitionFn = HashMap<(State, char), HashSet<(State, char)>>;
+
+// a state paired with the number of input symbols read
+type Node = (State, usize);
+
+#[derive(Clone, Debug)]
+pub struct FST {
+    pub(crate) states: HashSet<State>,
+    pub(crate) start: State,
+    pub(crate) accept: HashSet<State>,
+    pub(crate) input_alphabet: HashSet<char>,
+    pub(crate) output_alphabet: HashSet<char>,
+    pub(crate) tfn: TransitionFn,
+}
+
+impl FST {
+    fn validate_fst(
+        states: usize,
+        start: usize,
+        accept: &HashSet<usize>,
+        input_alphabet: &HashSet<char>,
+        output_alphabet: &HashSet<char>,
+        tfn: &TransitionFn,
+    ) -> Result<(), FSTTypeError> {
+        if start >= states {
+            return Err(FSTTypeError::InvalidStartState);
+        }
+        if !(accept.iter().all(|&s| s < states)) {
+            return Err(FSTTypeError::InvalidAcceptState);
+        }
+        if input_alphabet.contains(&EPSILON) || output_alphabet.contains(&EPSILON) {
+            return Err(FSTTypeError::ReservedCharacterInAlphabet);
+        }
+        if !tfn
+            .keys()
+            .all(|&(s, c)| s < states && (c == EPSILON || input_alphabet.contains(&c)))
+        {
+            return Err(FSTTypeError::InvalidTransitionFunction);
+        }
+        if !tfn.values().all(|st| {
+            st.iter()
+                .all(|&(s, o)| s < states && (o == EPSILON || output_alphabet.contains(&o)))
+        }) {
+            return Err(FSTTypeError::InvalidTransitionFunction);
+        }
+        Ok(())
+    }
+
+    /// Unlike `NFA::new`, the alphabets are kept without `EPSILON`.
+    pub fn new(
+        states: usize,
+        start: usize,
+        accept: HashSet<usize>,
+        input_alphabet: HashSet<char>,
+        output_alphabet: HashSet<char>,
+        tfn: TransitionFn,
+    ) -> Result<Self, FSTTypeError> {
+        Self::validate_fst(
+            states,
+            start,
+            &accept,
+            &input_alphabet,
+            &output_alphabet,
+            &tfn,
+        )?;
+
+        Ok(Self {
+            states: HashSet::from_iter(0..states),
+            start,
+            accept,
+            input_alphabet,
+            output_alphabet,
+            tfn,
+        })
+    }
+
+    /// Every output of an accepting run on `input`, without duplicates, shortest first and
+    /// alphabetically among equal lengths.
+    pub fn apply(&self, input: &str) -> Result<Vec<String>, InputError> {
+        if !input.chars().all(|c| self.input_alphabet.contains(&c)) {
+            return Err(InputError::InvalidSymbol);
+        }
+        let input: Vec<char> = input.chars().collect();
+
+        // runs are paths in the product with the input's positions; collect its reachable part
+        let start = (self.start, 0);
+        let mut edges: HashMap<Node, Vec<(Node, char)>> = HashMap::new();
+        let mut seen = HashSet::from([start]);
+        let mut work_queue = VecDeque::from([start]);
+        while let Some((q, i)) = work_queue.pop_front() {
+            let mut out = Vec::new();
+            let reading = input.get(i).map(|&c| (c, i + 1));
+            for (c, j) in [(EPSILON, i)].into_iter().chain(reading) {
+                for &(t, o) in self.tfn.get(&(q, c)).into_iter().flatten() {
+                    out.push(((t, j), o));
+                    if seen.insert((t, j)) {
+                        work_queue.push_back((t, j));
+                    }
+                }
+            }
+            edges.insert((q, i), out);
+        }
+
+        // keep the nodes from which an accepting end is reachable
+        let mut reverse: HashMap<Node, Vec<Node>> = HashMap::new();
+        for (&from, out) in &edges {
+            for &(to, _) in out {
+                reverse.entry(to).or_default().push(from);
+            }
+        }
+        let mut live: HashSet<Node> = seen
+            .iter()
+            .copied()
+            .filter(|&(q, i)| i == input.len() && self.accept.contains(&q))
+            .collect();
+        let mut work_queue: VecDeque<_> = live.iter().copied().collect();
+        while let Some(node) = work_queue.pop_front() {
+            for &from in reverse.get(&node).into_iter().flatten() {
+                if live.insert(from) {
+                    work_queue.push_back(from);
+                }
+            }
+        }
+        let live_edges = |node| {
+            edges[&node]
+                .iter()
+                .copied()
+                .filter(|(to, _)| live.contains(to))
+        };
+
+        // an output-writing edge on a live cycle pumps unboundedly long outputs; reading input
+        // moves forward, so only epsilon-input edges (which stay at one position) form cycles
+        let same_position = |node: Node| live_edges(node).filter(move |&((_, j), _)| j == node.1);
+        let component = components(live.iter().copied(), |node| {
+            same_position(node).map(|(to, _)| to).collect()
+        });
+        for &from in &live {
+            for (to, o) in same_position(from) {
+                if o != EPSILON && component[&from] == component[&to] {
+                    return Err(InputError::InfiniteOutputs);
+                }
+            }
+        }
+
+        // outputs are now bounded, so there are finitely many (node, output) configurations
+        let mut outputs = HashSet::new();
+        let mut configurations = HashSet::new();
+        let mut work_queue = VecDeque::new();
+        if live.contains(&start) {
+            configurations.insert((start, String::new()));
+            work_queue.push_back((start, String::new()));
+        }
+        while let Some(((q, i), written)) = work_queue.pop_front() {
+            if i == input.len() && self.accept.contains(&q) {
+                outputs.insert(written.clone());
+            }
+            for (to, o) in live_edges((q, i)) {
+                let mut next = written.clone();
+                if o != EPSILON {
+                    next.push(o);
+                }
+                if configurations.insert((to, next.clone())) {
+                    work_queue.push_back((to, next));
+                }
+            }
+        }
+        let mut outputs: Vec<String> = outputs.into_iter().collect();
+        outputs.sort_by(|a, b| a.chars().count().cmp(&b.chars().count()).then(a.cmp(b)));
+        Ok(outputs)
+    }
+
+    /// The transducer that feeds this one's outputs into `other`, relating `x` to `z` when this
+    /// relates `x` to some `y` that `other` relates to `z`.
+    pub fn compose(&self, other: &FST) -> FST {
+        let start = (self.start, other.start);
+        let mut index: HashMap<(State, State), State> = HashMap::from([(start, 0)]);
+        let mut pairs = vec![start];
+        let mut tfn: TransitionFn = HashMap::new();
+        let mut s = 0;
+        while s < pairs.len() {
+            let (p, q) = pairs[s];
+            let mut moves = Vec::new();
+            for (&(from, x), targets) in &self.tfn {
+                if from != p {
+                    continue;
+                }
+                for &(p2, y) in targets {
+                    if y == EPSILON {
+                        // this side writes nothing, so the other side waits
+                        moves.push((x, (p2, q), EPSILON));
+                    } else {
+                        for &(q2, z) in other.tfn.get(&(q, y)).into_iter().flatten() {
+                            moves.push((x, (p2, q2), z));
+                        }
+                    }
+                }
+            }
+            for &(q2, z) in other.tfn.get(&(q, EPSILON)).into_iter().flatten() {
+                moves.push((EPSILON, (p, q2), z));
+            }
+            for (x, target, z) in moves {
+                let t = *index.entry(target).or_insert_with(|| {
+                    pairs.push(target);
+                    pairs.len() - 1
+                });
+                tfn.entry((s, x)).or_default().insert((t, z));
+            }
+            s += 1;
+        }
+        let accept = pairs
+            .iter()
+            .enumerate()
+            .filter(|(_, (p, q))| self.accept.contains(p) && other.accept.contains(q))
+            .map(|(s, _)| s)
+            .collect();
+        FST::new(
+            pairs.len(),
+            0,
+            accept,
+            self.input_alphabet.clone(),
+            other.output_alphabet.clone(),
+            tfn,
+        )
+        .expect("composition of valid transducers is valid")
+    }
+
+    /// The inverse relation, with input and output swapped on every transition.
+    pub fn invert(&self) -> FST {
+        let mut tfn: TransitionFn = HashMap::new();
+        for (&(s, x), targets) in &self.tfn {
+            for &(t, y) in targets {
+                tfn.entry((s, y)).or_default().insert((t, x));
+            }
+        }
+        FST::new(
+            self.states.len(),
+            self.start,
+            self.accept.clone(),
+            self.output_alphabet.clone(),
+            self.input_alphabet.clone(),
+            tfn,
+        )
+        .expect("inverse of a valid transducer is valid")
+    }
+
+    /// The `NFA` accepting the inputs that have at least one output.
+    pub fn project_input(&self) -> NFA {
+        self.project(&self.input_alphabet, |x, _| x)
+    }
+
+    /// The `NFA` accepting every output of every input.
+    pub fn project_output(&self) -> NFA {
+        self.project(&self.output_alphabet, |_, y| y)
+    }
+
+    fn project(&self, alphabet: &HashSet<char>, label: impl Fn(char, char) -> char) -> NFA {
+        let mut tfn: HashMap<(State, char), HashSet<State>> = HashMap::new();
+        for (&(s, x), targets) in &self.tfn {
+            for &(t, y) in targets {
+                tfn.entry((s, label(x, y))).or_default().insert(t);
+            }
+        }
+        NFA::new(
+            self.states.len(),
+            self.start,
+            self.accept.clone(),
+            alphabet.clone(),
+            tfn,
+        )
+        .expect("projection of a valid transducer is a valid NFA")
+    }
+
+    /// Relates `x` to `y` when either transducer does.
+    pub fn union(&self, other: &FST) -> FST {
+        // a fresh start state 0, then this transducer's states, then the other's
+        let offset = 1 + self.states.len();
+        let mut tfn = shifted(&self.tfn, 1);
+        tfn.extend(shifted(&other.tfn, offset));
+        tfn.insert(
+            (0, EPSILON),
+            HashSet::from([(self.start + 1, EPSILON), (other.start + offset, EPSILON)]),
+        );
+        let accept = self
+            .accept
+            .iter()
+            .map(|s| s + 1)
+            .chain(other.accept.iter().map(|s| s + offset))
+            .collect();
+        self.combined(other, offset + other.states.len(), 0, accept, tfn)
+    }
+
+    /// Relates `x1 x2` to `y1 y2` when this transducer relates `x1` to `y1` and `other` relates
+    /// `x2` to `y2`.
+    pub fn concat(&self, other: &FST) -> FST {
+        let offset = self.states.len();
+        let mut tfn = shifted(&self.tfn, 0);
+        tfn.extend(shifted(&other.tfn, offset));
+        for &s in &self.accept {
+            tfn.entry((s, EPSILON))
+                .or_default()
+                .insert((other.start + offset, EPSILON));
+        }
+        let accept = other.accept.iter().map(|s| s + offset).collect();
+        self.combined(other, offset + other.states.len(), self.start, accept, tfn)
+    }
+
+    /// Relates `x1 … xn` to `y1 … yn` whenever this transducer relates each `xi` to `yi`,
+    /// including the empty string to itself.
+    pub fn star(&self) -> FST {
+        // a fresh accepting start state 0 that every accepting state returns to
+        let mut tfn = shifted(&self.tfn, 1);
+        tfn.insert((0, EPSILON), HashSet::from([(self.start + 1, EPSILON)]));
+        for &s in &self.accept {
+            tfn.entry((s + 1, EPSILON))
+                .or_default()
+                .insert((0, EPSILON));
+        }
+        FST::new(
+            self.states.len() + 1,
+            0,
+            HashSet::from([0]),
+            self.input_alphabet.clone(),
+            self.output_alphabet.clone(),
+            tfn,
+        )
+        .expect("star of a valid transducer is valid")
+    }
+
+    fn combined(
+        &self,
+        other: &FST,
+        states: usize,
+        start: State,
+        accept: HashSet<State>,
+        tfn: TransitionFn,
+    ) -> FST {
+        FST::new(
+            states,
+            start,
+            accept,
+            self.input_alphabet
+                .union(&other.input_alphabet)
+                .copied()
+                .collect(),
+            self.output_alphabet
+                .union(&other.output_alphabet)
+                .copied()
+                .collect(),
+            tfn,
+        )
+        .expect("combination of valid transducers is valid")
+    }
+}
+
+fn shifted(tfn: &TransitionFn, offset: usize) -> TransitionFn {
+    tfn.iter()
+        .map(|(&(s, x), targets)| {
+            let targets = targets.iter().map(|&(t, y)| (t + offset, y)).collect();
+            ((s + offset, x), targets)
+        })
+        .collect()
+}
+
+/// The strongly connected component of every node reachable from `nodes` along `edges`, as a
+/// component number per node. Tarjan's algorithm, with an explicit stack instead of recursion.
+fn components<N>(
+    nodes: impl IntoIterator<Item = N>,
+    edges: impl Fn(N) -> Vec<N>,
+) -> HashMap<N, usize>
+where
+    N: Copy + Eq + std::hash::Hash,
+{
+    let mut index: HashMap<N, usize> = HashMap::new();
+    let mut low: HashMap<N, usize> = HashMap::new();
+    let mut component: HashMap<N, usize> = HashMap::new();
+    let mut stack: Vec<N> = Vec::new();
+    // (node, its successors, how many of them have been visited)
+    let mut calls: Vec<(N, Vec<N>, usize)> = Vec::new();
+    let mut count = 0;
+    for root in nodes {
+        if index.contains_key(&root) {
+            continue;
+        }
+        let mut entering = Some(root);
+        loop {
+            if let Some(node) = entering.take() {
+                let i = index.len();
+                index.insert(node, i);
+                low.insert(node, i);
+                stack.push(node);
+                calls.push((node, edges(node), 0));
+            }
+            let Some((node, successors, visited)) = calls.last_mut() else {
+                break;
+            };
+            let node = *node;
+            if let Some(&next) = successors.get(*visited) {
+                *visited += 1;
+                if !index.contains_key(&next) {
+                    entering = Some(next);
+                } else if !component.contains_key(&next) {
+                    // still on the stack
+                    let lowest = low[&node].min(index[&next]);
+                    low.insert(node, lowest);
+                }
+                continue;
+            }
+            calls.pop();
+            if let Some(&(parent, ..)) = calls.last() {
+                let lowest = low[&parent].min(low[&node]);
+                low.insert(parent, lowest);
+            }
+            if low[&node] == index[&node] {
+                while let Some(member) = stack.pop() {
+                    component.insert(member, count);
+                    if member == node {
+                        break;
+                    }
+                }
+                count += 1;
+            }
+        }
+    }
+    component
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nfa::SimulationResult;
+
+    /// A one-state transducer applying `rule` to each symbol independently.
+    fn per_symbol(rule: &[(char, char)]) -> FST {
+        let mut tfn: TransitionFn = HashMap::new();
+        for &(x, y) in rule {
+            tfn.entry((0, x)).or_default().insert((0, y));
+        }
+        let symbols = |pick: fn(&(char, char)) -> char| {
+            rule.iter().map(pick).filter(|&c| c != EPSILON).collect()
+        };
+        FST::new(
+            1,
+            0,
+            HashSet::from([0]),
+            symbols(|r| r.0),
+            symbols(|r| r.1),
+            tfn,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn validation_errors() {
+        let tfn = HashMap::from([((0, 'a'), HashSet::from([(1, 'b')]))]);
+        let ab = || (HashSet::from(['a']), HashSet::from(['b']));
+        let (i, o) = ab();
+        assert!(matches!(
+            FST::new(1, 0, HashSet::new(), i, o, tfn.clone()),
+            Err(FSTTypeError::InvalidTransitionFunction)
+        ));
+        let (i, _) = ab();
+        assert!(matches!(
+            FST::new(2, 0, HashSet::new(), i, HashSet::from(['c']), tfn.clone()),
+            Err(FSTTypeError::InvalidTransitionFunction)
+        ));
+        let (_, o) = ab();
+        assert!(matches!(
+            FST::new(2, 0, HashSet::new(), HashSet::from(['a', EPSILON]), o, tfn),
+            Err(FSTTypeError::ReservedCharacterInAlphabet)
+        ));
+    }
+
+    #[test]
+    fn apply_enumerates_all_outputs() {
+        // a may be capitalized, x is deleted
+        let t = per_symbol(&[('a', 'a'), ('a', 'A'), ('b', 'b'), ('x', EPSILON)]);
+        assert_eq!(t.apply("axb").unwrap(), ["Ab", "ab"]);
+        assert_eq!(t.apply("xx").unwrap(), [""]);
+        assert!(matches!(t.apply("c"), Err(InputError::InvalidSymbol)));
+
+        // inserting any number of !'s anywhere
+        let bang = FST::new(
+            1,
+            0,
+            HashSet::from([0]),
+            HashSet::from(['a']),
+            HashSet::from(['a', '!']),
+            HashMap::from([
+                ((0, 'a'), HashSet::from([(0, 'a')])),
+                ((0, EPSILON), HashSet::from([(0, '!')])),
+            ]),
+        )
+        .unwrap();
+        assert!(matches!(bang.apply("a"), Err(InputError::InfiniteOutputs)));
+    }
+
+    #[test]
+    fn dead_output_cycles_do_not_make_outputs_infinite() {
+        // state 1 loops writing z but can never accept
+        let tfn = HashMap::from([
+            ((0, 'a'), HashSet::from([(0, 'a')])),
+            ((0, EPSILON), HashSet::from([(1, EPSILON)])),
+            ((1, EPSILON), HashSet::from([(1, 'z')])),
+        ]);
+        let t = FST::new(
+            2,
+            0,
+            HashSet::from([0]),
+            HashSet::from(['a']),
+            HashSet::from(['a', 'z']),
+            tfn,
+        )
+        .unwrap();
+        assert_eq!(t.apply("aa").unwrap(), ["aa"]);
+    }
+
+    #[test]
+    fn silent_epsilon_cycles_keep_outputs_finite() {
+        // 0 and 1 form an epsilon cycle at every position, but it writes nothing
+        let t = FST::new(
+            2,
+            0,
+            HashSet::from([0]),
+            HashSet::from(['a']),
+            HashSet::from(['a']),
+            HashMap::from([
+                ((0, 'a'), HashSet::from([(0, 'a')])),
+                ((0, EPSILON), HashSet::from([(1, EPSILON)])),
+                ((1, EPSILON), HashSet::from([(0, EPSILON)])),
+            ]),
+        )
+        .unwrap();
+        let input = "a".repeat(2_000);
+        assert_eq!(t.apply(&input).unwrap(), [input]);
+    }
+
+    #[test]
+    fn compose_and_invert() {
+        let upper = per_symbol(&[('a', 'A'), ('b', 'B')]);
+        // A becomes 1, B is deleted, and a 0 may be inserted before any A
+        let digits = FST::new(
+            2,
+            0,
+            HashSet::from([0]),
+            HashSet::from(['A', 'B']),
+            HashSet::from(['0', '1']),
+            HashMap::from([
+                ((0, 'A'), HashSet::from([(0, '1')])),
+                ((0, 'B'), HashSet::from([(0, EPSILON)])),
+                ((0, EPSILON), HashSet::from([(1, '0')])),
+                ((1, 'A'), HashSet::from([(0, '1')])),
+            ]),
+        )
+        .unwrap();
+
+        let pipeline = upper.compose(&digits);
+        assert_eq!(pipeline.apply("aba").unwrap(), ["11", "011", "101", "0101"]);
+        assert_eq!(upper.invert().apply("BAB").unwrap(), ["bab"]);
+        // any number of b's maps back to the deleted B's
+        assert!(matches!(
+            pipeline.invert().apply("101"),
+            Err(InputError::InfiniteOutputs)
+        ));
+    }
+
+    #[test]
+    fn projections() {
+        let t = per_symbol(&[('a', 'b'), ('c', EPSILON)]).concat(&per_symbol(&[('d', 'e')]));
+        let accepts = |nfa: &NFA, w| matches!(nfa.simulate(w), Ok(SimulationResult::Accepted));
+        let input = t.project_input();
+        assert!(accepts(&input, "acad"));
+        assert!(!accepts(&input, "b"));
+        let output = t.project_output();
+        assert!(accepts(&output, "bbe"));
+        assert!(!accepts(&output, "eb"));
+    }
+
+    #[test]
+    fn union_concat_star() {
+        let a_to_b = per_symbol(&[('a', 'b')]).concat(&per_symbol(&[]));
+        let swap = per_symbol(&[('a', 'x')]).union(&per_symbol(&[('a', 'y')]));
+        assert_eq!(swap.apply("aa").unwrap(), ["xx", "yy"]);
+        let once = FST::new(
+            2,
+            0,
+            HashSet::from([1]),
+            HashSet::from(['a']),
+            HashSet::from(['x', 'y']),
+            HashMap::from([((0, 'a'), HashSet::from([(1, 'x'), (1, 'y')]))]),
+        )
+        .unwrap();
+        assert_eq!(
+            once.concat(&once).apply("aa").unwrap(),
+            ["xx", "xy", "yx", "yy"]
+        );
+        assert_eq!(once.star().apply("aa").unwrap(), ["xx", "xy", "yx", "yy"]);
+        assert_eq!(once.star().apply("").unwrap(), [""]);
+        assert_eq!(a_to_b.apply("aaa").unwrap(), ["bbb"]);
+    }
+}
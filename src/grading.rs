@@ -0,0 +1,307 @@
+//! Autograding: compare a submitted machine with a reference and report what is wrong.
+//!
+//! Both sides are determinized and completed over the union of their alphabets (a symbol a
+//! machine does not know rejects the input). Counterexamples come from the product automata for
+//! "submission accepts, reference rejects" and the converse, enumerated in shortlex order, so the
+//! shortest ones are reported first. The score is exact: the number of strings up to the length
+//! bound on which the two agree, counted by path counting on the product.
+
+use std::collections::BTreeSet;
+
+use num_bigint::BigUint;
+use num_traits::ToPrimitive;
+
+use crate::algorithms::equivalence::{complete, product, shortest};
+use crate::algorithms::language::Language;
+use crate::algorithms::minimize_dfa::minimize_dfa;
+use crate::algorithms::subset_construction::determinize;
+use crate::dfa::DFA;
+use crate::nfa::{NFA, NFATypeError};
+use crate::regex::Regex;
+
+/// Anything that can serve as a reference or a submission.
+#[derive(Clone)]
+pub enum Machine {
+    DFA(DFA),
+    NFA(NFA),
+    Regex(Regex),
+}
+
+impl From<DFA> for Machine {
+    fn from(dfa: DFA) -> Self {
+        Machine::DFA(dfa)
+    }
+}
+
+impl From<NFA> for Machine {
+    fn from(nfa: NFA) -> Self {
+        Machine::NFA(nfa)
+    }
+}
+
+impl From<Regex> for Machine {
+    fn from(regex: Regex) -> Self {
+        Machine::Regex(regex)
+    }
+}
+
+impl Machine {
+    /// Fails only for a regex that uses `EPSILON` as a symbol; see `Regex::to_nfa`.
+    pub fn to_dfa(&self) -> Result<DFA, NFATypeError> {
+        Ok(match self {
+            Machine::DFA(dfa) => dfa.clone(),
+            Machine::NFA(nfa) => determinize(nfa),
+            Machine::Regex(regex) => determinize(&regex.to_nfa()?),
+        })
+    }
+
+    /// Number of states as written, or `None` for a regex.
+    pub fn state_count(&self) -> Option<usize> {
+        match self {
+            Machine::DFA(dfa) => Some(dfa.states.len()),
+            Machine::NFA(nfa) => Some(nfa.states.len()),
+            Machine::Regex(_) => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GradingOptions {
+    /// At most this many counterexamples of each kind are reported; fewer only when fewer
+    /// exist.
+    pub max_counterexamples: usize,
+    /// The score counts all strings of length at most this.
+    pub score_length: usize,
+}
+
+impl Default for GradingOptions {
+    fn default() -> Self {
+        Self {
+            max_counterexamples: 5,
+            score_length: 10,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct GradeReport {
+    pub equivalent: bool,
+    /// Strings the submission accepts but the reference rejects, shortest first.
+    pub wrongly_accepted: Vec<String>,
+    /// Strings the submission rejects but the reference accepts, shortest first.
+    pub wrongly_rejected: Vec<String>,
+    /// Strings of length at most `score_length` classified the same way by both.
+    pub agreeing: BigUint,
+    /// All strings of length at most `score_length`.
+    pub total: BigUint,
+    pub submitted_states: Option<usize>,
+    /// States of the minimal DFA of the submission's language.
+    pub submission_minimal_states: usize,
+    /// States of the minimal DFA of the reference language.
+    pub minimal_states: usize,
+    pub feedback: Vec<String>,
+}
+
+impl GradeReport {
+    /// `agreeing / total`, in `[0, 1]`. Both counts are shifted down to the precision of an
+    /// `f64` first, so the ratio stays finite however large `total` is.
+    pub fn score(&self) -> f64 {
+        let shift = self
+            .total
+            .bits()
+            .saturating_sub(u64::from(f64::MANTISSA_DIGITS));
+        let agreeing = (&self.agreeing >> shift).to_f64().unwrap_or(f64::MAX);
+        let total = (&self.total >> shift).to_f64().unwrap_or(f64::MAX);
+        agreeing / total
+    }
+}
+
+/// Fails only if either machine is a regex without an NFA; see `Machine::to_dfa`.
+pub fn grade(
+    reference: &Machine,
+    submission: &Machine,
+    options: &GradingOptions,
+) -> Result<GradeReport, NFATypeError> {
+    let reference_dfa = reference.to_dfa()?;
+    let submission_dfa = submission.to_dfa()?;
+    let alphabet: BTreeSet<char> = reference_dfa
+        .alphabet
+        .union(&submission_dfa.alphabet)
+        .copied()
+        .collect();
+    // minimal sizes are over each machine's own alphabet, before completion adds a trap state
+    let minimal_states = minimize_dfa(&reference_dfa).states.len();
+    let submission_minimal_states = minimize_dfa(&submission_dfa).states.len();
+    let reference_dfa = complete(&reference_dfa, &alphabet);
+    let submission_dfa = complete(&submission_dfa, &alphabet);
+
+    let over = product(&reference_dfa, &submission_dfa, |r, s| s && !r);
+    let under = product(&reference_dfa, &submission_dfa, |r, s| r && !s);
+    let wrongly_accepted = shortest(&over, options.max_counterexamples);
+    let wrongly_rejected = shortest(&under, options.max_counterexamples);
+    let equivalent = wrongly_accepted.is_empty() && wrongly_rejected.is_empty();
+
+    let mut disagreeing = BigUint::ZERO;
+    let mut total = BigUint::ZERO;
+    let mut over = Language::of_dfa(&over);
+    let mut under = Language::of_dfa(&under);
+    for n in 0..=options.score_length {
+        disagreeing += over.count_accepted(n) + under.count_accepted(n);
+        total += BigUint::from(alphabet.len()).pow(n as u32);
+    }
+
+    let submitted_states = submission.state_count();
+    let mut feedback = Vec::new();
+    if equivalent {
+        feedback.push("your machine accepts exactly the reference language".to_string());
+    } else {
+        if let Some(w) = wrongly_accepted.first() {
+            feedback.push(format!(
+                "your machine accepts {w:?}, which it should reject"
+            ));
+        }
+        if let Some(w) = wrongly_rejected.first() {
+            feedback.push(format!(
+                "your machine rejects {w:?}, which it should accept"
+            ));
+        }
+    }
+    if let Some(states) = submitted_states {
+        if states > minimal_states {
+            feedback.push(format!(
+                "your machine has {states} states, the minimal DFA has {minimal_states}"
+            ));
+        }
+        if !equivalent && states < minimal_states && matches!(submission, Machine::DFA(_)) {
+            feedback.push(format!(
+                "your machine has {states} states, but any correct DFA needs at least \
+                {minimal_states}"
+            ));
+        }
+    }
+
+    Ok(GradeReport {
+        equivalent,
+        wrongly_accepted,
+        wrongly_rejected,
+        agreeing: &total - disagreeing,
+        total,
+        submitted_states,
+        submission_minimal_states,
+        minimal_states,
+        feedback,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fsim_macros::dfa;
+    use std::collections::HashSet;
+
+    // strings over {a, b} ending in bb
+    fn ends_in_bb() -> DFA {
+        dfa! {
+            states: 3,
+            start: 0,
+            accept: [2],
+            alphabet: ['a', 'b'],
+            transitions: {
+                0 => { 'a' => 0, 'b' => 1 },
+                1 => { 'a' => 0, 'b' => 2 },
+                2 => { 'a' => 0, 'b' => 2 },
+            },
+        }
+    }
+
+    #[test]
+    fn equivalent_submission_gets_full_marks() {
+        let reference: Regex = "(a|b)*bb".parse().unwrap();
+        let report = grade(
+            &reference.into(),
+            &ends_in_bb().into(),
+            &GradingOptions::default(),
+        )
+        .unwrap();
+        assert!(report.equivalent);
+        assert_eq!(report.score(), 1.0);
+        assert_eq!(report.minimal_states, 3);
+        assert_eq!(report.wrongly_accepted, Vec::<String>::new());
+    }
+
+    #[test]
+    fn counterexamples_are_shortest_first() {
+        let reference: Regex = "(a|b)*abb".parse().unwrap();
+        let report = grade(
+            &reference.into(),
+            &ends_in_bb().into(),
+            &GradingOptions {
+                max_counterexamples: 3,
+                score_length: 4,
+            },
+        )
+        .unwrap();
+        assert!(!report.equivalent);
+        assert_eq!(report.wrongly_accepted, ["bb", "bbb", "abbb"]);
+        assert!(report.wrongly_rejected.is_empty());
+        // up to length 4 there are 31 strings; bb, bbb, abbb, bbbb are misclassified
+        assert_eq!(report.total, BigUint::from(31u8));
+        assert_eq!(report.agreeing, BigUint::from(27u8));
+        assert_eq!(
+            report.feedback[0],
+            "your machine accepts \"bb\", which it should reject"
+        );
+    }
+
+    #[test]
+    fn structural_feedback_and_foreign_symbols() {
+        // ends_in_bb with a redundant copy of state 0, over {a, b, c}: c goes to state 3
+        let mut tfn = ends_in_bb().tfn;
+        tfn.insert((0, 'c'), 3);
+        tfn.insert((1, 'c'), 3);
+        tfn.insert((2, 'c'), 3);
+        tfn.insert((3, 'a'), 3);
+        tfn.insert((3, 'b'), 1);
+        tfn.insert((3, 'c'), 3);
+        let submission = DFA::new(
+            4,
+            0,
+            HashSet::from([2]),
+            HashSet::from(['a', 'b', 'c']),
+            tfn,
+        )
+        .unwrap();
+        let reference = ends_in_bb();
+        let report = grade(
+            &reference.into(),
+            &submission.into(),
+            &GradingOptions::default(),
+        )
+        .unwrap();
+        // the reference knows no 'c', so it rejects "cbb" which the submission accepts
+        assert_eq!(report.wrongly_accepted[0], "cbb");
+        assert_eq!(report.submission_minimal_states, 3);
+        assert!(
+            report
+                .feedback
+                .contains(&"your machine has 4 states, the minimal DFA has 3".to_string())
+        );
+    }
+
+    #[test]
+    fn score_is_finite_for_long_bounds() {
+        let reference: Regex = "(a|b)*abb".parse().unwrap();
+        let report = grade(
+            &reference.into(),
+            &ends_in_bb().into(),
+            &GradingOptions {
+                max_counterexamples: 1,
+                score_length: 2000,
+            },
+        )
+        .unwrap();
+        assert!(report.total.to_f64().unwrap().is_infinite());
+        // asymptotically the two disagree exactly on strings ending in "bbb"
+        assert!((report.score() - 0.875).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,498 @@
+//! Layered (Sugiyama-style) layout for drawing machines.
+//!
+//! States are put in columns by breadth-first distance from the start state, so the start state
+//! is leftmost. Edges spanning several columns are routed through invisible dummy nodes, and the
+//! order within each column is chosen by repeated barycenter sweeps to reduce crossings. The
+//! result is renderer-neutral geometry: node centres, and every edge as a chain of cubic Bézier
+//! segments with a label anchor. Coordinates are in abstract units with y growing downwards.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::dfa::State;
+use crate::formats::diagram::Diagram;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Point {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Point {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y }
+    }
+
+    fn add(self, o: Point) -> Point {
+        Point::new(self.x + o.x, self.y + o.y)
+    }
+
+    fn sub(self, o: Point) -> Point {
+        Point::new(self.x - o.x, self.y - o.y)
+    }
+
+    fn scale(self, k: f64) -> Point {
+        Point::new(self.x * k, self.y * k)
+    }
+
+    fn len(self) -> f64 {
+        self.x.hypot(self.y)
+    }
+
+    fn unit(self) -> Point {
+        let l = self.len();
+        if l == 0.0 {
+            Point::new(1.0, 0.0)
+        } else {
+            self.scale(1.0 / l)
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LayoutOptions {
+    pub node_radius: f64,
+    pub layer_gap: f64,
+    pub row_gap: f64,
+    pub margin: f64,
+    /// Barycenter sweeps for crossing reduction; the best ordering seen is kept.
+    pub sweeps: usize,
+}
+
+impl Default for LayoutOptions {
+    fn default() -> Self {
+        Self {
+            node_radius: 20.0,
+            layer_gap: 100.0,
+            row_gap: 80.0,
+            margin: 40.0,
+            sweeps: 8,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EdgeKind {
+    Straight,
+    /// Curved to one side, used for edges with a reverse partner and edges within a column.
+    Bent,
+    /// Routed through dummy nodes across several columns.
+    Routed,
+    Loop,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CubicSegment {
+    pub c1: Point,
+    pub c2: Point,
+    pub end: Point,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct EdgeGeometry {
+    /// Index into `Diagram::edges`.
+    pub edge: usize,
+    pub kind: EdgeKind,
+    pub start: Point,
+    pub segments: Vec<CubicSegment>,
+    pub label: Point,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Layout {
+    /// Centre of every state, indexed by state.
+    pub nodes: Vec<Point>,
+    pub node_radius: f64,
+    pub layers: Vec<Vec<State>>,
+    pub edges: Vec<EdgeGeometry>,
+    pub width: f64,
+    pub height: f64,
+    pub crossings: usize,
+}
+
+impl Layout {
+    /// The point where an edge arrives, for drawing its arrowhead.
+    pub fn edge_end(&self, edge: &EdgeGeometry) -> Point {
+        edge.segments.last().map_or(edge.start, |s| s.end)
+    }
+}
+
+// a vertex of the layered graph: a real state, or a dummy standing in for an edge in a column
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+enum Vertex {
+    State(State),
+    Dummy { edge: usize, step: usize },
+}
+
+fn assign_layers(d: &Diagram) -> Vec<usize> {
+    let mut layer: Vec<Option<usize>> = vec![None; d.states];
+    let mut next_root_layer = 0;
+    let roots = std::iter::once(d.start).chain(0..d.states);
+    for root in roots {
+        if layer[root].is_some() {
+            continue;
+        }
+        layer[root] = Some(next_root_layer);
+        let mut work_queue = VecDeque::from([root]);
+        while let Some(s) = work_queue.pop_front() {
+            for e in d.edges.iter().filter(|e| e.from == s) {
+                if layer[e.to].is_none() {
+                    layer[e.to] = Some(layer[s].unwrap() + 1);
+                    work_queue.push_back(e.to);
+                }
+            }
+        }
+        // unreachable components go to the right of everything placed so far
+        next_root_layer = layer.iter().flatten().max().unwrap() + 1;
+    }
+    layer.into_iter().map(Option::unwrap).collect()
+}
+
+fn count_crossings(order: &[Vec<Vertex>], links: &[(Vertex, Vertex)]) -> usize {
+    let mut position: HashMap<Vertex, (usize, usize)> = HashMap::new();
+    for (l, vs) in order.iter().enumerate() {
+        for (i, &v) in vs.iter().enumerate() {
+            position.insert(v, (l, i));
+        }
+    }
+    // links normalised to (left, right) between adjacent layers
+    let spans: Vec<(usize, usize, usize)> = links
+        .iter()
+        .filter_map(|(a, b)| {
+            let (la, ia) = position[a];
+            let (lb, ib) = position[b];
+            match la.cmp(&lb) {
+                std::cmp::Ordering::Less => Some((la, ia, ib)),
+                std::cmp::Ordering::Greater => Some((lb, ib, ia)),
+                std::cmp::Ordering::Equal => None,
+            }
+        })
+        .collect();
+    let mut crossings = 0;
+    for (i, &(l1, a1, b1)) in spans.iter().enumerate() {
+        for &(l2, a2, b2) in &spans[i + 1..] {
+            if l1 == l2 && ((a1 < a2 && b1 > b2) || (a1 > a2 && b1 < b2)) {
+                crossings += 1;
+            }
+        }
+    }
+    crossings
+}
+
+fn order_layers(
+    mut order: Vec<Vec<Vertex>>,
+    links: &[(Vertex, Vertex)],
+    sweeps: usize,
+) -> (Vec<Vec<Vertex>>, usize) {
+    let mut neighbours: HashMap<Vertex, Vec<Vertex>> = HashMap::new();
+    for &(a, b) in links {
+        neighbours.entry(a).or_default().push(b);
+        neighbours.entry(b).or_default().push(a);
+    }
+
+    let mut best_crossings = count_crossings(&order, links);
+    let mut best = order.clone();
+    for sweep in 0..sweeps {
+        let down = sweep % 2 == 0;
+        let layer_ids: Vec<usize> = if down {
+            (1..order.len()).collect()
+        } else {
+            (0..order.len().saturating_sub(1)).rev().collect()
+        };
+        for l in layer_ids {
+            let fixed = if down { l - 1 } else { l + 1 };
+            let fixed_pos: HashMap<Vertex, usize> = order[fixed]
+                .iter()
+                .enumerate()
+                .map(|(i, &v)| (v, i))
+                .collect();
+            let mut keyed: Vec<(f64, usize, Vertex)> = order[l]
+                .iter()
+                .enumerate()
+                .map(|(i, &v)| {
+                    let ps: Vec<usize> = neighbours
+                        .get(&v)
+                        .into_iter()
+                        .flatten()
+                        .filter_map(|n| fixed_pos.get(n).copied())
+                        .collect();
+                    // vertices with no neighbour in the fixed layer keep their place
+                    let key = if ps.is_empty() {
+                        i as f64
+                    } else {
+                        ps.iter().sum::<usize>() as f64 / ps.len() as f64
+                    };
+                    (key, i, v)
+                })
+                .collect();
+            keyed.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.cmp(&b.1)));
+            order[l] = keyed.into_iter().map(|(_, _, v)| v).collect();
+        }
+        let crossings = count_crossings(&order, links);
+        if crossings < best_crossings {
+            best_crossings = crossings;
+            best = order.clone();
+        }
+    }
+    (best, best_crossings)
+}
+
+fn straight(edge: usize, from: Point, to: Point, r: f64) -> EdgeGeometry {
+    let dir = to.sub(from).unit();
+    let start = from.add(dir.scale(r));
+    let end = to.sub(dir.scale(r));
+    let d = end.sub(start);
+    EdgeGeometry {
+        edge,
+        kind: EdgeKind::Straight,
+        start,
+        segments: vec![CubicSegment {
+            c1: start.add(d.scale(1.0 / 3.0)),
+            c2: start.add(d.scale(2.0 / 3.0)),
+            end,
+        }],
+        label: start
+            .add(d.scale(0.5))
+            .add(Point::new(d.y, -d.x).unit().scale(r * 0.6)),
+    }
+}
+
+/// A quadratic curve bulging to the left of the direction of travel, so the two edges of a
+/// bidirectional pair separate.
+fn bent(edge: usize, from: Point, to: Point, r: f64) -> EdgeGeometry {
+    let d = to.sub(from);
+    let normal = Point::new(d.y, -d.x).unit();
+    let control = from.add(d.scale(0.5)).add(normal.scale(d.len() * 0.25 + r));
+    let start = from.add(control.sub(from).unit().scale(r));
+    let end = to.add(control.sub(to).unit().scale(r));
+    EdgeGeometry {
+        edge,
+        kind: EdgeKind::Bent,
+        start,
+        segments: vec![CubicSegment {
+            c1: start.add(control.sub(start).scale(2.0 / 3.0)),
+            c2: end.add(control.sub(end).scale(2.0 / 3.0)),
+            end,
+        }],
+        label: start
+            .scale(0.25)
+            .add(control.scale(0.5))
+            .add(end.scale(0.25))
+            .add(normal.scale(r * 0.4)),
+    }
+}
+
+fn self_loop(edge: usize, centre: Point, r: f64) -> EdgeGeometry {
+    let (sx, sy) = (r * 0.5, r * 0.866);
+    EdgeGeometry {
+        edge,
+        kind: EdgeKind::Loop,
+        start: Point::new(centre.x - sx, centre.y - sy),
+        segments: vec![CubicSegment {
+            c1: Point::new(centre.x - r * 1.2, centre.y - r * 2.6),
+            c2: Point::new(centre.x + r * 1.2, centre.y - r * 2.6),
+            end: Point::new(centre.x + sx, centre.y - sy),
+        }],
+        label: Point::new(centre.x, centre.y - r * 2.6),
+    }
+}
+
+/// A smooth curve through `points` (Catmull-Rom converted to Bézier), trimmed at both nodes.
+fn routed(edge: usize, mut points: Vec<Point>, r: f64) -> EdgeGeometry {
+    let n = points.len();
+    points[0] = points[0].add(points[1].sub(points[0]).unit().scale(r));
+    points[n - 1] = points[n - 1].add(points[n - 2].sub(points[n - 1]).unit().scale(r));
+    let at = |i: isize| points[i.clamp(0, n as isize - 1) as usize];
+    let segments = (0..n - 1)
+        .map(|i| {
+            let i = i as isize;
+            CubicSegment {
+                c1: at(i).add(at(i + 1).sub(at(i - 1)).scale(1.0 / 6.0)),
+                c2: at(i + 1).sub(at(i + 2).sub(at(i)).scale(1.0 / 6.0)),
+                end: at(i + 1),
+            }
+        })
+        .collect();
+    EdgeGeometry {
+        edge,
+        kind: EdgeKind::Routed,
+        start: points[0],
+        segments,
+        label: points[n / 2].add(Point::new(0.0, -r * 0.6)),
+    }
+}
+
+pub fn layout(d: &Diagram, options: &LayoutOptions) -> Layout {
+    let layer_of = assign_layers(d);
+    let layer_count = layer_of.iter().max().map_or(0, |m| m + 1);
+
+    let mut order: Vec<Vec<Vertex>> = vec![Vec::new(); layer_count];
+    for s in 0..d.states {
+        order[layer_of[s]].push(Vertex::State(s));
+    }
+    // chains of dummies for edges spanning more than one column, in either direction
+    let mut links = Vec::new();
+    let mut chains: HashMap<usize, Vec<Vertex>> = HashMap::new();
+    for (i, e) in d.edges.iter().enumerate() {
+        if e.is_loop() {
+            continue;
+        }
+        let (lf, lt) = (layer_of[e.from], layer_of[e.to]);
+        let mut chain = vec![Vertex::State(e.from)];
+        let between: Vec<usize> = if lf < lt {
+            (lf + 1..lt).collect()
+        } else {
+            (lt + 1..lf).rev().collect()
+        };
+        for (step, l) in between.into_iter().enumerate() {
+            let dummy = Vertex::Dummy { edge: i, step };
+            order[l].push(dummy);
+            chain.push(dummy);
+        }
+        chain.push(Vertex::State(e.to));
+        links.extend(chain.windows(2).map(|w| (w[0], w[1])));
+        chains.insert(i, chain);
+    }
+
+    let (order, crossings) = order_layers(order, &links, options.sweeps);
+
+    let tallest = order.iter().map(Vec::len).max().unwrap_or(0);
+    let mut position: HashMap<Vertex, Point> = HashMap::new();
+    for (l, vs) in order.iter().enumerate() {
+        let offset = (tallest - vs.len()) as f64 / 2.0;
+        for (i, &v) in vs.iter().enumerate() {
+            position.insert(
+                v,
+                Point::new(
+                    options.margin + l as f64 * options.layer_gap,
+                    options.margin + (i as f64 + offset) * options.row_gap,
+                ),
+            );
+        }
+    }
+
+    let nodes: Vec<Point> = (0..d.states).map(|s| position[&Vertex::State(s)]).collect();
+    let r = options.node_radius;
+    let edges = d
+        .edges
+        .iter()
+        .enumerate()
+        .map(|(i, e)| {
+            let (from, to) = (nodes[e.from], nodes[e.to]);
+            if e.is_loop() {
+                return self_loop(i, from, r);
+            }
+            let chain = &chains[&i];
+            if chain.len() > 2 {
+                return routed(i, chain.iter().map(|v| position[v]).collect(), r);
+            }
+            if d.has_reverse(e) || layer_of[e.from] == layer_of[e.to] {
+                return bent(i, from, to, r);
+            }
+            straight(i, from, to, r)
+        })
+        .collect();
+
+    Layout {
+        nodes,
+        node_radius: r,
+        layers: order
+            .iter()
+            .map(|vs| {
+                vs.iter()
+                    .filter_map(|v| match v {
+                        Vertex::State(s) => Some(*s),
+                        Vertex::Dummy { .. } => None,
+                    })
+                    .collect()
+            })
+            .collect(),
+        edges,
+        width: 2.0 * options.margin + layer_count.saturating_sub(1) as f64 * options.layer_gap,
+        height: 2.0 * options.margin + tallest.saturating_sub(1) as f64 * options.row_gap,
+        crossings,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dfa::DFA;
+    use std::collections::{HashMap, HashSet};
+
+    fn diagram(states: usize, start: State, edges: &[(State, char, State)]) -> Diagram {
+        let mut tfn: HashMap<(State, char), HashSet<State>> = HashMap::new();
+        let mut alphabet = HashSet::new();
+        for &(from, c, to) in edges {
+            tfn.entry((from, c)).or_default().insert(to);
+            alphabet.insert(c);
+        }
+        let nfa = crate::nfa::NFA::new(states, start, HashSet::new(), alphabet, tfn).unwrap();
+        Diagram::from_nfa(&nfa)
+    }
+
+    #[test]
+    fn start_state_is_leftmost() {
+        let mut tfn = HashMap::new();
+        for s in 0..3 {
+            tfn.insert((s, 'a'), (s + 2) % 3);
+        }
+        let dfa = DFA::new(3, 1, HashSet::new(), HashSet::from(['a']), tfn).unwrap();
+        let l = layout(&Diagram::from_dfa(&dfa), &LayoutOptions::default());
+        assert_eq!(l.layers, vec![vec![1], vec![0], vec![2]]);
+        assert!(l.nodes[1].x < l.nodes[0].x && l.nodes[0].x < l.nodes[2].x);
+    }
+
+    #[test]
+    fn crossings_are_removed() {
+        // 0 fans out to 1 and 2; 1 -> 4 and 2 -> 3 cross unless layer 2 is reordered
+        let d = diagram(5, 0, &[(0, 'a', 1), (0, 'b', 2), (1, 'a', 4), (2, 'a', 3)]);
+        let l = layout(&d, &LayoutOptions::default());
+        assert_eq!(l.crossings, 0);
+        assert_eq!(l.layers[2], vec![4, 3]);
+    }
+
+    #[test]
+    fn long_and_special_edges_are_shaped() {
+        let d = diagram(
+            3,
+            0,
+            &[
+                (0, 'a', 1),
+                (1, 'a', 2),
+                (2, 'a', 0),
+                (1, 'b', 1),
+                (1, 'c', 0),
+            ],
+        );
+        let l = layout(&d, &LayoutOptions::default());
+        let kind = |from, to| {
+            let i = d
+                .edges
+                .iter()
+                .position(|e| e.from == from && e.to == to)
+                .unwrap();
+            l.edges.iter().find(|g| g.edge == i).unwrap().kind
+        };
+        assert_eq!(kind(1, 2), EdgeKind::Straight);
+        assert_eq!(kind(2, 0), EdgeKind::Routed);
+        assert_eq!(kind(1, 1), EdgeKind::Loop);
+        assert_eq!(kind(0, 1), EdgeKind::Bent);
+        assert_eq!(kind(1, 0), EdgeKind::Bent);
+
+        // the routed edge passes through the middle column and ends on node 0's boundary
+        let routed = l.edges.iter().find(|g| g.kind == EdgeKind::Routed).unwrap();
+        assert_eq!(routed.segments.len(), 2);
+        let end = l.edge_end(routed);
+        let gap = end.sub(l.nodes[0]).len();
+        assert!((gap - l.node_radius).abs() < 1e-9);
+    }
+
+    #[test]
+    fn unreachable_states_go_right() {
+        let d = diagram(3, 0, &[(0, 'a', 1)]);
+        let l = layout(&d, &LayoutOptions::default());
+        assert_eq!(l.layers, vec![vec![0], vec![1], vec![2]]);
+        assert_eq!(l.width, 2.0 * 40.0 + 2.0 * 100.0);
+        assert_eq!(l.height, 80.0);
+    }
+}
@@ -0,0 +1,301 @@
+//! Angluin's L* active learning, in the Maler–Pnueli variant: every suffix of a counterexample is
+//! added as a column, so the rows of the access strings stay pairwise distinct and the table
+//! never needs a consistency check. The learner talks to the system under learning only through
+//! a `Teacher`.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::algorithms::equivalence::counterexample;
+use crate::dfa::{DFA, SimulationResult, State};
+use crate::random::SplitMix64;
+
+pub trait Teacher {
+    /// Whether the target accepts `input`.
+    fn membership(&mut self, input: &str) -> bool;
+    /// `None` if the hypothesis is accepted, otherwise an input on which it is wrong.
+    fn equivalence(&mut self, hypothesis: &DFA) -> Option<String>;
+}
+
+/// A teacher that knows the target DFA, answering equivalence queries exactly with a shortest
+/// counterexample.
+pub struct DFATeacher<'a> {
+    target: &'a DFA,
+}
+
+impl<'a> DFATeacher<'a> {
+    pub fn new(target: &'a DFA) -> Self {
+        Self { target }
+    }
+}
+
+fn accepts(dfa: &DFA, input: &str) -> bool {
+    matches!(dfa.simulate(input), Ok(SimulationResult::Accepted))
+}
+
+impl Teacher for DFATeacher<'_> {
+    fn membership(&mut self, input: &str) -> bool {
+        accepts(self.target, input)
+    }
+
+    fn equivalence(&mut self, hypothesis: &DFA) -> Option<String> {
+        counterexample(self.target, hypothesis)
+    }
+}
+
+/// A teacher for a black box, given as a membership oracle. Equivalence queries are answered
+/// approximately by testing the hypothesis on random inputs: a uniformly random length up to
+/// `max_len`, then uniformly random symbols.
+pub struct SamplingTeacher<F> {
+    oracle: F,
+    alphabet: Vec<char>,
+    rng: SplitMix64,
+    pub samples: usize,
+    pub max_len: usize,
+}
+
+impl<F: FnMut(&str) -> bool> SamplingTeacher<F> {
+    pub fn new(oracle: F, alphabet: &[char], seed: u64) -> Self {
+        Self {
+            oracle,
+            alphabet: alphabet.to_vec(),
+            rng: SplitMix64::new(seed),
+            samples: 1000,
+            max_len: 16,
+        }
+    }
+}
+
+impl<F: FnMut(&str) -> bool> Teacher for SamplingTeacher<F> {
+    fn membership(&mut self, input: &str) -> bool {
+        (self.oracle)(input)
+    }
+
+    fn equivalence(&mut self, hypothesis: &DFA) -> Option<String> {
+        for _ in 0..self.samples {
+            let len = self.rng.below(self.max_len + 1);
+            let input: String = (0..len)
+                .filter_map(|_| self.rng.choose(&self.alphabet).copied())
+                .collect();
+            if (self.oracle)(&input) != accepts(hypothesis, &input) {
+                return Some(input);
+            }
+        }
+        None
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LearningStats {
+    /// Membership queries asked of the teacher; repeats are answered from a cache.
+    pub membership_queries: usize,
+    /// Membership lookups including those answered from the cache.
+    pub membership_lookups: usize,
+    pub equivalence_queries: usize,
+    /// The counterexamples received, in order.
+    pub counterexamples: Vec<String>,
+    pub states: usize,
+    /// Size of the final observation table.
+    pub rows: usize,
+    pub columns: usize,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum LearningError {
+    /// The hypothesis outgrew `max_states`, e.g. because the target is not regular.
+    StateLimit { states: usize },
+    /// The teacher returned a counterexample the hypothesis already classifies correctly.
+    BadCounterexample(String),
+}
+
+#[derive(Clone, Debug)]
+pub struct LStarOptions {
+    pub max_states: usize,
+}
+
+impl Default for LStarOptions {
+    fn default() -> Self {
+        Self { max_states: 1000 }
+    }
+}
+
+struct ObservationTable<'t, T> {
+    teacher: &'t mut T,
+    alphabet: Vec<char>,
+    cache: HashMap<String, bool>,
+    // access strings, one per hypothesis state, with pairwise distinct rows
+    prefixes: Vec<String>,
+    suffixes: Vec<String>,
+    stats: LearningStats,
+}
+
+impl<T: Teacher> ObservationTable<'_, T> {
+    fn member(&mut self, input: String) -> bool {
+        self.stats.membership_lookups += 1;
+        if let Some(&answer) = self.cache.get(&input) {
+            return answer;
+        }
+        self.stats.membership_queries += 1;
+        let answer = self.teacher.membership(&input);
+        self.cache.insert(input, answer);
+        answer
+    }
+
+    fn row(&mut self, prefix: &str) -> Vec<bool> {
+        let suffixes = self.suffixes.clone();
+        suffixes
+            .iter()
+            .map(|e| self.member(format!("{prefix}{e}")))
+            .collect()
+    }
+
+    /// Adds one-symbol extensions with unseen rows as access strings until the table is closed,
+    /// then reads off the hypothesis.
+    fn close(&mut self, max_states: usize) -> Result<DFA, LearningError> {
+        // rows of the access strings kept from earlier rounds come first, so an extension is
+        // only added when its row is new to all of them
+        let prefixes = self.prefixes.clone();
+        let mut rows: Vec<Vec<bool>> = prefixes.iter().map(|p| self.row(p)).collect();
+        let mut index: HashMap<Vec<bool>, State> = rows
+            .iter()
+            .enumerate()
+            .map(|(s, row)| (row.clone(), s))
+            .collect();
+        let mut tfn = HashMap::new();
+        let mut s = 0;
+        while s < self.prefixes.len() {
+            for a in self.alphabet.clone() {
+                let extension = format!("{}{a}", self.prefixes[s]);
+                let row = self.row(&extension);
+                let t = match index.get(&row) {
+                    Some(&t) => t,
+                    None => {
+                        if self.prefixes.len() == max_states {
+                            return Err(LearningError::StateLimit {
+                                states: max_states + 1,
+                            });
+                        }
+                        let t = self.prefixes.len();
+                        self.prefixes.push(extension);
+                        index.insert(row.clone(), t);
+                        rows.push(row);
+                        t
+                    }
+                };
+                tfn.insert((s, a), t);
+            }
+            s += 1;
+        }
+        // the first column is the empty suffix, so it says whether the state accepts
+        let accept: HashSet<State> = (0..rows.len()).filter(|&s| rows[s][0]).collect();
+        Ok(DFA::new(
+            rows.len(),
+            0,
+            accept,
+            self.alphabet.iter().copied().collect(),
+            tfn,
+        )
+        .expect("a closed observation table gives a total DFA"))
+    }
+}
+
+/// Learns a DFA over `alphabet` from `teacher`. With an exact teacher the result is the minimal
+/// DFA of the target language.
+pub fn lstar<T: Teacher>(
+    teacher: &mut T,
+    alphabet: &[char],
+    options: &LStarOptions,
+) -> Result<(DFA, LearningStats), LearningError> {
+    let mut alphabet = alphabet.to_vec();
+    alphabet.sort();
+    alphabet.dedup();
+    let mut table = ObservationTable {
+        teacher,
+        alphabet,
+        cache: HashMap::new(),
+        prefixes: vec![String::new()],
+        suffixes: vec![String::new()],
+        stats: LearningStats::default(),
+    };
+    loop {
+        let hypothesis = table.close(options.max_states)?;
+        table.stats.equivalence_queries += 1;
+        let Some(cex) = table.teacher.equivalence(&hypothesis) else {
+            table.stats.states = hypothesis.states.len();
+            table.stats.rows = table.prefixes.len();
+            table.stats.columns = table.suffixes.len();
+            return Ok((hypothesis, table.stats));
+        };
+        if table.member(cex.clone()) == accepts(&hypothesis, &cex) {
+            return Err(LearningError::BadCounterexample(cex));
+        }
+        let chars: Vec<char> = cex.chars().collect();
+        for i in 0..chars.len() {
+            let suffix: String = chars[i..].iter().collect();
+            if !table.suffixes.contains(&suffix) {
+                table.suffixes.push(suffix);
+            }
+        }
+        table.stats.counterexamples.push(cex);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fsim_macros::dfa;
+
+    // even number of a's and even number of b's
+    fn even_even() -> DFA {
+        dfa! {
+            states: 4,
+            start: 0,
+            accept: [0],
+            alphabet: ['a', 'b'],
+            transitions: {
+                0 => { 'a' => 1, 'b' => 2 },
+                1 => { 'a' => 0, 'b' => 3 },
+                2 => { 'a' => 3, 'b' => 0 },
+                3 => { 'a' => 2, 'b' => 1 },
+            },
+        }
+    }
+
+    #[test]
+    fn learns_minimal_dfa_from_exact_teacher() {
+        let target = even_even();
+        let mut teacher = DFATeacher::new(&target);
+        let (learned, stats) = lstar(&mut teacher, &['a', 'b'], &LStarOptions::default()).unwrap();
+        assert_eq!(counterexample(&target, &learned), None);
+        assert_eq!(learned.states.len(), 4);
+        assert_eq!(stats.states, 4);
+        assert_eq!(stats.equivalence_queries, stats.counterexamples.len() + 1);
+        assert!(stats.membership_queries <= stats.membership_lookups);
+    }
+
+    #[test]
+    fn learns_black_box_by_sampling() {
+        // number of a's divisible by 3, seen only through a closure
+        let oracle = |w: &str| w.chars().filter(|&c| c == 'a').count() % 3 == 0;
+        let mut teacher = SamplingTeacher::new(oracle, &['a', 'b'], 47);
+        let (learned, stats) = lstar(&mut teacher, &['a', 'b'], &LStarOptions::default()).unwrap();
+        assert_eq!(stats.states, 3);
+        for w in ["", "b", "aaa", "abab", "babaa", "aaaaaab"] {
+            assert_eq!(accepts(&learned, w), oracle(w), "{w:?}");
+        }
+    }
+
+    #[test]
+    fn non_regular_target_hits_state_limit() {
+        let oracle = |w: &str| {
+            let a = w.chars().filter(|&c| c == 'a').count();
+            a == w.len() - a
+        };
+        let mut teacher = SamplingTeacher::new(oracle, &['a', 'b'], 1);
+        let result = lstar(&mut teacher, &['a', 'b'], &LStarOptions { max_states: 5 });
+        assert!(matches!(
+            result.map(|_| ()),
+            Err(LearningError::StateLimit { states: 6 })
+        ));
+    }
+}
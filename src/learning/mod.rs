@@ -0,0 +1,2 @@
+pub mod lstar;
+pub mod rpni;
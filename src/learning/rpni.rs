@@ -0,0 +1,370 @@
+//! Passive learning from labelled samples. Both learners start from the prefix-tree acceptor
+//! (PTA): the root plus one node per prefix of a sample, numbered in shortlex order, labelled
+//! accepting or rejecting where a sample ends there. `rpni` merges PTA nodes greedily;
+//! `minimal_consistent_dfa` searches exhaustively for the smallest DFA that agrees with the
+//! samples. Either way the result is checked against every sample before it is returned.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+
+use crate::dfa::{DFA, SimulationResult, State};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InferenceError {
+    /// The string is both a positive and a negative sample.
+    Contradictory(String),
+    /// No DFA with at most this many states is consistent with the samples.
+    NoDFAWithin { max_states: usize },
+    /// The learned DFA misclassifies this sample. Never expected; reported instead of returning
+    /// a wrong machine.
+    Inconsistent(String),
+}
+
+/// A partial DFA with optional labels; the PTA and every RPNI candidate.
+#[derive(Clone)]
+struct Partial {
+    next: Vec<HashMap<char, usize>>,
+    label: Vec<Option<bool>>,
+}
+
+struct PrefixTree {
+    tree: Partial,
+    // (parent, symbol) for every node but the root
+    parent: Vec<Option<(usize, char)>>,
+    alphabet: Vec<char>,
+}
+
+impl PrefixTree {
+    fn new(positive: &[&str], negative: &[&str]) -> Result<Self, InferenceError> {
+        let positive_set: HashSet<&str> = positive.iter().copied().collect();
+        if let Some(w) = negative.iter().find(|w| positive_set.contains(*w)) {
+            return Err(InferenceError::Contradictory(w.to_string()));
+        }
+        // every prefix, in shortlex order; the root is there even without samples
+        let mut prefixes: BTreeSet<(usize, Vec<char>)> = BTreeSet::from([(0, Vec::new())]);
+        for w in positive.iter().chain(negative) {
+            let chars: Vec<char> = w.chars().collect();
+            for i in 0..=chars.len() {
+                prefixes.insert((i, chars[..i].to_vec()));
+            }
+        }
+        let prefixes: Vec<Vec<char>> = prefixes.into_iter().map(|(_, p)| p).collect();
+        let index: HashMap<&[char], usize> = prefixes
+            .iter()
+            .enumerate()
+            .map(|(i, p)| (p.as_slice(), i))
+            .collect();
+
+        let n = prefixes.len();
+        let mut tree = Partial {
+            next: vec![HashMap::new(); n],
+            label: vec![None; n],
+        };
+        let mut parent = vec![None; n];
+        for (i, p) in prefixes.iter().enumerate().skip(1) {
+            let (&a, init) = p.split_last().unwrap();
+            let up = index[init];
+            tree.next[up].insert(a, i);
+            parent[i] = Some((up, a));
+        }
+        for (samples, label) in [(positive, true), (negative, false)] {
+            for w in samples {
+                let chars: Vec<char> = w.chars().collect();
+                tree.label[index[chars.as_slice()]] = Some(label);
+            }
+        }
+        let alphabet: BTreeSet<char> = prefixes.iter().flatten().copied().collect();
+        Ok(Self {
+            tree,
+            parent,
+            alphabet: alphabet.into_iter().collect(),
+        })
+    }
+}
+
+impl Partial {
+    /// Folds the subtree rooted at `q` into `p`, merging labels. `false` on a label conflict.
+    fn fold(&mut self, p: usize, q: usize) -> bool {
+        match (self.label[p], self.label[q]) {
+            (Some(a), Some(b)) if a != b => return false,
+            (None, b) => self.label[p] = b,
+            _ => {}
+        }
+        let mut children: Vec<(char, usize)> = self.next[q].iter().map(|(&a, &t)| (a, t)).collect();
+        children.sort();
+        for (a, q_child) in children {
+            match self.next[p].get(&a) {
+                Some(&p_child) => {
+                    if !self.fold(p_child, q_child) {
+                        return false;
+                    }
+                }
+                None => {
+                    self.next[p].insert(a, q_child);
+                }
+            }
+        }
+        true
+    }
+}
+
+/// Turns the states `keep` of a partial DFA into a total DFA over `alphabet`, adding a rejecting
+/// sink for missing transitions if there are any. Unlabelled states reject.
+fn complete(partial: &Partial, keep: &[usize], alphabet: &[char]) -> DFA {
+    let id: HashMap<usize, State> = keep.iter().enumerate().map(|(i, &s)| (s, i)).collect();
+    let sink = keep.len();
+    let mut tfn = HashMap::new();
+    for (i, &s) in keep.iter().enumerate() {
+        for &a in alphabet {
+            let t = partial.next[s].get(&a).map_or(sink, |t| id[t]);
+            tfn.insert((i, a), t);
+        }
+    }
+    let states = if tfn.values().any(|&t| t == sink) {
+        for &a in alphabet {
+            tfn.insert((sink, a), sink);
+        }
+        sink + 1
+    } else {
+        sink
+    };
+    let accept = keep
+        .iter()
+        .enumerate()
+        .filter(|&(_, &s)| partial.label[s] == Some(true))
+        .map(|(i, _)| i)
+        .collect();
+    DFA::new(states, 0, accept, alphabet.iter().copied().collect(), tfn)
+        .expect("completed DFA is total")
+}
+
+fn verify(dfa: DFA, positive: &[&str], negative: &[&str]) -> Result<DFA, InferenceError> {
+    let samples = positive
+        .iter()
+        .map(|w| (w, true))
+        .chain(negative.iter().map(|w| (w, false)));
+    for (w, expected) in samples {
+        let accepted = matches!(dfa.simulate(w), Ok(SimulationResult::Accepted));
+        if accepted != expected {
+            return Err(InferenceError::Inconsistent(w.to_string()));
+        }
+    }
+    Ok(dfa)
+}
+
+/// Regular Positive and Negative Inference. Blue nodes (children of red ones) are taken in
+/// shortlex order and merged into the first red node that gives no label conflict after
+/// folding; a blue node that merges with none is promoted to red. With a characteristic sample
+/// this identifies the target language in the limit.
+pub fn rpni(positive: &[&str], negative: &[&str]) -> Result<DFA, InferenceError> {
+    let pta = PrefixTree::new(positive, negative)?;
+    let mut current = pta.tree.clone();
+    let mut red: Vec<usize> = vec![0];
+    loop {
+        let red_set: HashSet<usize> = red.iter().copied().collect();
+        // the shortlex-least child of a red node that is not red itself, with its one incoming
+        // edge; folding moves edges but never duplicates them
+        let blue = red
+            .iter()
+            .flat_map(|&r| current.next[r].iter().map(move |(&a, &t)| (t, r, a)))
+            .filter(|(t, _, _)| !red_set.contains(t))
+            .min();
+        let Some((q, up, a)) = blue else { break };
+        let merged = red.iter().find_map(|&p| {
+            let mut candidate = current.clone();
+            candidate.next[up].insert(a, p);
+            candidate.fold(p, q).then_some(candidate)
+        });
+        match merged {
+            Some(candidate) => current = candidate,
+            None => red.push(q),
+        }
+    }
+    red.sort();
+    verify(complete(&current, &red, &pta.alphabet), positive, negative)
+}
+
+struct Search<'a> {
+    pta: &'a PrefixTree,
+    size: usize,
+    assign: Vec<usize>,
+    delta: HashMap<(usize, char), usize>,
+    label: Vec<Option<bool>>,
+    used: usize,
+}
+
+impl Search<'_> {
+    /// Assigns PTA nodes `node..` to DFA states by backtracking. Nodes go in shortlex order, and
+    /// a node may only open the next unused state, which rules out renamings of one solution.
+    fn solve(&mut self, node: usize) -> bool {
+        if node == self.assign.len() {
+            return true;
+        }
+        let (up, a) = self.pta.parent[node].expect("the root is assigned first");
+        let from = self.assign[up];
+        let candidates: Vec<usize> = match self.delta.get(&(from, a)) {
+            Some(&forced) => vec![forced],
+            None => (0..(self.used + 1).min(self.size)).collect(),
+        };
+        let forced = self.delta.contains_key(&(from, a));
+        for s in candidates {
+            let node_label = self.pta.tree.label[node];
+            let old_label = self.label[s];
+            if let (Some(x), Some(y)) = (old_label, node_label)
+                && x != y
+            {
+                continue;
+            }
+            let old_used = self.used;
+            self.assign[node] = s;
+            self.label[s] = old_label.or(node_label);
+            self.used = self.used.max(s + 1);
+            if !forced {
+                self.delta.insert((from, a), s);
+            }
+            if self.solve(node + 1) {
+                return true;
+            }
+            if !forced {
+                self.delta.remove(&(from, a));
+            }
+            self.used = old_used;
+            self.label[s] = old_label;
+        }
+        false
+    }
+}
+
+/// The smallest DFA (counting a sink if one is needed) consistent with the samples, by
+/// exhaustive search over assignments of PTA nodes to `1, 2, …, max_states` states. Exponential
+/// in the worst case, so keep `max_states` small.
+pub fn minimal_consistent_dfa(
+    positive: &[&str],
+    negative: &[&str],
+    max_states: usize,
+) -> Result<DFA, InferenceError> {
+    let pta = PrefixTree::new(positive, negative)?;
+    for size in 1..=max_states {
+        let nodes = pta.tree.label.len();
+        let mut search = Search {
+            pta: &pta,
+            size,
+            assign: vec![0; nodes],
+            delta: HashMap::new(),
+            label: vec![None; size],
+            used: 1,
+        };
+        search.label[0] = pta.tree.label[0];
+        if !search.solve(1) {
+            continue;
+        }
+        // transitions no sample constrains are sent to state 0, which keeps the size
+        let mut tfn = HashMap::new();
+        for s in 0..size {
+            for &a in &pta.alphabet {
+                tfn.insert((s, a), search.delta.get(&(s, a)).copied().unwrap_or(0));
+            }
+        }
+        let accept = (0..size)
+            .filter(|&s| search.label[s] == Some(true))
+            .collect();
+        let dfa = DFA::new(size, 0, accept, pta.alphabet.iter().copied().collect(), tfn)
+            .expect("every transition is filled in");
+        return verify(dfa, positive, negative);
+    }
+    Err(InferenceError::NoDFAWithin { max_states })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // (ab)*
+    const POSITIVE: &[&str] = &["", "ab", "abab"];
+    const NEGATIVE: &[&str] = &["a", "b", "aa", "ba", "bb", "aba", "abb"];
+
+    fn accepts(dfa: &DFA, w: &str) -> bool {
+        matches!(dfa.simulate(w), Ok(SimulationResult::Accepted))
+    }
+
+    #[test]
+    fn rpni_generalizes_from_characteristic_sample() {
+        let dfa = rpni(POSITIVE, NEGATIVE).unwrap();
+        assert!(accepts(&dfa, "ababab"));
+        assert!(!accepts(&dfa, "abba"));
+        assert!(!accepts(&dfa, "ababa"));
+        assert_eq!(dfa.states.len(), 3);
+    }
+
+    #[test]
+    fn exact_search_finds_smallest_dfa() {
+        let dfa = minimal_consistent_dfa(POSITIVE, NEGATIVE, 5).unwrap();
+        assert_eq!(dfa.states.len(), 3);
+        assert!(accepts(&dfa, "ababab"));
+        assert_eq!(
+            minimal_consistent_dfa(POSITIVE, NEGATIVE, 2).map(|_| ()),
+            Err(InferenceError::NoDFAWithin { max_states: 2 })
+        );
+    }
+
+    #[test]
+    fn contradictory_samples_are_rejected() {
+        assert_eq!(
+            rpni(&["ab", "a"], &["a"]).map(|_| ()),
+            Err(InferenceError::Contradictory("a".to_string()))
+        );
+    }
+
+    #[test]
+    fn only_positive_samples() {
+        // with nothing to keep apart everything merges into one accepting state
+        let dfa = rpni(&["a", "bb"], &[]).unwrap();
+        assert_eq!(dfa.states.len(), 1);
+        let dfa = minimal_consistent_dfa(&["a", "bb"], &[], 3).unwrap();
+        assert_eq!(dfa.states.len(), 1);
+    }
+
+    #[test]
+    fn no_samples_give_the_empty_language() {
+        let dfa = rpni(&[], &[]).unwrap();
+        assert_eq!(dfa.states.len(), 1);
+        assert!(!accepts(&dfa, ""));
+        let dfa = minimal_consistent_dfa(&[], &[], 1).unwrap();
+        assert_eq!(dfa.states.len(), 1);
+        assert!(dfa.accept.is_empty());
+    }
+
+    #[test]
+    fn complete_sample_recovers_target() {
+        // every string up to length 4, labelled by "number of a's is even and ends in b or is
+        // empty"; such a sample is characteristic for this small target
+        let target = |w: &str| {
+            w.chars().filter(|&c| c == 'a').count() % 2 == 0 && (w.is_empty() || w.ends_with('b'))
+        };
+        let mut words = vec![String::new()];
+        for len in 1..=4 {
+            let prev: Vec<String> = words
+                .iter()
+                .filter(|w| w.len() == len - 1)
+                .cloned()
+                .collect();
+            for w in prev {
+                words.push(format!("{w}a"));
+                words.push(format!("{w}b"));
+            }
+        }
+        let positive: Vec<&str> = words
+            .iter()
+            .map(|w| w.as_str())
+            .filter(|w| target(w))
+            .collect();
+        let negative: Vec<&str> = words
+            .iter()
+            .map(|w| w.as_str())
+            .filter(|w| !target(w))
+            .collect();
+        let dfa = rpni(&positive, &negative).unwrap();
+        for w in ["aab", "babab", "aaaab", "abababab", "aabba"] {
+            assert_eq!(accepts(&dfa, w), target(w), "{w:?}");
+        }
+    }
+}
@@ -1,3 +1,21 @@
+// the dfa! and nfa! expansions name `::fsim`, which lets them run in this crate's own tests
+extern crate self as fsim;
+
 pub mod algorithms;
 pub mod dfa;
+#[cfg(test)]
+mod fixtures;
+pub mod formats;
+pub mod fst;
+pub mod grading;
+pub mod layout;
+pub mod learning;
+pub mod mealy;
+pub mod moore;
 pub mod nfa;
+pub mod random;
+pub mod regex;
+pub mod simulation;
+
+#[cfg(feature = "macros")]
+pub use fsim_macros::{dfa, nfa};
@@ -0,0 +1,212 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+
+use itertools::Itertools;
+
+use crate::dfa::State;
+use crate::moore::MooreMachine;
+
+#[derive(Debug)]
+pub enum MealyTypeError {
+    InvalidStartState,
+    InvalidTransitionFunction,
+    NonTotalTransitionFunction,
+    InvalidOutputSymbol,
+}
+
+#[derive(Debug)]
+pub enum InputError {
+    InvalidSymbol,
+}
+
+/// Transition function of a Mealy machine: the next state and the symbol output on the way.
+pub type MealyTransitionFn = HashMap<(State, char), (State, char)>;
+
+/// A deterministic transducer whose outputs are attached to transitions, so an input of length
+/// `n` produces an output of length `n`.
+#[derive(Clone, Debug)]
+pub struct MealyMachine {
+    pub(crate) states: HashSet<State>,
+    pub(crate) start: State,
+    pub(crate) input_alphabet: HashSet<char>,
+    pub(crate) output_alphabet: HashSet<char>,
+    pub(crate) tfn: MealyTransitionFn,
+}
+
+impl MealyMachine {
+    fn validate_mealy(
+        states: usize,
+        start: usize,
+        input_alphabet: &HashSet<char>,
+        output_alphabet: &HashSet<char>,
+        tfn: &MealyTransitionFn,
+    ) -> Result<(), MealyTypeError> {
+        if start >= states {
+            return Err(MealyTypeError::InvalidStartState);
+        }
+        let domain_of_tfn = (0..states).cartesian_product(input_alphabet.iter());
+        if !(domain_of_tfn
+            .into_iter()
+            .all(|(s, &a)| tfn.contains_key(&(s, a))))
+        {
+            return Err(MealyTypeError::NonTotalTransitionFunction);
+        }
+        if tfn.len() != states * input_alphabet.len() || !tfn.values().all(|&(t, _)| t < states) {
+            return Err(MealyTypeError::InvalidTransitionFunction);
+        }
+        if !tfn.values().all(|(_, o)| output_alphabet.contains(o)) {
+            return Err(MealyTypeError::InvalidOutputSymbol);
+        }
+
+        Ok(())
+    }
+
+    pub fn new(
+        states: usize,
+        start: usize,
+        input_alphabet: HashSet<char>,
+        output_alphabet: HashSet<char>,
+        tfn: MealyTransitionFn,
+    ) -> Result<Self, MealyTypeError> {
+        Self::validate_mealy(states, start, &input_alphabet, &output_alphabet, &tfn)?;
+
+        Ok(Self {
+            states: HashSet::from_iter(0..states),
+            start,
+            input_alphabet,
+            output_alphabet,
+            tfn,
+        })
+    }
+
+    /// The output produced while reading `input`, one symbol per input symbol.
+    pub fn simulate(&self, input: &str) -> Result<String, InputError> {
+        let mut current_state = self.start;
+        let mut output = String::new();
+        for a in input.chars() {
+            let &(next, o) = self
+                .tfn
+                .get(&(current_state, a))
+                .ok_or(InputError::InvalidSymbol)?;
+            output.push(o);
+            current_state = next;
+        }
+        Ok(output)
+    }
+
+    /// An equivalent Moore machine. Its states are the reachable pairs of a Mealy state and the
+    /// output that led into it, numbered breadth first; the start state outputs
+    /// `initial_output`, so every output is `initial_output` followed by this machine's output.
+    pub fn to_moore(&self, initial_output: char) -> MooreMachine {
+        let mut alphabet: Vec<char> = self.input_alphabet.iter().copied().collect();
+        alphabet.sort();
+        let start = (self.start, initial_output);
+        let mut index: HashMap<(State, char), State> = HashMap::from([(start, 0)]);
+        let mut pairs = Vec::with_capacity(self.states.len());
+        pairs.push(start);
+        let mut work_queue = VecDeque::from([start]);
+        let mut tfn = HashMap::new();
+        while let Some((q, o)) = work_queue.pop_front() {
+            let from = index[&(q, o)];
+            for &a in &alphabet {
+                let next = self.tfn[&(q, a)];
+                let to = *index.entry(next).or_insert_with(|| {
+                    pairs.push(next);
+                    work_queue.push_back(next);
+                    pairs.len() - 1
+                });
+                tfn.insert((from, a), to);
+            }
+        }
+        let output = pairs
+            .iter()
+            .enumerate()
+            .map(|(i, &(_, o))| (i, o))
+            .collect();
+        let mut output_alphabet = self.output_alphabet.clone();
+        output_alphabet.insert(initial_output);
+        MooreMachine::new(
+            pairs.len(),
+            0,
+            self.input_alphabet.clone(),
+            output_alphabet,
+            tfn,
+            output,
+        )
+        .expect("converting a valid Mealy machine gives a valid Moore machine")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // outputs '1' exactly when the current input repeats the previous one
+    fn repeat_detector() -> MealyMachine {
+        let mut tfn = HashMap::new();
+        tfn.insert((0, 'a'), (1, '0'));
+        tfn.insert((0, 'b'), (2, '0'));
+        tfn.insert((1, 'a'), (1, '1'));
+        tfn.insert((1, 'b'), (2, '0'));
+        tfn.insert((2, 'a'), (1, '0'));
+        tfn.insert((2, 'b'), (2, '1'));
+        MealyMachine::new(
+            3,
+            0,
+            HashSet::from(['a', 'b']),
+            HashSet::from(['0', '1']),
+            tfn,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn simulate_outputs_per_symbol() {
+        let m = repeat_detector();
+        assert_eq!(m.simulate("aabbba").unwrap(), "010110");
+        assert_eq!(m.simulate("").unwrap(), "");
+        assert!(matches!(m.simulate("ac"), Err(InputError::InvalidSymbol)));
+    }
+
+    #[test]
+    fn validation_errors() {
+        let mut tfn = repeat_detector().tfn;
+        tfn.insert((2, 'b'), (2, 'x'));
+        let bad = MealyMachine::new(
+            3,
+            0,
+            HashSet::from(['a', 'b']),
+            HashSet::from(['0', '1']),
+            tfn,
+        );
+        assert!(matches!(bad, Err(MealyTypeError::InvalidOutputSymbol)));
+
+        let mut tfn = repeat_detector().tfn;
+        tfn.remove(&(2, 'b'));
+        let bad = MealyMachine::new(
+            3,
+            0,
+            HashSet::from(['a', 'b']),
+            HashSet::from(['0', '1']),
+            tfn,
+        );
+        assert!(matches!(
+            bad,
+            Err(MealyTypeError::NonTotalTransitionFunction)
+        ));
+
+        let bad = MealyMachine::new(0, 0, HashSet::new(), HashSet::new(), HashMap::new());
+        assert!(matches!(bad, Err(MealyTypeError::InvalidStartState)));
+    }
+
+    #[test]
+    fn to_moore_prefixes_initial_output() {
+        let mealy = repeat_detector();
+        let moore = mealy.to_moore('-');
+        for input in ["", "a", "abba", "bbbab"] {
+            let expected = format!("-{}", mealy.simulate(input).unwrap());
+            assert_eq!(moore.simulate(input).unwrap(), expected);
+        }
+        // (0, -), (1, 0), (2, 0), (1, 1), (2, 1)
+        assert_eq!(moore.states.len(), 5);
+    }
+}
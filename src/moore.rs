@@ -0,0 +1,194 @@
+use std::collections::{HashMap, HashSet};
+
+use itertools::Itertools;
+
+use crate::dfa::State;
+use crate::mealy::MealyMachine;
+
+#[derive(Debug)]
+pub enum MooreTypeError {
+    InvalidStartState,
+    InvalidTransitionFunction,
+    NonTotalTransitionFunction,
+    MissingOutput,
+    InvalidOutputSymbol,
+}
+
+#[derive(Debug)]
+pub enum InputError {
+    InvalidSymbol,
+}
+
+/// A deterministic transducer whose outputs are attached to states. The output for an input of
+/// length `n` has length `n + 1`: the start state's output, then one per state entered.
+#[derive(Clone, Debug)]
+pub struct MooreMachine {
+    pub(crate) states: HashSet<State>,
+    pub(crate) start: State,
+    pub(crate) input_alphabet: HashSet<char>,
+    pub(crate) output_alphabet: HashSet<char>,
+    pub(crate) tfn: HashMap<(State, char), State>,
+    pub(crate) output: HashMap<State, char>,
+}
+
+impl MooreMachine {
+    fn validate_moore(
+        states: usize,
+        start: usize,
+        input_alphabet: &HashSet<char>,
+        output_alphabet: &HashSet<char>,
+        tfn: &HashMap<(State, char), State>,
+        output: &HashMap<State, char>,
+    ) -> Result<(), MooreTypeError> {
+        if start >= states {
+            return Err(MooreTypeError::InvalidStartState);
+        }
+        let domain_of_tfn = (0..states).cartesian_product(input_alphabet.iter());
+        if !(domain_of_tfn
+            .into_iter()
+            .all(|(s, &a)| tfn.contains_key(&(s, a))))
+        {
+            return Err(MooreTypeError::NonTotalTransitionFunction);
+        }
+        if tfn.len() != states * input_alphabet.len() || !tfn.values().all(|&v| v < states) {
+            return Err(MooreTypeError::InvalidTransitionFunction);
+        }
+        if output.len() != states || !(0..states).all(|s| output.contains_key(&s)) {
+            return Err(MooreTypeError::MissingOutput);
+        }
+        if !output.values().all(|o| output_alphabet.contains(o)) {
+            return Err(MooreTypeError::InvalidOutputSymbol);
+        }
+
+        Ok(())
+    }
+
+    pub fn new(
+        states: usize,
+        start: usize,
+        input_alphabet: HashSet<char>,
+        output_alphabet: HashSet<char>,
+        tfn: HashMap<(State, char), State>,
+        output: HashMap<State, char>,
+    ) -> Result<Self, MooreTypeError> {
+        Self::validate_moore(
+            states,
+            start,
+            &input_alphabet,
+            &output_alphabet,
+            &tfn,
+            &output,
+        )?;
+
+        Ok(Self {
+            states: HashSet::from_iter(0..states),
+            start,
+            input_alphabet,
+            output_alphabet,
+            tfn,
+            output,
+        })
+    }
+
+    /// The outputs of the states visited while reading `input`, starting with the start state.
+    pub fn simulate(&self, input: &str) -> Result<String, InputError> {
+        let mut current_state = self.start;
+        let mut output = String::from(self.output[&current_state]);
+        for a in input.chars() {
+            current_state = *self
+                .tfn
+                .get(&(current_state, a))
+                .ok_or(InputError::InvalidSymbol)?;
+            output.push(self.output[&current_state]);
+        }
+        Ok(output)
+    }
+
+    /// An equivalent Mealy machine on the same states, each transition outputting what its target
+    /// state outputs. Its output is this machine's without the first symbol.
+    pub fn to_mealy(&self) -> MealyMachine {
+        let tfn = self
+            .tfn
+            .iter()
+            .map(|(&key, &t)| (key, (t, self.output[&t])))
+            .collect();
+        MealyMachine::new(
+            self.states.len(),
+            self.start,
+            self.input_alphabet.clone(),
+            self.output_alphabet.clone(),
+            tfn,
+        )
+        .expect("converting a valid Moore machine gives a valid Mealy machine")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // outputs the number of a's read so far, mod 3
+    fn count_a_mod_3() -> MooreMachine {
+        let mut tfn = HashMap::new();
+        for s in 0..3 {
+            tfn.insert((s, 'a'), (s + 1) % 3);
+            tfn.insert((s, 'b'), s);
+        }
+        let output = HashMap::from([(0, '0'), (1, '1'), (2, '2')]);
+        MooreMachine::new(
+            3,
+            0,
+            HashSet::from(['a', 'b']),
+            HashSet::from(['0', '1', '2']),
+            tfn,
+            output,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn simulate_includes_start_output() {
+        let m = count_a_mod_3();
+        assert_eq!(m.simulate("").unwrap(), "0");
+        assert_eq!(m.simulate("abaa").unwrap(), "01120");
+        assert!(matches!(m.simulate("x"), Err(InputError::InvalidSymbol)));
+    }
+
+    #[test]
+    fn validation_errors() {
+        let m = count_a_mod_3();
+        let mut output = m.output.clone();
+        output.remove(&2);
+        let bad = MooreMachine::new(
+            3,
+            0,
+            m.input_alphabet.clone(),
+            m.output_alphabet.clone(),
+            m.tfn.clone(),
+            output,
+        );
+        assert!(matches!(bad, Err(MooreTypeError::MissingOutput)));
+
+        let bad = MooreMachine::new(
+            3,
+            0,
+            m.input_alphabet.clone(),
+            HashSet::from(['0', '1']),
+            m.tfn.clone(),
+            m.output.clone(),
+        );
+        assert!(matches!(bad, Err(MooreTypeError::InvalidOutputSymbol)));
+    }
+
+    #[test]
+    fn to_mealy_drops_initial_output() {
+        let moore = count_a_mod_3();
+        let mealy = moore.to_mealy();
+        for input in ["", "a", "abba", "aaaab"] {
+            assert_eq!(
+                mealy.simulate(input).unwrap(),
+                moore.simulate(input).unwrap()[1..]
+            );
+        }
+    }
+}
@@ -1,4 +1,9 @@
-use std::collections::{HashMap, HashSet, VecDeque};
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    fmt,
+};
+
+use crate::formats::table::{TableFormat, nfa_table};
 
 #[derive(Debug)]
 pub enum NFATypeError {
@@ -18,17 +23,18 @@ pub enum InputError {
     InvalidSymbol,
 }
 
-const EPSILON: char = '~';
+pub const EPSILON: char = '~';
 
-type State = usize;
-type TransitionFn = HashMap<(State, char), HashSet<State>>;
+pub type State = usize;
+pub type TransitionFn = HashMap<(State, char), HashSet<State>>;
 
+#[derive(Clone)]
 pub struct NFA {
-    states: HashSet<State>,
-    start: State,
-    accept: HashSet<State>,
-    alphabet: HashSet<char>,
-    tfn: TransitionFn,
+    pub(crate) states: HashSet<State>,
+    pub(crate) start: State,
+    pub(crate) accept: HashSet<State>,
+    pub(crate) alphabet: HashSet<char>,
+    pub(crate) tfn: TransitionFn,
 }
 
 impl NFA {
@@ -39,7 +45,7 @@ impl NFA {
         alphabet: &HashSet<char>,
         tfn: &HashMap<(usize, char), HashSet<usize>>,
     ) -> Result<(), NFATypeError> {
-        if !(start < states) {
+        if start >= states {
             return Err(NFATypeError::InvalidStartState);
         }
         if !(accept.iter().all(|&s| s < states)) {
@@ -84,14 +90,14 @@ impl NFA {
         Ok(nfa)
     }
 
-    fn validate_input(&self, input: &String) -> Result<(), InputError> {
+    fn validate_input(&self, input: &str) -> Result<(), InputError> {
         if input.chars().all(|c| self.alphabet.contains(&c)) {
             return Ok(());
         }
         Err(InputError::InvalidSymbol)
     }
 
-    fn epsilon_closure(&self, states: &HashSet<State>) -> HashSet<State> {
+    pub(crate) fn epsilon_closure(&self, states: &HashSet<State>) -> HashSet<State> {
         let mut closure = states.clone();
         let mut worklist: VecDeque<State> = states.iter().cloned().collect();
 
@@ -108,7 +114,7 @@ impl NFA {
         closure
     }
 
-    pub fn simulate(&self, input: &String) -> Result<SimulationResult, InputError> {
+    pub fn simulate(&self, input: &str) -> Result<SimulationResult, InputError> {
         self.validate_input(input)?;
         // TODO: understand better what is going on here. is self.start moved? cloned? what happens in the loop?
         let mut current_states = HashSet::from([self.start]);
@@ -116,10 +122,8 @@ impl NFA {
             let epsilon_closure = self.epsilon_closure(&current_states);
             let mut new_states: HashSet<State> = HashSet::new();
             for current_state in epsilon_closure {
-                let new_states_for_current_state = self.tfn.get(&(current_state, s));
-                match new_states_for_current_state {
-                    Some(s) => new_states.extend(s),
-                    None => (),
+                if let Some(s) = self.tfn.get(&(current_state, s)) {
+                    new_states.extend(s);
                 }
             }
             current_states = new_states;
@@ -133,7 +137,17 @@ impl NFA {
     }
 }
 
+impl fmt::Display for NFA {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", nfa_table(self, TableFormat::Plain))
+    }
+}
+
 #[cfg(test)]
+#[allow(
+    clippy::assertions_on_constants,
+    clippy::unnecessary_owned_empty_strings
+)]
 mod tests {
     use super::*;
 
@@ -0,0 +1,347 @@
+//! Differential testing: run random machines through every simulator and transformation fsim
+//! has and check they all agree on every input up to a length bound.
+//!
+//! A DFA is checked against its minimization and against the determinization of itself viewed
+//! as an NFA. An NFA is checked against `BitsetNFA`, `LazyDFA` (with a tiny cache so flushes
+//! happen), its determinization and the minimization of that. A regex is checked against its
+//! Thompson NFA, with the syntax-tree matcher as the oracle. Minimized machines must also have
+//! exactly as many states as an independent Moore partition refinement predicts.
+
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use crate::algorithms::minimize_dfa::minimize_dfa;
+use crate::algorithms::subset_construction::determinize;
+use crate::dfa::{self, DFA, State};
+use crate::nfa::{self, EPSILON, NFA};
+use crate::random::SplitMix64;
+use crate::random::generate::{
+    DFAOptions, NFAOptions, RegexOptions, random_dfa, random_nfa, random_regex,
+};
+use crate::regex::Regex;
+use crate::simulation::bitset::BitsetNFA;
+use crate::simulation::lazy_dfa::LazyDFA;
+
+/// A disagreement found by one of the checks.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Mismatch {
+    pub check: &'static str,
+    /// The input the two sides disagreed on, if the check is about a single input.
+    pub input: Option<String>,
+    pub detail: String,
+}
+
+/// A mismatch together with what is needed to reproduce it.
+#[derive(Clone, Debug)]
+pub struct Discrepancy {
+    pub seed: u64,
+    pub case: usize,
+    /// The offending machine or regex, printed.
+    pub subject: String,
+    pub mismatch: Mismatch,
+}
+
+impl fmt::Display for Discrepancy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "seed {} case {}: {}",
+            self.seed, self.case, self.mismatch.check
+        )?;
+        if let Some(input) = &self.mismatch.input {
+            write!(f, " on {input:?}")?;
+        }
+        write!(f, ": {}\n{}", self.mismatch.detail, self.subject)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Differential {
+    /// Number of machines of each kind to generate.
+    pub cases: usize,
+    /// Every string over the alphabet up to this length is tried.
+    pub max_len: usize,
+    pub dfa: DFAOptions,
+    pub nfa: NFAOptions,
+    pub regex: RegexOptions,
+}
+
+impl Default for Differential {
+    fn default() -> Self {
+        Self {
+            cases: 100,
+            max_len: 6,
+            dfa: DFAOptions::default(),
+            nfa: NFAOptions::default(),
+            regex: RegexOptions::default(),
+        }
+    }
+}
+
+impl Differential {
+    /// Runs all cases from `seed`, stopping at the first discrepancy.
+    pub fn run(&self, seed: u64) -> Result<(), Discrepancy> {
+        let mut rng = SplitMix64::new(seed);
+        for case in 0..self.cases {
+            let found = |subject: String, mismatch| Discrepancy {
+                seed,
+                case,
+                subject,
+                mismatch,
+            };
+            let dfa = random_dfa(&mut rng, &self.dfa);
+            check_dfa(&dfa, self.max_len).map_err(|m| found(dfa.to_string(), m))?;
+            let nfa = random_nfa(&mut rng, &self.nfa);
+            check_nfa(&nfa, self.max_len).map_err(|m| found(nfa.to_string(), m))?;
+            let regex = random_regex(&mut rng, &self.regex);
+            check_regex(&regex, &self.regex.alphabet, self.max_len)
+                .map_err(|m| found(regex.to_string(), m))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn check_dfa(dfa: &DFA, max_len: usize) -> Result<(), Mismatch> {
+    let alphabet = sorted(dfa.alphabet.iter().copied());
+    let minimized = minimize_dfa(dfa);
+    let determinized = determinize(&dfa_as_nfa(dfa));
+    for input in all_strings(&alphabet, max_len) {
+        let expected = dfa_accepts(dfa, &input);
+        compare(
+            "minimize_dfa",
+            &input,
+            expected,
+            dfa_accepts(&minimized, &input),
+        )?;
+        compare(
+            "determinize",
+            &input,
+            expected,
+            dfa_accepts(&determinized, &input),
+        )?;
+    }
+    check_minimal(dfa, &minimized)
+}
+
+pub fn check_nfa(nfa: &NFA, max_len: usize) -> Result<(), Mismatch> {
+    check_nfa_against(nfa, max_len, |input| nfa_accepts(nfa, input))
+}
+
+/// Checks the regex's Thompson NFA against `Regex::is_match` on all strings over `alphabet`,
+/// which may include symbols the regex does not use. Panics if the regex has no Thompson NFA,
+/// i.e. uses `EPSILON` as a symbol.
+pub fn check_regex(regex: &Regex, alphabet: &[char], max_len: usize) -> Result<(), Mismatch> {
+    let nfa = regex
+        .to_nfa()
+        .expect("check_regex needs a regex without EPSILON symbols");
+    let mut alphabet = alphabet.to_vec();
+    alphabet.extend(regex.symbols());
+    let alphabet = sorted(alphabet);
+    for input in all_strings(&alphabet, max_len) {
+        compare(
+            "Thompson construction",
+            &input,
+            regex.is_match(&input),
+            nfa_accepts(&nfa, &input),
+        )?;
+    }
+    check_nfa_against(&nfa, max_len, |input| regex.is_match(input))
+}
+
+fn check_nfa_against(
+    nfa: &NFA,
+    max_len: usize,
+    oracle: impl Fn(&str) -> bool,
+) -> Result<(), Mismatch> {
+    let alphabet = sorted(nfa.alphabet.iter().copied().filter(|&c| c != EPSILON));
+    let bitset = BitsetNFA::new(nfa);
+    let mut lazy = LazyDFA::with_cache_capacity(nfa, 2);
+    let determinized = determinize(nfa);
+    let minimized = minimize_dfa(&determinized);
+    for input in all_strings(&alphabet, max_len) {
+        let expected = oracle(&input);
+        let results = [
+            ("NFA::simulate", nfa_accepts(nfa, &input)),
+            ("BitsetNFA", accepted(bitset.simulate(&input))),
+            ("LazyDFA", accepted(lazy.simulate(&input))),
+            ("determinize", dfa_accepts(&determinized, &input)),
+            ("minimize_dfa", dfa_accepts(&minimized, &input)),
+        ];
+        for (check, actual) in results {
+            compare(check, &input, expected, actual)?;
+        }
+    }
+    check_minimal(&determinized, &minimized)
+}
+
+fn compare(check: &'static str, input: &str, expected: bool, actual: bool) -> Result<(), Mismatch> {
+    if expected == actual {
+        return Ok(());
+    }
+    let verdict = |a| if a { "accepts" } else { "rejects" };
+    Err(Mismatch {
+        check,
+        input: Some(input.to_string()),
+        detail: format!(
+            "reference {}, {check} {}",
+            verdict(expected),
+            verdict(actual)
+        ),
+    })
+}
+
+fn check_minimal(dfa: &DFA, minimized: &DFA) -> Result<(), Mismatch> {
+    let expected = moore_class_count(dfa);
+    if minimized.states.len() == expected {
+        return Ok(());
+    }
+    Err(Mismatch {
+        check: "minimize_dfa state count",
+        input: None,
+        detail: format!("expected {expected} states, got {}", minimized.states.len()),
+    })
+}
+
+/// Number of states of the minimal DFA, by Moore's partition refinement over the reachable
+/// states. Deliberately shares nothing with `minimize_dfa`.
+fn moore_class_count(dfa: &DFA) -> usize {
+    let alphabet = sorted(dfa.alphabet.iter().copied());
+    let mut reachable = vec![dfa.start];
+    let mut seen = HashSet::from([dfa.start]);
+    let mut work_queue = VecDeque::from([dfa.start]);
+    while let Some(s) = work_queue.pop_front() {
+        for &c in &alphabet {
+            let t = dfa.tfn[&(s, c)];
+            if seen.insert(t) {
+                reachable.push(t);
+                work_queue.push_back(t);
+            }
+        }
+    }
+
+    let mut class: HashMap<State, usize> = reachable
+        .iter()
+        .map(|s| (*s, usize::from(dfa.accept.contains(s))))
+        .collect();
+    let mut count = class.values().collect::<HashSet<_>>().len();
+    loop {
+        let mut ids: HashMap<Vec<usize>, usize> = HashMap::new();
+        let refined: HashMap<State, usize> = reachable
+            .iter()
+            .map(|&s| {
+                let mut key = vec![class[&s]];
+                key.extend(alphabet.iter().map(|&c| class[&dfa.tfn[&(s, c)]]));
+                let next = ids.len();
+                (s, *ids.entry(key).or_insert(next))
+            })
+            .collect();
+        if ids.len() == count {
+            return count;
+        }
+        count = ids.len();
+        class = refined;
+    }
+}
+
+fn dfa_as_nfa(dfa: &DFA) -> NFA {
+    let tfn = dfa
+        .tfn
+        .iter()
+        .map(|(&key, &t)| (key, HashSet::from([t])))
+        .collect();
+    NFA::new(
+        dfa.states.len(),
+        dfa.start,
+        dfa.accept.clone(),
+        dfa.alphabet.clone(),
+        tfn,
+    )
+    .expect("a valid DFA is a valid NFA")
+}
+
+fn dfa_accepts(dfa: &DFA, input: &str) -> bool {
+    matches!(dfa.simulate(input), Ok(dfa::SimulationResult::Accepted))
+}
+
+fn nfa_accepts(nfa: &NFA, input: &str) -> bool {
+    accepted(nfa.simulate(input))
+}
+
+fn accepted<E>(result: Result<nfa::SimulationResult, E>) -> bool {
+    matches!(result, Ok(nfa::SimulationResult::Accepted))
+}
+
+fn sorted(symbols: impl IntoIterator<Item = char>) -> Vec<char> {
+    let mut symbols: Vec<char> = symbols.into_iter().collect();
+    symbols.sort();
+    symbols.dedup();
+    symbols
+}
+
+/// Every string over `alphabet` of length at most `max_len`, shortest first.
+fn all_strings(alphabet: &[char], max_len: usize) -> Vec<String> {
+    let mut out = vec![String::new()];
+    let mut layer = vec![String::new()];
+    for _ in 0..max_len {
+        layer = layer
+            .iter()
+            .flat_map(|prefix| {
+                alphabet.iter().map(move |&c| {
+                    let mut s = prefix.clone();
+                    s.push(c);
+                    s
+                })
+            })
+            .collect();
+        out.extend(layer.iter().cloned());
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn random_machines_agree_everywhere() {
+        let differential = Differential {
+            cases: 40,
+            ..Differential::default()
+        };
+        for seed in 0..2 {
+            if let Err(discrepancy) = differential.run(seed) {
+                panic!("{discrepancy}");
+            }
+        }
+    }
+
+    #[test]
+    fn dense_nfas_and_deep_regexes_agree() {
+        let differential = Differential {
+            cases: 10,
+            max_len: 5,
+            nfa: NFAOptions {
+                states: 8,
+                alphabet: vec!['a', 'b', 'c'],
+                branching: 2.0,
+                epsilon: 1.0,
+                ..NFAOptions::default()
+            },
+            regex: RegexOptions {
+                depth: 6,
+                ..RegexOptions::default()
+            },
+            ..Differential::default()
+        };
+        if let Err(discrepancy) = differential.run(40) {
+            panic!("{discrepancy}");
+        }
+    }
+
+    #[test]
+    fn reports_the_failing_input() {
+        let mismatch = compare("minimize_dfa", "ab", true, false).unwrap_err();
+        assert_eq!(mismatch.input.as_deref(), Some("ab"));
+        assert_eq!(mismatch.detail, "reference accepts, minimize_dfa rejects");
+    }
+}
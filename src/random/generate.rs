@@ -0,0 +1,264 @@
+//! Seeded generators for random machines and regexes, for property-based and differential
+//! testing. The same `SplitMix64` seed always gives the same machine.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::dfa::DFA;
+use crate::nfa::{EPSILON, NFA};
+use crate::random::SplitMix64;
+use crate::regex::Regex;
+
+#[derive(Clone, Debug)]
+pub struct DFAOptions {
+    pub states: usize,
+    pub alphabet: Vec<char>,
+    /// Probability that each state is accepting.
+    pub accept_probability: f64,
+}
+
+impl Default for DFAOptions {
+    fn default() -> Self {
+        Self {
+            states: 6,
+            alphabet: vec!['a', 'b'],
+            accept_probability: 0.3,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct NFAOptions {
+    pub states: usize,
+    pub alphabet: Vec<char>,
+    pub accept_probability: f64,
+    /// Expected number of targets per state and symbol; 1.0 is DFA-like, 0 leaves every
+    /// transition missing.
+    pub branching: f64,
+    /// Expected number of epsilon moves out of each state.
+    pub epsilon: f64,
+}
+
+impl Default for NFAOptions {
+    fn default() -> Self {
+        Self {
+            states: 6,
+            alphabet: vec!['a', 'b'],
+            accept_probability: 0.3,
+            branching: 1.2,
+            epsilon: 0.4,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct RegexOptions {
+    pub alphabet: Vec<char>,
+    /// Maximum nesting depth of operators.
+    pub depth: usize,
+    /// Probability that a leaf is `~` rather than a symbol.
+    pub epsilon_probability: f64,
+}
+
+impl Default for RegexOptions {
+    fn default() -> Self {
+        Self {
+            alphabet: vec!['a', 'b'],
+            depth: 4,
+            epsilon_probability: 0.1,
+        }
+    }
+}
+
+/// A total DFA with states `0..states` and uniformly random transitions. Unreachable and
+/// equivalent states are common, which is what minimization tests want.
+///
+/// Panics if `options.states` is 0, since a DFA needs a start state.
+pub fn random_dfa(rng: &mut SplitMix64, options: &DFAOptions) -> DFA {
+    assert!(options.states > 0, "a random DFA needs at least one state");
+    let mut tfn = HashMap::new();
+    for s in 0..options.states {
+        for &c in &options.alphabet {
+            tfn.insert((s, c), rng.below(options.states));
+        }
+    }
+    let accept = (0..options.states)
+        .filter(|_| rng.chance(options.accept_probability))
+        .collect();
+    DFA::new(
+        options.states,
+        0,
+        accept,
+        options.alphabet.iter().copied().collect(),
+        tfn,
+    )
+    .expect("generated DFA is total")
+}
+
+/// An NFA with states `0..states` where each possible edge is present independently, with
+/// probabilities chosen so the expected out-degrees match `branching` and `epsilon`.
+///
+/// Panics if `options.states` is 0 or `options.alphabet` contains `EPSILON`.
+pub fn random_nfa(rng: &mut SplitMix64, options: &NFAOptions) -> NFA {
+    let n = options.states;
+    assert!(n > 0, "a random NFA needs at least one state");
+    assert!(
+        !options.alphabet.contains(&EPSILON),
+        "EPSILON is reserved and cannot be in a random NFA's alphabet"
+    );
+    let edge_probability = options.branching / n as f64;
+    let epsilon_probability = options.epsilon / n.saturating_sub(1).max(1) as f64;
+    let mut tfn: HashMap<(usize, char), HashSet<usize>> = HashMap::new();
+    for s in 0..n {
+        for &c in &options.alphabet {
+            let targets: HashSet<usize> = (0..n).filter(|_| rng.chance(edge_probability)).collect();
+            if !targets.is_empty() {
+                tfn.insert((s, c), targets);
+            }
+        }
+        let targets: HashSet<usize> = (0..n)
+            .filter(|&t| t != s && rng.chance(epsilon_probability))
+            .collect();
+        if !targets.is_empty() {
+            tfn.insert((s, EPSILON), targets);
+        }
+    }
+    let accept = (0..n)
+        .filter(|_| rng.chance(options.accept_probability))
+        .collect();
+    NFA::new(
+        n,
+        0,
+        accept,
+        options.alphabet.iter().copied().collect(),
+        tfn,
+    )
+    .expect("generated NFA is valid")
+}
+
+/// A random regex of nesting depth at most `depth`. Leaves get likelier as depth runs out, so
+/// sizes vary instead of always being full trees.
+///
+/// Panics if `options.alphabet` contains `EPSILON`, which no regex can use as a symbol.
+pub fn random_regex(rng: &mut SplitMix64, options: &RegexOptions) -> Regex {
+    assert!(
+        !options.alphabet.contains(&EPSILON),
+        "EPSILON is reserved and cannot be in a random regex's alphabet"
+    );
+    random_regex_at(rng, options, options.depth)
+}
+
+fn random_regex_at(rng: &mut SplitMix64, options: &RegexOptions, depth: usize) -> Regex {
+    if depth == 0 || rng.chance(1.0 / (depth + 1) as f64) {
+        return match rng.choose(&options.alphabet) {
+            Some(&c) if !rng.chance(options.epsilon_probability) => Regex::Symbol(c),
+            _ => Regex::Epsilon,
+        };
+    }
+    match rng.below(3) {
+        0 => Regex::concat(
+            random_regex_at(rng, options, depth - 1),
+            random_regex_at(rng, options, depth - 1),
+        ),
+        1 => Regex::alt(
+            random_regex_at(rng, options, depth - 1),
+            random_regex_at(rng, options, depth - 1),
+        ),
+        _ => Regex::star(random_regex_at(rng, options, depth - 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generators_are_reproducible() {
+        let dfa_a = random_dfa(&mut SplitMix64::new(40), &DFAOptions::default());
+        let dfa_b = random_dfa(&mut SplitMix64::new(40), &DFAOptions::default());
+        assert_eq!(dfa_a.tfn, dfa_b.tfn);
+        assert_eq!(dfa_a.accept, dfa_b.accept);
+
+        let nfa_a = random_nfa(&mut SplitMix64::new(40), &NFAOptions::default());
+        let nfa_b = random_nfa(&mut SplitMix64::new(40), &NFAOptions::default());
+        assert_eq!(nfa_a.tfn, nfa_b.tfn);
+
+        let regex_a = random_regex(&mut SplitMix64::new(40), &RegexOptions::default());
+        let regex_b = random_regex(&mut SplitMix64::new(40), &RegexOptions::default());
+        assert_eq!(regex_a, regex_b);
+    }
+
+    #[test]
+    fn nfa_density_is_tunable() {
+        let options = NFAOptions {
+            states: 20,
+            branching: 0.0,
+            epsilon: 0.0,
+            ..NFAOptions::default()
+        };
+        let sparse = random_nfa(&mut SplitMix64::new(1), &options);
+        assert!(sparse.tfn.is_empty());
+
+        let options = NFAOptions {
+            states: 20,
+            branching: 3.0,
+            epsilon: 2.0,
+            ..NFAOptions::default()
+        };
+        let dense = random_nfa(&mut SplitMix64::new(1), &options);
+        let epsilon_moves: usize = (0..20)
+            .filter_map(|s| dense.tfn.get(&(s, EPSILON)))
+            .map(|t| t.len())
+            .sum();
+        assert!(epsilon_moves > 10);
+        assert!(dense.tfn.values().any(|t| t.len() > 1));
+    }
+
+    #[test]
+    fn dfa_alphabet_may_contain_tilde() {
+        let options = DFAOptions {
+            alphabet: vec!['a', EPSILON],
+            ..DFAOptions::default()
+        };
+        let dfa = random_dfa(&mut SplitMix64::new(5), &options);
+        assert!(dfa.tfn.contains_key(&(0, EPSILON)));
+    }
+
+    #[test]
+    #[should_panic(expected = "at least one state")]
+    fn dfa_without_states_is_rejected() {
+        let options = DFAOptions {
+            states: 0,
+            ..DFAOptions::default()
+        };
+        random_dfa(&mut SplitMix64::new(5), &options);
+    }
+
+    #[test]
+    #[should_panic(expected = "EPSILON is reserved")]
+    fn nfa_alphabet_with_epsilon_is_rejected() {
+        let options = NFAOptions {
+            alphabet: vec!['a', EPSILON],
+            ..NFAOptions::default()
+        };
+        random_nfa(&mut SplitMix64::new(5), &options);
+    }
+
+    #[test]
+    fn regex_depth_is_bounded() {
+        fn depth(r: &Regex) -> usize {
+            match r {
+                Regex::Epsilon | Regex::Symbol(_) => 0,
+                Regex::Concat(a, b) | Regex::Alt(a, b) => 1 + depth(a).max(depth(b)),
+                Regex::Star(a) => 1 + depth(a),
+            }
+        }
+        let mut rng = SplitMix64::new(3);
+        let options = RegexOptions {
+            depth: 3,
+            ..RegexOptions::default()
+        };
+        for _ in 0..50 {
+            assert!(depth(&random_regex(&mut rng, &options)) <= 3);
+        }
+    }
+}
@@ -0,0 +1,383 @@
+//! Regular expressions over single-char symbols, with Thompson construction to an `NFA`.
+//!
+//! The syntax is the textbook one: `|` for alternation, juxtaposition for concatenation, postfix
+//! `*`, `+` and `?`, parentheses for grouping and `~` (the NFA epsilon symbol) for the empty
+//! string. Any other char is a literal; `\` escapes the operators, except `~`, which is reserved
+//! by `NFA` and cannot be a literal. An empty alternative such as `(a|)` also denotes the empty
+//! string.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::nfa::{EPSILON, NFA, NFATypeError};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Regex {
+    Epsilon,
+    Symbol(char),
+    Concat(Box<Regex>, Box<Regex>),
+    Alt(Box<Regex>, Box<Regex>),
+    Star(Box<Regex>),
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum RegexParseError {
+    UnexpectedChar { position: usize, found: char },
+    UnclosedGroup { position: usize },
+    DanglingEscape,
+    EscapedEpsilon { position: usize },
+    NothingToRepeat { position: usize },
+}
+
+const OPERATORS: &[char] = &['|', '*', '+', '?', '(', ')', '\\', EPSILON];
+
+impl Regex {
+    pub fn concat(a: Regex, b: Regex) -> Regex {
+        Regex::Concat(Box::new(a), Box::new(b))
+    }
+
+    pub fn alt(a: Regex, b: Regex) -> Regex {
+        Regex::Alt(Box::new(a), Box::new(b))
+    }
+
+    pub fn star(a: Regex) -> Regex {
+        Regex::Star(Box::new(a))
+    }
+
+    /// The literal symbols used, i.e. the alphabet of the Thompson NFA.
+    pub fn symbols(&self) -> BTreeSet<char> {
+        let mut out = BTreeSet::new();
+        self.collect_symbols(&mut out);
+        out
+    }
+
+    fn collect_symbols(&self, out: &mut BTreeSet<char>) {
+        match self {
+            Regex::Epsilon => {}
+            Regex::Symbol(c) => {
+                out.insert(*c);
+            }
+            Regex::Concat(a, b) | Regex::Alt(a, b) => {
+                a.collect_symbols(out);
+                b.collect_symbols(out);
+            }
+            Regex::Star(a) => a.collect_symbols(out),
+        }
+    }
+
+    /// Direct backtracking matcher on the syntax tree. It shares no code with the automata, which
+    /// makes it a useful oracle when testing them.
+    pub fn is_match(&self, input: &str) -> bool {
+        let chars: Vec<char> = input.chars().collect();
+        self.ends(&chars, 0).contains(&chars.len())
+    }
+
+    /// All positions `j` such that `chars[i..j]` matches.
+    fn ends(&self, chars: &[char], i: usize) -> BTreeSet<usize> {
+        match self {
+            Regex::Epsilon => BTreeSet::from([i]),
+            Regex::Symbol(c) => chars
+                .get(i)
+                .filter(|&d| d == c)
+                .map(|_| i + 1)
+                .into_iter()
+                .collect(),
+            Regex::Concat(a, b) => a
+                .ends(chars, i)
+                .into_iter()
+                .flat_map(|j| b.ends(chars, j))
+                .collect(),
+            Regex::Alt(a, b) => {
+                let mut out = a.ends(chars, i);
+                out.extend(b.ends(chars, i));
+                out
+            }
+            Regex::Star(a) => {
+                let mut out = BTreeSet::from([i]);
+                let mut frontier = vec![i];
+                while let Some(j) = frontier.pop() {
+                    for k in a.ends(chars, j) {
+                        if out.insert(k) {
+                            frontier.push(k);
+                        }
+                    }
+                }
+                out
+            }
+        }
+    }
+
+    /// Thompson construction: one fresh start and accept state per operator, joined with epsilon
+    /// moves. The NFA has a single accept state and its alphabet is `symbols()`.
+    ///
+    /// The parser never produces `Symbol(EPSILON)`, but the enum is public, so a regex built by
+    /// hand can contain one. `NFA` reserves that symbol, so such a regex is rejected here with
+    /// `ReservedCharacterInAlphabet` rather than at construction.
+    pub fn to_nfa(&self) -> Result<NFA, NFATypeError> {
+        let mut builder = Thompson::default();
+        let (start, accept) = builder.build(self);
+        NFA::new(
+            builder.states,
+            start,
+            HashSet::from([accept]),
+            self.symbols().into_iter().collect(),
+            builder.tfn,
+        )
+    }
+
+    fn precedence(&self) -> u8 {
+        match self {
+            Regex::Alt(..) => 0,
+            Regex::Concat(..) => 1,
+            Regex::Star(_) => 2,
+            Regex::Epsilon | Regex::Symbol(_) => 3,
+        }
+    }
+
+    fn fmt_at(&self, f: &mut fmt::Formatter, min: u8) -> fmt::Result {
+        if self.precedence() < min {
+            write!(f, "(")?;
+            self.fmt_at(f, 0)?;
+            return write!(f, ")");
+        }
+        match self {
+            Regex::Epsilon => write!(f, "{EPSILON}"),
+            Regex::Symbol(c) if OPERATORS.contains(c) => write!(f, "\\{c}"),
+            Regex::Symbol(c) => write!(f, "{c}"),
+            Regex::Concat(a, b) => {
+                a.fmt_at(f, 1)?;
+                b.fmt_at(f, 2)
+            }
+            Regex::Alt(a, b) => {
+                a.fmt_at(f, 0)?;
+                write!(f, "|")?;
+                b.fmt_at(f, 1)
+            }
+            Regex::Star(a) => {
+                a.fmt_at(f, 3)?;
+                write!(f, "*")
+            }
+        }
+    }
+}
+
+impl fmt::Display for Regex {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.fmt_at(f, 0)
+    }
+}
+
+#[derive(Default)]
+struct Thompson {
+    states: usize,
+    tfn: HashMap<(usize, char), HashSet<usize>>,
+}
+
+impl Thompson {
+    fn fresh(&mut self) -> usize {
+        self.states += 1;
+        self.states - 1
+    }
+
+    fn edge(&mut self, from: usize, symbol: char, to: usize) {
+        self.tfn.entry((from, symbol)).or_default().insert(to);
+    }
+
+    fn build(&mut self, regex: &Regex) -> (usize, usize) {
+        match regex {
+            Regex::Epsilon | Regex::Symbol(_) => {
+                let (start, accept) = (self.fresh(), self.fresh());
+                let symbol = match regex {
+                    Regex::Symbol(c) => *c,
+                    _ => EPSILON,
+                };
+                self.edge(start, symbol, accept);
+                (start, accept)
+            }
+            Regex::Concat(a, b) => {
+                let (a_start, a_accept) = self.build(a);
+                let (b_start, b_accept) = self.build(b);
+                self.edge(a_accept, EPSILON, b_start);
+                (a_start, b_accept)
+            }
+            Regex::Alt(a, b) => {
+                let start = self.fresh();
+                let (a_start, a_accept) = self.build(a);
+                let (b_start, b_accept) = self.build(b);
+                let accept = self.fresh();
+                self.edge(start, EPSILON, a_start);
+                self.edge(start, EPSILON, b_start);
+                self.edge(a_accept, EPSILON, accept);
+                self.edge(b_accept, EPSILON, accept);
+                (start, accept)
+            }
+            Regex::Star(a) => {
+                let start = self.fresh();
+                let (a_start, a_accept) = self.build(a);
+                let accept = self.fresh();
+                self.edge(start, EPSILON, a_start);
+                self.edge(start, EPSILON, accept);
+                self.edge(a_accept, EPSILON, a_start);
+                self.edge(a_accept, EPSILON, accept);
+                (start, accept)
+            }
+        }
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn alternation(&mut self) -> Result<Regex, RegexParseError> {
+        let mut regex = self.concatenation()?;
+        while self.peek() == Some('|') {
+            self.pos += 1;
+            regex = Regex::alt(regex, self.concatenation()?);
+        }
+        Ok(regex)
+    }
+
+    fn concatenation(&mut self) -> Result<Regex, RegexParseError> {
+        let mut regex: Option<Regex> = None;
+        while let Some(c) = self.peek() {
+            if c == '|' || c == ')' {
+                break;
+            }
+            let next = self.repetition()?;
+            regex = Some(match regex {
+                Some(prev) => Regex::concat(prev, next),
+                None => next,
+            });
+        }
+        Ok(regex.unwrap_or(Regex::Epsilon))
+    }
+
+    fn repetition(&mut self) -> Result<Regex, RegexParseError> {
+        let mut regex = self.atom()?;
+        while let Some(c) = self.peek() {
+            regex = match c {
+                '*' => Regex::star(regex),
+                '+' => Regex::concat(regex.clone(), Regex::star(regex)),
+                '?' => Regex::alt(regex, Regex::Epsilon),
+                _ => break,
+            };
+            self.pos += 1;
+        }
+        Ok(regex)
+    }
+
+    fn atom(&mut self) -> Result<Regex, RegexParseError> {
+        let position = self.pos;
+        let c = self.peek().expect("callers check for end of input");
+        self.pos += 1;
+        match c {
+            '(' => {
+                let regex = self.alternation()?;
+                if self.peek() != Some(')') {
+                    return Err(RegexParseError::UnclosedGroup { position });
+                }
+                self.pos += 1;
+                Ok(regex)
+            }
+            '\\' => {
+                let c = self.peek().ok_or(RegexParseError::DanglingEscape)?;
+                if c == EPSILON {
+                    return Err(RegexParseError::EscapedEpsilon { position });
+                }
+                self.pos += 1;
+                Ok(Regex::Symbol(c))
+            }
+            '*' | '+' | '?' => Err(RegexParseError::NothingToRepeat { position }),
+            EPSILON => Ok(Regex::Epsilon),
+            c => Ok(Regex::Symbol(c)),
+        }
+    }
+}
+
+impl FromStr for Regex {
+    type Err = RegexParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser {
+            chars: s.chars().collect(),
+            pos: 0,
+        };
+        let regex = parser.alternation()?;
+        match parser.peek() {
+            None => Ok(regex),
+            Some(found) => Err(RegexParseError::UnexpectedChar {
+                position: parser.pos,
+                found,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::nfa::SimulationResult;
+
+    #[test]
+    fn parse_and_display_round_trip() {
+        for text in ["a", "ab|c", "(a|b)*abb", "a(b|~)", "\\*\\(", "(ab)*|c*d"] {
+            let regex: Regex = text.parse().unwrap();
+            assert_eq!(regex.to_string(), text);
+        }
+        let plus: Regex = "a+".parse().unwrap();
+        assert_eq!(plus.to_string(), "aa*");
+        let empty_alternative: Regex = "(a|)".parse().unwrap();
+        assert_eq!(empty_alternative.to_string(), "a|~");
+    }
+
+    #[test]
+    fn parse_errors() {
+        assert_eq!(
+            "(ab".parse::<Regex>(),
+            Err(RegexParseError::UnclosedGroup { position: 0 })
+        );
+        assert_eq!(
+            "a)".parse::<Regex>(),
+            Err(RegexParseError::UnexpectedChar {
+                position: 1,
+                found: ')'
+            })
+        );
+        assert_eq!(
+            "*a".parse::<Regex>(),
+            Err(RegexParseError::NothingToRepeat { position: 0 })
+        );
+        assert_eq!("a\\".parse::<Regex>(), Err(RegexParseError::DanglingEscape));
+        assert_eq!(
+            "a\\~".parse::<Regex>(),
+            Err(RegexParseError::EscapedEpsilon { position: 1 })
+        );
+        // a hand-built epsilon symbol has no NFA, and does not parse back either
+        let tilde = Regex::Symbol(EPSILON);
+        assert!(matches!(
+            tilde.to_nfa(),
+            Err(NFATypeError::ReservedCharacterInAlphabet)
+        ));
+        assert!(tilde.to_string().parse::<Regex>().is_err());
+    }
+
+    #[test]
+    fn thompson_nfa_agrees_with_matcher() {
+        let regex: Regex = "(a|b)*abb|~".parse().unwrap();
+        let nfa = regex.to_nfa().unwrap();
+        for input in ["", "abb", "aabb", "babb", "ab", "abba", "b"] {
+            let accepted = matches!(nfa.simulate(input), Ok(SimulationResult::Accepted));
+            assert_eq!(accepted, regex.is_match(input), "{input:?}");
+        }
+        assert!(regex.is_match("bbabb"));
+        assert!(!regex.is_match("bba"));
+    }
+}
@@ -0,0 +1,234 @@
+use std::collections::{HashMap, HashSet};
+
+use crate::nfa::{EPSILON, InputError, NFA, SimulationResult, State};
+
+/// Fixed-capacity set of states backed by 64-bit words.
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+pub struct BitSet {
+    words: Vec<u64>,
+}
+
+impl BitSet {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            words: vec![0; capacity.div_ceil(64)],
+        }
+    }
+
+    pub fn insert(&mut self, s: State) -> bool {
+        let (w, b) = (s / 64, 1u64 << (s % 64));
+        let fresh = self.words[w] & b == 0;
+        self.words[w] |= b;
+        fresh
+    }
+
+    pub fn contains(&self, s: State) -> bool {
+        self.words
+            .get(s / 64)
+            .is_some_and(|w| w & (1u64 << (s % 64)) != 0)
+    }
+
+    pub fn union_with(&mut self, other: &BitSet) {
+        for (w, o) in self.words.iter_mut().zip(&other.words) {
+            *w |= o;
+        }
+    }
+
+    pub fn intersects(&self, other: &BitSet) -> bool {
+        self.words.iter().zip(&other.words).any(|(w, o)| w & o != 0)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.words.iter().all(|&w| w == 0)
+    }
+
+    pub fn clear(&mut self) {
+        self.words.iter_mut().for_each(|w| *w = 0);
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = State> + '_ {
+        self.words.iter().enumerate().flat_map(|(i, &w)| {
+            let mut w = w;
+            std::iter::from_fn(move || {
+                if w == 0 {
+                    return None;
+                }
+                let b = w.trailing_zeros() as usize;
+                w &= w - 1;
+                Some(i * 64 + b)
+            })
+        })
+    }
+
+    pub fn to_hash_set(&self) -> HashSet<State> {
+        self.iter().collect()
+    }
+}
+
+/// An `NFA` compiled for repeated simulation: epsilon closures are computed once up front and
+/// every state set is a `BitSet`, so a step is a handful of word-wide ORs instead of hashing.
+#[derive(Clone)]
+pub struct BitsetNFA {
+    pub(crate) num_states: usize,
+    pub(crate) symbols: HashMap<char, usize>,
+    pub(crate) start: BitSet,
+    pub(crate) accept: BitSet,
+    // closures[s] is the epsilon closure of s
+    pub(crate) closures: Vec<BitSet>,
+    // moves[s][a] is the epsilon closure of everything reachable from s on a
+    pub(crate) moves: Vec<Vec<BitSet>>,
+}
+
+impl BitsetNFA {
+    pub fn new(nfa: &NFA) -> Self {
+        let num_states = nfa.states.len();
+        let mut alphabet: Vec<char> = nfa
+            .alphabet
+            .iter()
+            .copied()
+            .filter(|&c| c != EPSILON)
+            .collect();
+        alphabet.sort();
+        let symbols: HashMap<char, usize> =
+            alphabet.iter().enumerate().map(|(i, &c)| (c, i)).collect();
+
+        let to_bitset = |set: &HashSet<State>| {
+            let mut b = BitSet::new(num_states);
+            set.iter().for_each(|&s| {
+                b.insert(s);
+            });
+            b
+        };
+
+        let closures: Vec<BitSet> = (0..num_states)
+            .map(|s| to_bitset(&nfa.epsilon_closure(&HashSet::from([s]))))
+            .collect();
+
+        let moves = (0..num_states)
+            .map(|s| {
+                alphabet
+                    .iter()
+                    .map(|&c| {
+                        let mut next = BitSet::new(num_states);
+                        if let Some(targets) = nfa.tfn.get(&(s, c)) {
+                            targets.iter().for_each(|&t| next.union_with(&closures[t]));
+                        }
+                        next
+                    })
+                    .collect()
+            })
+            .collect();
+
+        Self {
+            num_states,
+            symbols,
+            start: closures[nfa.start].clone(),
+            accept: to_bitset(&nfa.accept),
+            closures,
+            moves,
+        }
+    }
+
+    pub(crate) fn symbol_index(&self, c: char) -> Result<usize, InputError> {
+        self.symbols
+            .get(&c)
+            .copied()
+            .ok_or(InputError::InvalidSymbol)
+    }
+
+    pub(crate) fn step_into(&self, current: &BitSet, symbol: usize, next: &mut BitSet) {
+        next.clear();
+        for s in current.iter() {
+            next.union_with(&self.moves[s][symbol]);
+        }
+    }
+
+    pub fn start_set(&self) -> &BitSet {
+        &self.start
+    }
+
+    pub fn is_accepting(&self, set: &BitSet) -> bool {
+        set.intersects(&self.accept)
+    }
+
+    pub fn simulate(&self, input: &str) -> Result<SimulationResult, InputError> {
+        let symbols = input
+            .chars()
+            .map(|c| self.symbol_index(c))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut current = self.start.clone();
+        let mut next = BitSet::new(self.num_states);
+        for a in symbols {
+            self.step_into(&current, a, &mut next);
+            std::mem::swap(&mut current, &mut next);
+            if current.is_empty() {
+                break;
+            }
+        }
+        if self.is_accepting(&current) {
+            return Ok(SimulationResult::Accepted);
+        }
+        Ok(SimulationResult::Rejected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::ends_with_11;
+
+    #[test]
+    fn bitset_insert_and_iterate() {
+        let mut b = BitSet::new(130);
+        assert!(b.insert(0));
+        assert!(b.insert(64));
+        assert!(b.insert(129));
+        assert!(!b.insert(64));
+        assert_eq!(b.iter().collect::<Vec<_>>(), vec![0, 64, 129]);
+        assert!(b.contains(129));
+        assert!(!b.contains(1));
+        assert!(!b.contains(500));
+    }
+
+    #[test]
+    fn simulate_matches_nfa_simulate() {
+        let nfa = ends_with_11();
+        let fast = BitsetNFA::new(&nfa);
+        for input in ["", "0", "1", "11", "0011", "0110", "111", "1010"] {
+            let expected = matches!(nfa.simulate(input), Ok(SimulationResult::Accepted));
+            let actual = matches!(fast.simulate(input), Ok(SimulationResult::Accepted));
+            assert_eq!(expected, actual, "input {input:?}");
+        }
+    }
+
+    #[test]
+    fn simulate_follows_epsilon_transitions() {
+        // 0 -~-> 1 -a-> 2 -~-> 3, accept 3
+        let mut tfn = HashMap::new();
+        tfn.insert((0, EPSILON), HashSet::from([1]));
+        tfn.insert((1, 'a'), HashSet::from([2]));
+        tfn.insert((2, EPSILON), HashSet::from([3]));
+        let nfa = NFA::new(4, 0, HashSet::from([3]), HashSet::from(['a']), tfn).unwrap();
+        let fast = BitsetNFA::new(&nfa);
+        assert!(matches!(fast.simulate("a"), Ok(SimulationResult::Accepted)));
+        assert!(matches!(fast.simulate(""), Ok(SimulationResult::Rejected)));
+        assert!(matches!(
+            fast.simulate("aa"),
+            Ok(SimulationResult::Rejected)
+        ));
+    }
+
+    #[test]
+    fn simulate_fails_on_invalid_input() {
+        let fast = BitsetNFA::new(&ends_with_11());
+        assert!(matches!(
+            fast.simulate("01a"),
+            Err(InputError::InvalidSymbol)
+        ));
+        assert!(matches!(
+            fast.simulate("0~1"),
+            Err(InputError::InvalidSymbol)
+        ));
+    }
+}
@@ -0,0 +1,441 @@
+//! Explanations of why a machine accepted or rejected an input: the run itself, the point from
+//! which no accept state was reachable any more, and the shortest suffix that would have led to
+//! acceptance from where the run ended. The reports are plain data; `Display` renders them as
+//! text for a student.
+
+use std::collections::{BTreeSet, HashMap, HashSet, VecDeque};
+use std::fmt;
+
+use crate::dfa::{DFA, State};
+use crate::nfa::{EPSILON, NFA};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Verdict {
+    Accepted,
+    Rejected,
+    /// The run stopped at a symbol outside the alphabet; `position` is in chars.
+    InvalidSymbol {
+        position: usize,
+        symbol: char,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DFAStep {
+    pub symbol: char,
+    pub from: State,
+    pub to: State,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct DFAExplanation {
+    pub input: String,
+    pub verdict: Verdict,
+    pub trace: Vec<DFAStep>,
+    pub final_state: State,
+    /// Number of symbols read when the run entered a state from which no accept state is
+    /// reachable, if it did.
+    pub dead_after: Option<usize>,
+    /// Shortest suffix (shortlex-least among those) accepted from `final_state`; `Some("")` when
+    /// the input was accepted and `None` when no suffix helps.
+    pub completion: Option<String>,
+}
+
+/// One step of an NFA run, between epsilon closures.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NFAStep {
+    pub symbol: char,
+    pub from: Vec<State>,
+    pub to: Vec<State>,
+    /// States of `from` with no move on `symbol`: the branches that ended here.
+    pub died: Vec<State>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct NFAExplanation {
+    pub input: String,
+    pub verdict: Verdict,
+    /// Epsilon closure of the start state.
+    pub start: Vec<State>,
+    pub trace: Vec<NFAStep>,
+    pub final_states: Vec<State>,
+    /// Number of symbols read when no branch could reach an accept state any more.
+    pub dead_after: Option<usize>,
+    pub completion: Option<String>,
+}
+
+/// States from which an accept state is reachable, by a backwards search over `edges`.
+fn live_states(
+    accept: &HashSet<State>,
+    edges: impl IntoIterator<Item = (State, State)>,
+) -> HashSet<State> {
+    let mut reverse: HashMap<State, Vec<State>> = HashMap::new();
+    for (p, q) in edges {
+        reverse.entry(q).or_default().push(p);
+    }
+    let mut live = accept.clone();
+    let mut work_queue: VecDeque<State> = accept.iter().copied().collect();
+    while let Some(q) = work_queue.pop_front() {
+        for &p in reverse.get(&q).into_iter().flatten() {
+            if live.insert(p) {
+                work_queue.push_back(p);
+            }
+        }
+    }
+    live
+}
+
+fn sorted_alphabet<'a>(alphabet: impl IntoIterator<Item = &'a char>) -> Vec<char> {
+    let mut alphabet: Vec<char> = alphabet.into_iter().copied().collect();
+    alphabet.sort();
+    alphabet
+}
+
+/// Breadth-first search for the shortlex-least word leading from `start` to a configuration
+/// satisfying `is_goal`.
+fn shortest_word<C: Clone + Eq + std::hash::Hash>(
+    start: C,
+    alphabet: &[char],
+    step: impl Fn(&C, char) -> C,
+    is_goal: impl Fn(&C) -> bool,
+) -> Option<String> {
+    let mut parent: HashMap<C, Option<(C, char)>> = HashMap::from([(start.clone(), None)]);
+    let mut work_queue = VecDeque::from([start]);
+    while let Some(config) = work_queue.pop_front() {
+        if is_goal(&config) {
+            let mut word = Vec::new();
+            let mut at = config;
+            while let Some((prev, c)) = parent[&at].clone() {
+                word.push(c);
+                at = prev;
+            }
+            return Some(word.into_iter().rev().collect());
+        }
+        for &c in alphabet {
+            let next = step(&config, c);
+            if !parent.contains_key(&next) {
+                parent.insert(next.clone(), Some((config.clone(), c)));
+                work_queue.push_back(next);
+            }
+        }
+    }
+    None
+}
+
+impl DFA {
+    pub fn explain(&self, input: &str) -> DFAExplanation {
+        let live = live_states(&self.accept, self.tfn.iter().map(|(&(p, _), &q)| (p, q)));
+        let mut state = self.start;
+        let mut trace = Vec::new();
+        let mut dead_after = (!live.contains(&state)).then_some(0);
+        let mut verdict = None;
+        for (position, symbol) in input.chars().enumerate() {
+            let Some(&to) = self.tfn.get(&(state, symbol)) else {
+                verdict = Some(Verdict::InvalidSymbol { position, symbol });
+                break;
+            };
+            trace.push(DFAStep {
+                symbol,
+                from: state,
+                to,
+            });
+            state = to;
+            if dead_after.is_none() && !live.contains(&state) {
+                dead_after = Some(position + 1);
+            }
+        }
+        let verdict = verdict.unwrap_or(if self.accept.contains(&state) {
+            Verdict::Accepted
+        } else {
+            Verdict::Rejected
+        });
+        let completion = if live.contains(&state) {
+            shortest_word(
+                state,
+                // a DFA has no epsilon moves, so `~` is an ordinary symbol here
+                &sorted_alphabet(&self.alphabet),
+                |&s, c| self.tfn[&(s, c)],
+                |s| self.accept.contains(s),
+            )
+        } else {
+            None
+        };
+        DFAExplanation {
+            input: input.to_string(),
+            verdict,
+            trace,
+            final_state: state,
+            dead_after,
+            completion,
+        }
+    }
+}
+
+impl NFA {
+    fn closure_sorted(&self, states: impl IntoIterator<Item = State>) -> BTreeSet<State> {
+        self.epsilon_closure(&states.into_iter().collect())
+            .into_iter()
+            .collect()
+    }
+
+    pub fn explain(&self, input: &str) -> NFAExplanation {
+        let live = live_states(
+            &self.accept,
+            self.tfn
+                .iter()
+                .flat_map(|(&(p, _), targets)| targets.iter().map(move |&q| (p, q))),
+        );
+        let is_dead = |set: &BTreeSet<State>| !set.iter().any(|s| live.contains(s));
+        let alphabet = sorted_alphabet(self.alphabet.iter().filter(|&&c| c != EPSILON));
+
+        let start = self.closure_sorted([self.start]);
+        let mut current = start.clone();
+        let mut trace = Vec::new();
+        let mut dead_after = is_dead(&current).then_some(0);
+        let mut verdict = None;
+        for (position, symbol) in input.chars().enumerate() {
+            if symbol == EPSILON || !self.alphabet.contains(&symbol) {
+                verdict = Some(Verdict::InvalidSymbol { position, symbol });
+                break;
+            }
+            let mut died = Vec::new();
+            let mut moved = Vec::new();
+            for &s in &current {
+                match self.tfn.get(&(s, symbol)) {
+                    Some(targets) => moved.extend(targets.iter().copied()),
+                    None => died.push(s),
+                }
+            }
+            let next = self.closure_sorted(moved);
+            trace.push(NFAStep {
+                symbol,
+                from: current.iter().copied().collect(),
+                to: next.iter().copied().collect(),
+                died,
+            });
+            current = next;
+            if dead_after.is_none() && is_dead(&current) {
+                dead_after = Some(position + 1);
+            }
+        }
+        let accepted = current.iter().any(|s| self.accept.contains(s));
+        let verdict = verdict.unwrap_or(if accepted {
+            Verdict::Accepted
+        } else {
+            Verdict::Rejected
+        });
+        let completion = if is_dead(&current) {
+            None
+        } else {
+            shortest_word(
+                current.clone(),
+                &alphabet,
+                |set, c| {
+                    self.closure_sorted(
+                        set.iter()
+                            .flat_map(|&s| self.tfn.get(&(s, c)).into_iter().flatten().copied()),
+                    )
+                },
+                |set| set.iter().any(|s| self.accept.contains(s)),
+            )
+        };
+        NFAExplanation {
+            input: input.to_string(),
+            verdict,
+            start: start.into_iter().collect(),
+            trace,
+            final_states: current.into_iter().collect(),
+            dead_after,
+            completion,
+        }
+    }
+}
+
+fn state_set(states: &[State]) -> String {
+    let names: Vec<String> = states.iter().map(|s| format!("q{s}")).collect();
+    format!("{{{}}}", names.join(", "))
+}
+
+fn write_verdict(f: &mut fmt::Formatter, input: &str, verdict: Verdict) -> fmt::Result {
+    match verdict {
+        Verdict::Accepted => writeln!(f, "{input:?} is accepted"),
+        Verdict::Rejected => writeln!(f, "{input:?} is rejected"),
+        Verdict::InvalidSymbol { position, symbol } => writeln!(
+            f,
+            "{input:?} is rejected: {symbol:?} at position {position} is not in the alphabet"
+        ),
+    }
+}
+
+fn write_outcome(
+    f: &mut fmt::Formatter,
+    input: &str,
+    verdict: Verdict,
+    dead_after: Option<usize>,
+    completion: &Option<String>,
+) -> fmt::Result {
+    if let Some(n) = dead_after {
+        let prefix: String = input.chars().take(n).collect();
+        writeln!(
+            f,
+            "after reading {prefix:?} no accept state is reachable any more"
+        )?;
+    }
+    match (verdict, completion) {
+        (Verdict::Accepted, _) => Ok(()),
+        (_, Some(suffix)) => writeln!(f, "appending {suffix:?} would be accepted"),
+        (_, None) => writeln!(f, "no continuation can be accepted"),
+    }
+}
+
+impl fmt::Display for DFAExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_verdict(f, &self.input, self.verdict)?;
+        for step in &self.trace {
+            writeln!(f, "  q{} --{}--> q{}", step.from, step.symbol, step.to)?;
+        }
+        writeln!(f, "the run ends in q{}", self.final_state)?;
+        write_outcome(
+            f,
+            &self.input,
+            self.verdict,
+            self.dead_after,
+            &self.completion,
+        )
+    }
+}
+
+impl fmt::Display for NFAExplanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write_verdict(f, &self.input, self.verdict)?;
+        writeln!(f, "  start in {}", state_set(&self.start))?;
+        for step in &self.trace {
+            write!(
+                f,
+                "  {} --{}--> {}",
+                state_set(&step.from),
+                step.symbol,
+                state_set(&step.to)
+            )?;
+            if !step.died.is_empty() {
+                write!(f, "; branches in {} die", state_set(&step.died))?;
+            }
+            writeln!(f)?;
+        }
+        writeln!(f, "the run ends in {}", state_set(&self.final_states))?;
+        write_outcome(
+            f,
+            &self.input,
+            self.verdict,
+            self.dead_after,
+            &self.completion,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fsim_macros::dfa;
+
+    // strings over {a, b} starting with "ab"; state 3 is the trap
+    fn starts_with_ab() -> DFA {
+        dfa! {
+            states: 4,
+            start: 0,
+            accept: [2],
+            alphabet: ['a', 'b'],
+            transitions: {
+                0 => { 'a' => 1, 'b' => 3 },
+                1 => { 'a' => 3, 'b' => 2 },
+                2 => { 'a' | 'b' => 2 },
+                3 => { 'a' | 'b' => 3 },
+            },
+        }
+    }
+
+    #[test]
+    fn dfa_rejection_reports_dead_point() {
+        let report = starts_with_ab().explain("aab");
+        assert_eq!(report.verdict, Verdict::Rejected);
+        assert_eq!(report.trace.len(), 3);
+        assert_eq!(report.dead_after, Some(2));
+        assert_eq!(report.completion, None);
+        assert_eq!(
+            report.to_string(),
+            concat!(
+                "\"aab\" is rejected\n",
+                "  q0 --a--> q1\n",
+                "  q1 --a--> q3\n",
+                "  q3 --b--> q3\n",
+                "the run ends in q3\n",
+                "after reading \"aa\" no accept state is reachable any more\n",
+                "no continuation can be accepted\n",
+            )
+        );
+    }
+
+    #[test]
+    fn dfa_completion_and_invalid_symbol() {
+        let dfa = starts_with_ab();
+        let report = dfa.explain("a");
+        assert_eq!(report.dead_after, None);
+        assert_eq!(report.completion.as_deref(), Some("b"));
+        assert!(
+            report
+                .to_string()
+                .ends_with("appending \"b\" would be accepted\n")
+        );
+
+        let report = dfa.explain("abx");
+        assert_eq!(
+            report.verdict,
+            Verdict::InvalidSymbol {
+                position: 2,
+                symbol: 'x'
+            }
+        );
+        assert_eq!(report.final_state, 2);
+        assert_eq!(dfa.explain("abba").completion.as_deref(), Some(""));
+    }
+
+    #[test]
+    fn dfa_completion_may_use_tilde() {
+        let dfa = dfa! {
+            states: 2,
+            start: 0,
+            accept: [1],
+            alphabet: ['a', '~'],
+            transitions: {
+                0 => { 'a' => 0, '~' => 1 },
+                1 => { 'a' | '~' => 1 },
+            },
+        };
+        assert_eq!(dfa.explain("a").completion.as_deref(), Some("~"));
+    }
+
+    #[test]
+    fn nfa_reports_dying_branches() {
+        // second-to-last symbol is 1
+        let mut tfn = HashMap::new();
+        tfn.insert((0, '0'), HashSet::from([0]));
+        tfn.insert((0, '1'), HashSet::from([0, 1]));
+        tfn.insert((1, '0'), HashSet::from([2]));
+        tfn.insert((1, '1'), HashSet::from([2]));
+        let nfa = NFA::new(3, 0, HashSet::from([2]), HashSet::from(['0', '1']), tfn).unwrap();
+        let report = nfa.explain("1001");
+        assert_eq!(report.verdict, Verdict::Rejected);
+        assert_eq!(report.trace[1].to, [0, 2]);
+        assert_eq!(report.trace[2].died, [2]);
+        assert_eq!(report.final_states, [0, 1]);
+        assert_eq!(report.completion.as_deref(), Some("0"));
+        assert_eq!(report.dead_after, None);
+        let text = report.to_string();
+        assert!(text.contains("  {q0, q2} --0--> {q0}; branches in {q2} die\n"));
+    }
+}
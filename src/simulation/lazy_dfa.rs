@@ -0,0 +1,174 @@
+use std::collections::HashMap;
+
+use crate::nfa::{InputError, NFA, SimulationResult};
+use crate::simulation::bitset::{BitSet, BitsetNFA};
+
+const DEFAULT_CACHE_CAPACITY: usize = 10_000;
+
+type CachedState = usize;
+
+/// Simulates an `NFA` by building its subset-construction DFA on the fly, only for the subsets
+/// the input actually visits. Transitions are memoized, so after a warm-up every symbol costs a
+/// single table lookup. When more than `cache_capacity` subsets have been discovered the cache is
+/// flushed and rebuilt from the current subset, which bounds memory on adversarial inputs.
+pub struct LazyDFA {
+    nfa: BitsetNFA,
+    cache_capacity: usize,
+    subsets: Vec<BitSet>,
+    index: HashMap<BitSet, CachedState>,
+    accepting: Vec<bool>,
+    // transitions[q * num_symbols + a], None if not yet computed
+    transitions: Vec<Option<CachedState>>,
+    cache_clears: usize,
+}
+
+impl LazyDFA {
+    pub fn new(nfa: &NFA) -> Self {
+        Self::with_cache_capacity(nfa, DEFAULT_CACHE_CAPACITY)
+    }
+
+    pub fn with_cache_capacity(nfa: &NFA, cache_capacity: usize) -> Self {
+        let mut lazy = Self {
+            nfa: BitsetNFA::new(nfa),
+            // the start subset and the subset being stepped from must both fit
+            cache_capacity: cache_capacity.max(2),
+            subsets: Vec::new(),
+            index: HashMap::new(),
+            accepting: Vec::new(),
+            transitions: Vec::new(),
+            cache_clears: 0,
+        };
+        lazy.reset_cache();
+        lazy
+    }
+
+    /// Number of subset states currently held in the cache.
+    pub fn cached_states(&self) -> usize {
+        self.subsets.len()
+    }
+
+    /// Number of times the cache has been flushed because it hit its capacity.
+    pub fn cache_clears(&self) -> usize {
+        self.cache_clears
+    }
+
+    fn num_symbols(&self) -> usize {
+        self.nfa.symbols.len()
+    }
+
+    fn reset_cache(&mut self) {
+        self.subsets.clear();
+        self.index.clear();
+        self.accepting.clear();
+        self.transitions.clear();
+        let start = self.nfa.start.clone();
+        self.intern(start);
+    }
+
+    fn intern(&mut self, subset: BitSet) -> CachedState {
+        if let Some(&q) = self.index.get(&subset) {
+            return q;
+        }
+        let q = self.subsets.len();
+        self.accepting.push(self.nfa.is_accepting(&subset));
+        self.index.insert(subset.clone(), q);
+        self.subsets.push(subset);
+        let width = self.num_symbols();
+        self.transitions.extend(std::iter::repeat_n(None, width));
+        q
+    }
+
+    fn step(&mut self, q: CachedState, symbol: usize) -> CachedState {
+        let slot = q * self.num_symbols() + symbol;
+        if let Some(next) = self.transitions[slot] {
+            return next;
+        }
+
+        let mut next_subset = BitSet::new(self.nfa.num_states);
+        self.nfa
+            .step_into(&self.subsets[q], symbol, &mut next_subset);
+
+        if !self.index.contains_key(&next_subset) && self.subsets.len() >= self.cache_capacity {
+            // keep the cache bounded: start over with just the start subset and the target
+            self.cache_clears += 1;
+            self.reset_cache();
+            return self.intern(next_subset);
+        }
+
+        let next = self.intern(next_subset);
+        self.transitions[slot] = Some(next);
+        next
+    }
+
+    pub fn simulate(&mut self, input: &str) -> Result<SimulationResult, InputError> {
+        let symbols = input
+            .chars()
+            .map(|c| self.nfa.symbol_index(c))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut q = self.intern(self.nfa.start.clone());
+        for a in symbols {
+            q = self.step(q, a);
+        }
+        if self.accepting[q] {
+            return Ok(SimulationResult::Accepted);
+        }
+        Ok(SimulationResult::Rejected)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fsim_macros::nfa;
+
+    // the classic blow-up: strings whose 3rd-from-last symbol is 1
+    fn third_from_last_is_1() -> NFA {
+        nfa! {
+            states: 4,
+            start: 0,
+            accept: [3],
+            alphabet: ['0', '1'],
+            transitions: {
+                0 => { '0' => 0, '1' => [0, 1] },
+                1 => { '0' | '1' => 2 },
+                2 => { '0' | '1' => 3 },
+            },
+        }
+    }
+
+    #[test]
+    fn simulate_matches_nfa_simulate() {
+        let nfa = third_from_last_is_1();
+        let mut lazy = LazyDFA::new(&nfa);
+        for input in ["", "1", "100", "0100", "1011", "0001", "110110", "0000100"] {
+            let expected = matches!(nfa.simulate(input), Ok(SimulationResult::Accepted));
+            let actual = matches!(lazy.simulate(input), Ok(SimulationResult::Accepted));
+            assert_eq!(expected, actual, "input {input:?}");
+        }
+        // at most 2^3 subsets containing state 0 are reachable
+        assert!(lazy.cached_states() <= 8);
+        assert_eq!(lazy.cache_clears(), 0);
+    }
+
+    #[test]
+    fn cache_capacity_is_respected() {
+        let nfa = third_from_last_is_1();
+        let mut lazy = LazyDFA::with_cache_capacity(&nfa, 3);
+        let input = "0110100111010001".repeat(8);
+        let expected = matches!(nfa.simulate(&input), Ok(SimulationResult::Accepted));
+        let actual = matches!(lazy.simulate(&input), Ok(SimulationResult::Accepted));
+        assert_eq!(expected, actual);
+        assert!(lazy.cached_states() <= 3);
+        assert!(lazy.cache_clears() > 0);
+    }
+
+    #[test]
+    fn simulate_fails_on_invalid_input() {
+        let mut lazy = LazyDFA::new(&third_from_last_is_1());
+        assert!(matches!(
+            lazy.simulate("01x"),
+            Err(InputError::InvalidSymbol)
+        ));
+    }
+}
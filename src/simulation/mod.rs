@@ -0,0 +1,5 @@
+pub mod bitset;
+pub mod explain;
+pub mod lazy_dfa;
+pub mod search;
+pub mod stream;
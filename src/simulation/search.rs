@@ -0,0 +1,582 @@
+use std::collections::{HashSet, VecDeque};
+
+use crate::dfa::{DFA, State};
+use crate::nfa::NFA;
+use crate::simulation::bitset::{BitSet, BitsetNFA};
+
+/// A match of a machine against a substring of the haystack, as half-open ranges in both byte
+/// and char offsets.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Match {
+    pub start: usize,
+    pub end: usize,
+    pub char_start: usize,
+    pub char_end: usize,
+}
+
+impl Match {
+    pub fn as_str<'t>(&self, haystack: &'t str) -> &'t str {
+        &haystack[self.start..self.end]
+    }
+}
+
+/// Which match to report among those starting at the leftmost possible position. An automaton
+/// has no alternation priority, so there is no leftmost-first order; `LeftmostShortest` reports
+/// the first accepting position reached while scanning forward from the leftmost start.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MatchKind {
+    LeftmostLongest,
+    LeftmostShortest,
+}
+
+/// A machine run as a set of threads, one per active state. Only live states (those from which
+/// an accept state is reachable) are ever returned, which lets a scan stop once every thread has
+/// died. Symbols outside the alphabet have no successors, so a machine can scan arbitrary text.
+pub(crate) trait Scanner {
+    fn num_states(&self) -> usize;
+    /// The live states active before reading anything.
+    fn starts(&self) -> &[State];
+    /// The live states active after reading `symbol` in `state`.
+    fn successors(&self, state: State, symbol: char) -> &[State];
+    fn is_accepting(&self, state: State) -> bool;
+}
+
+/// States from which a state in `accept` is reachable, given each state's successors.
+fn live_states(num_states: usize, accept: &HashSet<State>, edges: &[Vec<State>]) -> HashSet<State> {
+    let mut reverse: Vec<Vec<State>> = vec![Vec::new(); num_states];
+    for (p, targets) in edges.iter().enumerate() {
+        for &q in targets {
+            reverse[q].push(p);
+        }
+    }
+    let mut live: HashSet<State> = accept.clone();
+    let mut work_queue: VecDeque<State> = accept.iter().copied().collect();
+    while let Some(q) = work_queue.pop_front() {
+        for &p in &reverse[q] {
+            if live.insert(p) {
+                work_queue.push_back(p);
+            }
+        }
+    }
+    live
+}
+
+/// Searches a haystack with a `DFA`.
+pub struct DFASearcher<'a> {
+    dfa: &'a DFA,
+    starts: Vec<State>,
+    live: HashSet<State>,
+}
+
+impl<'a> DFASearcher<'a> {
+    pub fn new(dfa: &'a DFA) -> Self {
+        let mut edges: Vec<Vec<State>> = vec![Vec::new(); dfa.states.len()];
+        for (&(p, _), &q) in &dfa.tfn {
+            edges[p].push(q);
+        }
+        let live = live_states(dfa.states.len(), &dfa.accept, &edges);
+        let starts = Some(dfa.start)
+            .filter(|s| live.contains(s))
+            .into_iter()
+            .collect();
+        Self { dfa, starts, live }
+    }
+}
+
+impl Scanner for DFASearcher<'_> {
+    fn num_states(&self) -> usize {
+        self.dfa.states.len()
+    }
+
+    fn starts(&self) -> &[State] {
+        &self.starts
+    }
+
+    fn successors(&self, state: State, symbol: char) -> &[State] {
+        match self.dfa.tfn.get(&(state, symbol)) {
+            Some(next) if self.live.contains(next) => std::slice::from_ref(next),
+            _ => &[],
+        }
+    }
+
+    fn is_accepting(&self, state: State) -> bool {
+        self.dfa.accept.contains(&state)
+    }
+}
+
+/// Searches a haystack with an `NFA`. Epsilon closures are computed once here, so build one
+/// searcher and reuse it rather than calling the `NFA` methods in a loop.
+pub struct NFASearcher {
+    nfa: BitsetNFA,
+    starts: Vec<State>,
+    // moves[s][a] lists the live states of the closed move from s on symbol a
+    moves: Vec<Vec<Vec<State>>>,
+}
+
+impl NFASearcher {
+    pub fn new(nfa: &NFA) -> Self {
+        let nfa = BitsetNFA::new(nfa);
+        let edges: Vec<Vec<State>> = nfa
+            .moves
+            .iter()
+            .map(|row| row.iter().flat_map(BitSet::iter).collect())
+            .collect();
+        let live = live_states(nfa.num_states, &nfa.accept.to_hash_set(), &edges);
+        let keep_live = |set: &BitSet| set.iter().filter(|s| live.contains(s)).collect();
+        let moves = nfa
+            .moves
+            .iter()
+            .map(|row| row.iter().map(keep_live).collect())
+            .collect();
+        let starts = keep_live(&nfa.start);
+        Self { nfa, starts, moves }
+    }
+}
+
+impl Scanner for NFASearcher {
+    fn num_states(&self) -> usize {
+        self.nfa.num_states
+    }
+
+    fn starts(&self) -> &[State] {
+        &self.starts
+    }
+
+    fn successors(&self, state: State, symbol: char) -> &[State] {
+        match self.nfa.symbol_index(symbol) {
+            Ok(a) => &self.moves[state][a],
+            Err(_) => &[],
+        }
+    }
+
+    fn is_accepting(&self, state: State) -> bool {
+        self.nfa.accept.contains(state)
+    }
+}
+
+struct Haystack<'t> {
+    text: &'t str,
+    chars: Vec<(usize, char)>,
+}
+
+impl<'t> Haystack<'t> {
+    fn new(text: &'t str) -> Self {
+        Self {
+            text,
+            chars: text.char_indices().collect(),
+        }
+    }
+
+    fn byte_offset(&self, char_offset: usize) -> usize {
+        self.chars
+            .get(char_offset)
+            .map_or(self.text.len(), |&(b, _)| b)
+    }
+
+    fn make_match(&self, char_start: usize, char_end: usize) -> Match {
+        Match {
+            start: self.byte_offset(char_start),
+            end: self.byte_offset(char_end),
+            char_start,
+            char_end,
+        }
+    }
+}
+
+/// The active threads: for each active state, the start (in chars) it is kept for.
+struct Threads {
+    start: Vec<Option<usize>>,
+    active: Vec<State>,
+}
+
+impl Threads {
+    fn new(num_states: usize) -> Self {
+        Self {
+            start: vec![None; num_states],
+            active: Vec::new(),
+        }
+    }
+
+    /// Adds a thread for `state`, keeping whichever start `better` prefers if one exists.
+    fn add(&mut self, state: State, from: usize, better: fn(usize, usize) -> bool) {
+        match self.start[state] {
+            None => {
+                self.start[state] = Some(from);
+                self.active.push(state);
+            }
+            Some(existing) if better(from, existing) => self.start[state] = Some(from),
+            Some(_) => {}
+        }
+    }
+
+    fn clear(&mut self) {
+        for &s in &self.active {
+            self.start[s] = None;
+        }
+        self.active.clear();
+    }
+
+    /// Drops the threads whose start fails `keep`.
+    fn retain(&mut self, keep: impl Fn(usize) -> bool) {
+        let start = &mut self.start;
+        self.active.retain(|&s| {
+            let kept = keep(start[s].expect("active states have a start"));
+            if !kept {
+                start[s] = None;
+            }
+            kept
+        });
+    }
+
+    /// Replaces the threads with their successors on `symbol`.
+    fn step<S: Scanner>(
+        &mut self,
+        scanner: &S,
+        symbol: char,
+        next: &mut Threads,
+        better: fn(usize, usize) -> bool,
+    ) {
+        next.clear();
+        for &s in &self.active {
+            let from = self.start[s].expect("active states have a start");
+            for &t in scanner.successors(s, symbol) {
+                next.add(t, from, better);
+            }
+        }
+        std::mem::swap(self, next);
+    }
+}
+
+/// The leftmost match starting at or after `from`, in a single forward pass. A thread is
+/// started at every position until a match is found, and each state keeps only its leftmost
+/// start, since any match through that state is also a match from the earlier start.
+fn find_from<S: Scanner>(
+    scanner: &S,
+    hay: &Haystack,
+    from: usize,
+    kind: MatchKind,
+    forbid_empty_at: Option<usize>,
+) -> Option<Match> {
+    let leftmost = |a: usize, b: usize| a < b;
+    let mut threads = Threads::new(scanner.num_states());
+    let mut next = Threads::new(scanner.num_states());
+    let mut best: Option<(usize, usize)> = None;
+    for i in from..=hay.chars.len() {
+        if best.is_none() {
+            for &s in scanner.starts() {
+                threads.add(s, i, leftmost);
+            }
+        }
+        for &s in &threads.active {
+            if !scanner.is_accepting(s) {
+                continue;
+            }
+            let start = threads.start[s].expect("active states have a start");
+            // an empty match directly after the previous match is skipped, but a longer one
+            // starting at the same place is still allowed
+            if start == i && forbid_empty_at == Some(i) {
+                continue;
+            }
+            best = match best {
+                Some((b, _)) if start < b => Some((start, i)),
+                Some((b, _)) if start == b && kind == MatchKind::LeftmostLongest => {
+                    Some((start, i))
+                }
+                None => Some((start, i)),
+                keep => keep,
+            };
+        }
+        if let Some((b, _)) = best {
+            // only an earlier start can still win, or the same start extending a longest match
+            let extend = kind == MatchKind::LeftmostLongest;
+            threads.retain(|st| st < b || (st == b && extend));
+            if threads.active.is_empty() {
+                break;
+            }
+        }
+        let Some(&(_, c)) = hay.chars.get(i) else {
+            break;
+        };
+        threads.step(scanner, c, &mut next, leftmost);
+    }
+    best.map(|(start, end)| hay.make_match(start, end))
+}
+
+/// The match that ends first; among matches ending there, the shortest one. Runs in a single
+/// pass, keeping for every active state the latest position it was entered from.
+fn shortest<S: Scanner>(scanner: &S, hay: &Haystack) -> Option<Match> {
+    let latest = |a: usize, b: usize| a > b;
+    let mut threads = Threads::new(scanner.num_states());
+    let mut next = Threads::new(scanner.num_states());
+    for i in 0..=hay.chars.len() {
+        for &s in scanner.starts() {
+            threads.add(s, i, latest);
+        }
+        let best = threads
+            .active
+            .iter()
+            .filter(|&&s| scanner.is_accepting(s))
+            .filter_map(|&s| threads.start[s])
+            .max();
+        if let Some(from) = best {
+            return Some(hay.make_match(from, i));
+        }
+        let Some(&(_, c)) = hay.chars.get(i) else {
+            break;
+        };
+        threads.step(scanner, c, &mut next, latest);
+    }
+    None
+}
+
+fn find_iter<'s, S: Scanner + 's>(
+    scanner: S,
+    text: &'s str,
+    kind: MatchKind,
+) -> impl Iterator<Item = Match> + 's {
+    let hay = Haystack::new(text);
+    let mut from = 0;
+    let mut last_end = None;
+    std::iter::from_fn(move || {
+        if from > hay.chars.len() {
+            return None;
+        }
+        let m = find_from(&scanner, &hay, from, kind, last_end)?;
+        from = if m.char_end == m.char_start {
+            m.char_end + 1
+        } else {
+            m.char_end
+        };
+        last_end = Some(m.char_end);
+        Some(m)
+    })
+}
+
+// Every search is one forward pass costing O(states) per char, so a haystack with no match is
+// scanned once. `find_iter` restarts after each match, and a leftmost-longest search may read
+// past the end of the match it reports, so text after a match can be scanned again.
+macro_rules! search_methods {
+    ($searcher:ty) => {
+        impl $searcher {
+            pub fn is_match_anywhere(&self, haystack: &str) -> bool {
+                self.shortest_match(haystack).is_some()
+            }
+
+            pub fn find(&self, haystack: &str, kind: MatchKind) -> Option<Match> {
+                find_from(self, &Haystack::new(haystack), 0, kind, None)
+            }
+
+            /// Successive non-overlapping matches, scanning left to right.
+            pub fn find_iter<'s>(
+                &'s self,
+                haystack: &'s str,
+                kind: MatchKind,
+            ) -> impl Iterator<Item = Match> + 's {
+                find_iter(self, haystack, kind)
+            }
+
+            pub fn shortest_match(&self, haystack: &str) -> Option<Match> {
+                shortest(self, &Haystack::new(haystack))
+            }
+        }
+    };
+}
+
+search_methods!(DFASearcher<'_>);
+search_methods!(NFASearcher);
+
+impl<S: Scanner> Scanner for &S {
+    fn num_states(&self) -> usize {
+        (*self).num_states()
+    }
+
+    fn starts(&self) -> &[State] {
+        (*self).starts()
+    }
+
+    fn successors(&self, state: State, symbol: char) -> &[State] {
+        (*self).successors(state, symbol)
+    }
+
+    fn is_accepting(&self, state: State) -> bool {
+        (*self).is_accepting(state)
+    }
+}
+
+impl DFA {
+    pub fn is_match_anywhere(&self, haystack: &str) -> bool {
+        DFASearcher::new(self).is_match_anywhere(haystack)
+    }
+
+    pub fn find(&self, haystack: &str, kind: MatchKind) -> Option<Match> {
+        DFASearcher::new(self).find(haystack, kind)
+    }
+
+    /// Successive non-overlapping matches, scanning left to right.
+    pub fn find_iter<'s>(
+        &'s self,
+        haystack: &'s str,
+        kind: MatchKind,
+    ) -> impl Iterator<Item = Match> + 's {
+        find_iter(DFASearcher::new(self), haystack, kind)
+    }
+
+    pub fn shortest_match(&self, haystack: &str) -> Option<Match> {
+        DFASearcher::new(self).shortest_match(haystack)
+    }
+}
+
+/// These compile the `NFA` on every call; use an `NFASearcher` for repeated searches.
+impl NFA {
+    pub fn is_match_anywhere(&self, haystack: &str) -> bool {
+        NFASearcher::new(self).is_match_anywhere(haystack)
+    }
+
+    pub fn find(&self, haystack: &str, kind: MatchKind) -> Option<Match> {
+        NFASearcher::new(self).find(haystack, kind)
+    }
+
+    /// Successive non-overlapping matches, scanning left to right.
+    pub fn find_iter<'s>(
+        &self,
+        haystack: &'s str,
+        kind: MatchKind,
+    ) -> impl Iterator<Item = Match> + 's {
+        find_iter(NFASearcher::new(self), haystack, kind)
+    }
+
+    pub fn shortest_match(&self, haystack: &str) -> Option<Match> {
+        NFASearcher::new(self).shortest_match(haystack)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::regex::Regex;
+    use fsim_macros::{dfa, nfa};
+
+    // a+b over {a, b}; state 2 accepts and state 3 is a dead state
+    fn a_plus_b() -> DFA {
+        dfa! {
+            states: 4,
+            start: 0,
+            accept: [2],
+            alphabet: ['a', 'b'],
+            transitions: {
+                0 => { 'a' => 1, 'b' => 3 },
+                1 => { 'a' => 1, 'b' => 2 },
+                2 => { 'a' | 'b' => 3 },
+                3 => { 'a' | 'b' => 3 },
+            },
+        }
+    }
+
+    // (ab)* over {a, b} as an NFA with no dead state
+    fn ab_star() -> NFA {
+        nfa! {
+            states: 2,
+            start: 0,
+            accept: [0],
+            alphabet: ['a', 'b'],
+            transitions: {
+                0 => { 'a' => 1 },
+                1 => { 'b' => 0 },
+            },
+        }
+    }
+
+    #[test]
+    fn find_reports_byte_and_char_offsets() {
+        let dfa = a_plus_b();
+        let hay = "ééxaab";
+        let m = dfa.find(hay, MatchKind::LeftmostLongest).unwrap();
+        assert_eq!((m.char_start, m.char_end), (3, 6));
+        assert_eq!((m.start, m.end), (5, 8));
+        assert_eq!(m.as_str(hay), "aab");
+        assert!(dfa.is_match_anywhere(hay));
+        assert!(!dfa.is_match_anywhere("aaa ba"));
+    }
+
+    #[test]
+    fn find_iter_returns_non_overlapping_matches() {
+        let dfa = a_plus_b();
+        let hay = "ab aab b aaab";
+        let found: Vec<&str> = dfa
+            .find_iter(hay, MatchKind::LeftmostLongest)
+            .map(|m| m.as_str(hay))
+            .collect();
+        assert_eq!(found, vec!["ab", "aab", "aaab"]);
+    }
+
+    #[test]
+    fn leftmost_shortest_stops_at_first_accept() {
+        let nfa = ab_star();
+        let longest = nfa.find("xabab", MatchKind::LeftmostLongest).unwrap();
+        let first = nfa.find("xabab", MatchKind::LeftmostShortest).unwrap();
+        // the empty string is accepted, so both start at 0
+        assert_eq!((longest.char_start, longest.char_end), (0, 0));
+        assert_eq!((first.char_start, first.char_end), (0, 0));
+
+        let spans: Vec<(usize, usize)> = nfa
+            .find_iter("xabab", MatchKind::LeftmostLongest)
+            .map(|m| (m.char_start, m.char_end))
+            .collect();
+        assert_eq!(spans, vec![(0, 0), (1, 5)]);
+    }
+
+    #[test]
+    fn shortest_match_ends_earliest() {
+        let dfa = a_plus_b();
+        let m = dfa.shortest_match("aaab ab").unwrap();
+        assert_eq!((m.char_start, m.char_end), (2, 4));
+        assert!(dfa.shortest_match("bbbb").is_none());
+        let nfa = ab_star();
+        assert_eq!(nfa.shortest_match("zz").unwrap().char_end, 0);
+    }
+
+    #[test]
+    fn leftmost_start_wins_over_earlier_end() {
+        let regex: Regex = "abc|b".parse().unwrap();
+        let searcher = NFASearcher::new(&regex.to_nfa().unwrap());
+        let span = |m: Option<Match>| m.map(|m| (m.char_start, m.char_end));
+        assert_eq!(
+            span(searcher.find("xabc", MatchKind::LeftmostLongest)),
+            Some((1, 4))
+        );
+        assert_eq!(
+            span(searcher.find("xabc", MatchKind::LeftmostShortest)),
+            Some((1, 4))
+        );
+        assert_eq!(span(searcher.shortest_match("xabc")), Some((2, 3)));
+    }
+
+    #[test]
+    fn find_agrees_with_brute_force() {
+        let regex: Regex = "a(b|ab)*|ba*".parse().unwrap();
+        let searcher = NFASearcher::new(&regex.to_nfa().unwrap());
+        for hay in ["", "c", "abab", "cbaaab", "aaabbab", "bcbab"] {
+            let chars: Vec<char> = hay.chars().collect();
+            let matches =
+                |i: usize, j: usize| regex.is_match(&chars[i..j].iter().collect::<String>());
+            let expected = |longest: bool| {
+                (0..=chars.len()).find_map(|i| {
+                    let mut ends = (i..=chars.len()).filter(|&j| matches(i, j));
+                    let end = if longest {
+                        ends.next_back()
+                    } else {
+                        ends.next()
+                    };
+                    end.map(|j| (i, j))
+                })
+            };
+            for (kind, longest) in [
+                (MatchKind::LeftmostLongest, true),
+                (MatchKind::LeftmostShortest, false),
+            ] {
+                let found = searcher.find(hay, kind).map(|m| (m.char_start, m.char_end));
+                assert_eq!(found, expected(longest), "{hay:?} {kind:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,373 @@
+use std::{fmt, io::Read, str::FromStr};
+
+use crate::dfa::{DFA, State};
+use crate::simulation::bitset::{BitSet, BitsetNFA};
+
+const READ_CHUNK: usize = 64 * 1024;
+
+#[derive(Debug)]
+pub enum StreamError {
+    InvalidSymbol { position: u64, symbol: char },
+    InvalidUtf8 { byte_offset: u64 },
+    InvalidConfiguration,
+    Io(std::io::Error),
+}
+
+/// A paused run: the set of active states and how many symbols have been consumed. It prints as
+/// `consumed:s0,s1,...` and parses back from the same form, so it can be stored anywhere.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Configuration {
+    pub consumed: u64,
+    pub states: Vec<State>,
+}
+
+impl fmt::Display for Configuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:", self.consumed)?;
+        let states: Vec<String> = self.states.iter().map(|s| s.to_string()).collect();
+        write!(f, "{}", states.join(","))
+    }
+}
+
+impl FromStr for Configuration {
+    type Err = StreamError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (consumed, states) = s.split_once(':').ok_or(StreamError::InvalidConfiguration)?;
+        let consumed = consumed
+            .parse()
+            .map_err(|_| StreamError::InvalidConfiguration)?;
+        let states = if states.is_empty() {
+            Vec::new()
+        } else {
+            states
+                .split(',')
+                .map(|s| s.parse().map_err(|_| StreamError::InvalidConfiguration))
+                .collect::<Result<Vec<_>, _>>()?
+        };
+        Ok(Self { consumed, states })
+    }
+}
+
+/// Incremental simulation: symbols are fed one at a time and acceptance of the prefix read so far
+/// can be queried at any point. A runner can be snapshotted, dropped and later resumed from the
+/// snapshot against the same machine.
+pub trait StreamRunner {
+    fn feed(&mut self, symbol: char) -> Result<(), StreamError>;
+
+    /// Whether the prefix consumed so far is accepted.
+    fn is_accepting(&self) -> bool;
+
+    fn consumed(&self) -> u64;
+
+    fn snapshot(&self) -> Configuration;
+
+    fn feed_chars<I>(&mut self, input: I) -> Result<(), StreamError>
+    where
+        I: IntoIterator<Item = char>,
+    {
+        input.into_iter().try_for_each(|c| self.feed(c))
+    }
+
+    /// Consumes `input` and yields the length of every accepted prefix along the way. The empty
+    /// prefix is not reported; check `is_accepting` before feeding for that.
+    fn accepting_prefixes<I>(&mut self, input: I) -> impl Iterator<Item = Result<u64, StreamError>>
+    where
+        I: IntoIterator<Item = char>,
+        Self: Sized,
+    {
+        input.into_iter().filter_map(move |c| match self.feed(c) {
+            Ok(()) if self.is_accepting() => Some(Ok(self.consumed())),
+            Ok(()) => None,
+            Err(e) => Some(Err(e)),
+        })
+    }
+
+    /// Decodes UTF-8 from `reader` in fixed-size chunks and feeds every character, so inputs far
+    /// larger than memory can be run. Multi-byte characters split across reads are handled.
+    fn feed_reader<R: Read>(&mut self, mut reader: R) -> Result<(), StreamError> {
+        let mut buf = vec![0u8; READ_CHUNK];
+        let mut pending = 0;
+        let mut byte_offset = 0u64;
+        loop {
+            let n = match reader.read(&mut buf[pending..]) {
+                Ok(n) => n,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => return Err(StreamError::Io(e)),
+            };
+            let end = pending + n;
+            if n == 0 {
+                if pending > 0 {
+                    // input ended in the middle of a character
+                    return Err(StreamError::InvalidUtf8 { byte_offset });
+                }
+                return Ok(());
+            }
+
+            let (valid, invalid) = match std::str::from_utf8(&buf[..end]) {
+                Ok(s) => (s, false),
+                Err(e) => {
+                    let valid = std::str::from_utf8(&buf[..e.valid_up_to()]).unwrap();
+                    (valid, e.error_len().is_some())
+                }
+            };
+            valid.chars().try_for_each(|c| self.feed(c))?;
+            let decoded = valid.len();
+            byte_offset += decoded as u64;
+            if invalid {
+                return Err(StreamError::InvalidUtf8 { byte_offset });
+            }
+
+            buf.copy_within(decoded..end, 0);
+            pending = end - decoded;
+        }
+    }
+}
+
+pub struct DFARunner<'a> {
+    dfa: &'a DFA,
+    state: State,
+    consumed: u64,
+}
+
+impl<'a> DFARunner<'a> {
+    pub fn new(dfa: &'a DFA) -> Self {
+        Self {
+            dfa,
+            state: dfa.start,
+            consumed: 0,
+        }
+    }
+
+    pub fn resume(dfa: &'a DFA, config: &Configuration) -> Result<Self, StreamError> {
+        match config.states[..] {
+            [state] if dfa.states.contains(&state) => Ok(Self {
+                dfa,
+                state,
+                consumed: config.consumed,
+            }),
+            _ => Err(StreamError::InvalidConfiguration),
+        }
+    }
+
+    pub fn state(&self) -> State {
+        self.state
+    }
+}
+
+impl StreamRunner for DFARunner<'_> {
+    fn feed(&mut self, symbol: char) -> Result<(), StreamError> {
+        match self.dfa.tfn.get(&(self.state, symbol)) {
+            Some(&next) => {
+                self.state = next;
+                self.consumed += 1;
+                Ok(())
+            }
+            None => Err(StreamError::InvalidSymbol {
+                position: self.consumed,
+                symbol,
+            }),
+        }
+    }
+
+    fn is_accepting(&self) -> bool {
+        self.dfa.accept.contains(&self.state)
+    }
+
+    fn consumed(&self) -> u64 {
+        self.consumed
+    }
+
+    fn snapshot(&self) -> Configuration {
+        Configuration {
+            consumed: self.consumed,
+            states: vec![self.state],
+        }
+    }
+}
+
+pub struct NFARunner<'a> {
+    nfa: &'a BitsetNFA,
+    current: BitSet,
+    next: BitSet,
+    consumed: u64,
+}
+
+impl<'a> NFARunner<'a> {
+    pub fn new(nfa: &'a BitsetNFA) -> Self {
+        Self {
+            nfa,
+            current: nfa.start.clone(),
+            next: BitSet::new(nfa.num_states),
+            consumed: 0,
+        }
+    }
+
+    /// Resumes from a snapshot. The stored states are epsilon-closed again, so a hand-written
+    /// configuration behaves like the runner that would have reached those states.
+    pub fn resume(nfa: &'a BitsetNFA, config: &Configuration) -> Result<Self, StreamError> {
+        let mut current = BitSet::new(nfa.num_states);
+        for &s in &config.states {
+            if s >= nfa.num_states {
+                return Err(StreamError::InvalidConfiguration);
+            }
+            current.union_with(&nfa.closures[s]);
+        }
+        Ok(Self {
+            nfa,
+            current,
+            next: BitSet::new(nfa.num_states),
+            consumed: config.consumed,
+        })
+    }
+
+    pub fn states(&self) -> &BitSet {
+        &self.current
+    }
+}
+
+impl StreamRunner for NFARunner<'_> {
+    fn feed(&mut self, symbol: char) -> Result<(), StreamError> {
+        let a = self
+            .nfa
+            .symbol_index(symbol)
+            .map_err(|_| StreamError::InvalidSymbol {
+                position: self.consumed,
+                symbol,
+            })?;
+        self.nfa.step_into(&self.current, a, &mut self.next);
+        std::mem::swap(&mut self.current, &mut self.next);
+        self.consumed += 1;
+        Ok(())
+    }
+
+    fn is_accepting(&self) -> bool {
+        self.nfa.is_accepting(&self.current)
+    }
+
+    fn consumed(&self) -> u64 {
+        self.consumed
+    }
+
+    fn snapshot(&self) -> Configuration {
+        Configuration {
+            consumed: self.consumed,
+            states: self.current.iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fixtures::{ends_with_11, even_length};
+    use crate::nfa::NFA;
+    use std::collections::{HashMap, HashSet};
+
+    #[test]
+    fn dfa_runner_reports_accepting_prefixes() {
+        let dfa = even_length();
+        let mut runner = DFARunner::new(&dfa);
+        let prefixes: Vec<u64> = runner
+            .accepting_prefixes("01101".chars())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(prefixes, vec![2, 4]);
+        assert!(!runner.is_accepting());
+    }
+
+    #[test]
+    fn nfa_runner_snapshot_and_resume() {
+        let nfa = BitsetNFA::new(&ends_with_11());
+        let mut runner = NFARunner::new(&nfa);
+        runner.feed_chars("0101".chars()).unwrap();
+        let saved = runner.snapshot().to_string();
+        drop(runner);
+
+        let config: Configuration = saved.parse().unwrap();
+        assert_eq!(config.consumed, 4);
+        let mut resumed = NFARunner::resume(&nfa, &config).unwrap();
+        resumed.feed('1').unwrap();
+        assert!(resumed.is_accepting());
+        assert_eq!(resumed.consumed(), 5);
+    }
+
+    #[test]
+    fn nfa_resume_closes_stored_states() {
+        // 0 --~--> 1 --a--> 2, with 1 accepting
+        let mut tfn = HashMap::new();
+        tfn.insert((0, '~'), HashSet::from([1]));
+        tfn.insert((1, 'a'), HashSet::from([2]));
+        let nfa =
+            BitsetNFA::new(&NFA::new(3, 0, HashSet::from([1]), HashSet::from(['a']), tfn).unwrap());
+        let mut resumed = NFARunner::resume(&nfa, &"0:0".parse().unwrap()).unwrap();
+        assert!(resumed.is_accepting());
+        resumed.feed('a').unwrap();
+        assert_eq!(resumed.states().iter().collect::<Vec<_>>(), [2]);
+        assert!(matches!(
+            NFARunner::resume(&nfa, &"0:3".parse().unwrap()),
+            Err(StreamError::InvalidConfiguration)
+        ));
+    }
+
+    #[test]
+    fn resume_rejects_bad_configuration() {
+        let dfa = even_length();
+        let config: Configuration = "3:5".parse().unwrap();
+        assert!(matches!(
+            DFARunner::resume(&dfa, &config),
+            Err(StreamError::InvalidConfiguration)
+        ));
+        assert!(matches!(
+            "x:1".parse::<Configuration>(),
+            Err(StreamError::InvalidConfiguration)
+        ));
+    }
+
+    #[test]
+    fn feed_reader_handles_split_characters() {
+        let mut tfn = HashMap::new();
+        tfn.insert((0, 'é'), 1);
+        tfn.insert((1, 'é'), 0);
+        let dfa = DFA::new(2, 0, HashSet::from([0]), HashSet::from(['é']), tfn).unwrap();
+
+        // a reader that returns one byte at a time splits every 'é' across two reads
+        struct ByteAtATime<'b>(&'b [u8]);
+        impl Read for ByteAtATime<'_> {
+            fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+                match self.0.split_first() {
+                    Some((&b, rest)) if !buf.is_empty() => {
+                        buf[0] = b;
+                        self.0 = rest;
+                        Ok(1)
+                    }
+                    _ => Ok(0),
+                }
+            }
+        }
+
+        let mut runner = DFARunner::new(&dfa);
+        runner.feed_reader(ByteAtATime("éééé".as_bytes())).unwrap();
+        assert_eq!(runner.consumed(), 4);
+        assert!(runner.is_accepting());
+    }
+
+    #[test]
+    fn feed_reader_reports_invalid_input() {
+        let dfa = even_length();
+        let mut runner = DFARunner::new(&dfa);
+        let err = runner.feed_reader(&b"01\xff1"[..]).unwrap_err();
+        assert!(matches!(err, StreamError::InvalidUtf8 { byte_offset: 2 }));
+
+        let mut runner = DFARunner::new(&dfa);
+        let err = runner.feed_reader(&b"012"[..]).unwrap_err();
+        assert!(matches!(
+            err,
+            StreamError::InvalidSymbol {
+                position: 2,
+                symbol: '2'
+            }
+        ));
+    }
+}